@@ -0,0 +1,42 @@
+// Runs the programs in benchmark/ on the tree-walk interpreter and prints how
+// long each one took. Pass names to run only some of them:
+//
+//     cargo bench -- fib zoo
+use std::{env, fs, path::PathBuf, time::Instant};
+
+use rustlox::RustLox;
+
+fn main() {
+    let filters: Vec<String> = env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+
+    let mut paths: Vec<PathBuf> = fs::read_dir("benchmark")
+        .expect("Couldn't read the benchmark directory.")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+        .collect();
+    paths.sort();
+
+    let mut results = Vec::new();
+    for path in paths {
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        if !filters.is_empty() && !filters.contains(&name) {
+            continue;
+        }
+
+        println!("== {name}");
+        let start = Instant::now();
+        RustLox::new()
+            .run_file(path.to_str().unwrap())
+            .expect("Couldn't run the benchmark.");
+        results.push((name, start.elapsed()));
+    }
+
+    println!();
+    println!("{:<20} {:>12}", "benchmark", "tree-walker");
+    for (name, elapsed) in results {
+        println!("{:<20} {:>11.3}s", name, elapsed.as_secs_f64());
+    }
+}
@@ -0,0 +1,43 @@
+#ifndef RUSTLOX_H
+#define RUSTLOX_H
+
+/* Generated by cbindgen from src/capi.rs, don't edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// An interpreter for C programs, with the result of the last evaluation.
+typedef struct Lox Lox;
+
+// Creates an interpreter, to be freed with `lox_free`.
+struct Lox *lox_new(void);
+
+// Runs a script in the interpreter, keeping its globals for later calls.
+// Returns 0 if it ran, with the value of its last expression statement as
+// the result, or 1 if it failed, with the errors as the result.
+//
+// # Safety
+//
+// `lox` must come from `lox_new` and `source` must be a null-terminated
+// string.
+int lox_eval(struct Lox *lox, const char *source);
+
+// The result of the last `lox_eval`, owned by the interpreter and valid
+// until the next call to `lox_eval` or `lox_free`. Empty before the first
+// evaluation.
+//
+// # Safety
+//
+// `lox` must come from `lox_new`.
+const char *lox_get_string_result(const struct Lox *lox);
+
+// Frees an interpreter. Does nothing if `lox` is null.
+//
+// # Safety
+//
+// `lox` must come from `lox_new` and not be used afterwards.
+void lox_free(struct Lox *lox);
+
+#endif  /* RUSTLOX_H */
@@ -1,108 +1,341 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt;
-use std::rc::Rc;
+use std::ops::{Index, Range};
+use std::rc::{Rc, Weak};
 
-use crate::callable::LoxCallable;
-use crate::class::LoxInstance;
+use crate::callable::{LoxCallable, LoxFunction};
+use crate::class::{LoxClass, LoxInstance};
+use crate::render::{render, Style};
 use crate::token::Token;
 
+/// An expression. Operands are `ExprId`s, looked up in the `Ast` that holds
+/// the expression.
 #[derive(Clone)]
 pub enum Expr {
     Ternary {
-        condition: Box<Expr>,
-        then_branch: Box<Expr>,
-        else_branch: Box<Expr>,
+        question: Token,
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
     },
     Binary {
-        left: Box<Expr>,
+        left: ExprId,
         operator: Token,
-        right: Box<Expr>,
+        right: ExprId,
     },
-    Grouping(Box<Expr>),
+    Grouping(ExprId),
     Literal(Object),
     Unary {
         operator: Token,
-        right: Box<Expr>,
+        right: ExprId,
     },
     Variable(Token),
     Assign {
         name: Token,
-        value: Box<Expr>,
+        value: ExprId,
     },
     Logical {
-        left: Box<Expr>,
+        left: ExprId,
         operator: Token,
-        right: Box<Expr>,
+        right: ExprId,
     },
     Call {
-        callee: Box<Expr>,
+        callee: ExprId,
         paren: Token,
-        arguments: Vec<Expr>,
+        arguments: Vec<ExprId>,
     },
     Get {
-        object: Box<Expr>,
+        object: ExprId,
         name: Token,
     },
     Set {
-        object: Box<Expr>,
+        object: ExprId,
         name: Token,
-        value: Box<Expr>,
+        value: ExprId,
     },
     Super {
         keyword: Token,
         method: Token,
     },
     This(Token),
+    List(Vec<ExprId>),
+    /// `...list`, which can only be a call argument or a list element and
+    /// stands for the elements of the list.
+    Spread {
+        ellipsis: Token,
+        list: ExprId,
+    },
+    Index {
+        object: ExprId,
+        bracket: Token,
+        index: ExprId,
+    },
+    IndexSet {
+        object: ExprId,
+        bracket: Token,
+        index: ExprId,
+        value: ExprId,
+    },
+    /// A function, named or not. Function declarations and methods hold
+    /// one too.
     Lambda {
         params: Vec<Token>,
         body: Vec<Stmt>,
     },
 }
 
+/// A statement or declaration.
 #[derive(Clone)]
 pub enum Stmt {
-    Expression(Expr),
-    Print(Expr),
+    Expression {
+        expression: ExprId,
+        /// Where the statement starts.
+        line: u32,
+    },
+    Print {
+        keyword: Token,
+        expression: ExprId,
+    },
     Var {
         name: Token,
-        initializer: Option<Expr>,
+        initializer: Option<ExprId>,
     },
     Block(Vec<Stmt>),
     If {
-        condition: Expr,
+        keyword: Token,
+        condition: ExprId,
         then_branch: Box<Stmt>,
         else_branch: Option<Box<Stmt>>,
     },
     While {
-        condition: Expr,
+        keyword: Token,
+        condition: ExprId,
         body: Box<Stmt>,
     },
     Function {
         name: Token,
-        definition: Expr,
+        definition: ExprId,
     },
     Return {
         keyword: Token,
-        value: Option<Expr>,
+        value: Option<ExprId>,
+    },
+    Delete {
+        object: ExprId,
+        name: Token,
     },
     Class {
         name: Token,
-        superclass: Option<Expr>,
+        superclass: Option<ExprId>,
+        /// `Stmt::Function`s.
         methods: Vec<Stmt>,
+        /// `Stmt::Var`s.
+        statics: Vec<Stmt>,
     },
 }
 
+/// Identifies an expression in an `Ast`, which can be indexed with it.
+// Each AST numbers its expressions starting where the previous one stopped,
+// so IDs are unique across the programs run by an interpreter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ExprId(u32);
+
+/// A parsed program, from `RustLox::parse`. Statements hold their
+/// expressions as `ExprId`s, and `ast[id]` is the expression.
+// The whole tree lives in one arena, so it's shared instead of copied when a
+// function is declared or called.
+#[derive(Default)]
+pub struct Ast {
+    first_id: u32,
+    exprs: Vec<Expr>,
+    /// The top-level statements, in order.
+    pub statements: Vec<Stmt>,
+    // The script the AST was parsed from, if it's kept, and where each
+    // function's parameters and body are in it, so snapshots can save
+    // functions as source.
+    pub(crate) source: Option<Rc<str>>,
+    function_spans: HashMap<ExprId, Range<usize>>,
+    // The method each property access or "super" expression found last, and
+    // the class it was looked up in, by the expression's position. Classes
+    // can't change once created, so the method is valid for as long as the
+    // class is the same. Kept here so it goes away with the program; both
+    // are weak since a method holds the AST it was declared in.
+    method_cache: RefCell<Vec<Option<CachedMethod>>>,
+    // What the resolver found, also kept here so it goes away with the
+    // program instead of piling up in the interpreter.
+    resolution: RefCell<Resolution>,
+}
+
+type CachedMethod = (Weak<LoxClass>, Weak<LoxFunction>);
+
+#[derive(Default)]
+struct Resolution {
+    // How many environments up each resolved local is, and its slot there.
+    locals: HashMap<ExprId, (usize, usize)>,
+    // The local scopes the resolver went through, as the scope enclosing
+    // each one and the names declared in it, and the scope where each global
+    // is used inside a block or function. They're only read to suggest a
+    // local when the global isn't defined.
+    scopes: Vec<(Option<usize>, Vec<Rc<str>>)>,
+    global_scopes: HashMap<ExprId, usize>,
+}
+
+impl Ast {
+    pub(crate) fn new(first_id: u32) -> Self {
+        Self {
+            first_id,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn add(&mut self, expr: Expr) -> ExprId {
+        self.exprs.push(expr);
+        ExprId(self.next_id() - 1)
+    }
+
+    /// Every expression, in the order they were parsed. Operands come
+    /// before the expressions using them.
+    pub fn exprs(&self) -> &[Expr] {
+        &self.exprs
+    }
+
+    pub(crate) fn add_function_span(&mut self, function: ExprId, span: Range<usize>) {
+        self.function_spans.insert(function, span);
+    }
+
+    pub(crate) fn cached_method(&self, id: ExprId, class: &Rc<LoxClass>) -> Option<LoxCallable> {
+        let cache = self.method_cache.borrow();
+        match cache.get(self.position(id)) {
+            Some(Some((cached_class, function))) if cached_class.as_ptr() == Rc::as_ptr(class) => {
+                let function = function.upgrade()?;
+                Some(LoxCallable::LoxFunction { function })
+            }
+            _ => None,
+        }
+    }
+
+    // Only methods declared in Lox are cached.
+    pub(crate) fn cache_method(&self, id: ExprId, class: &Rc<LoxClass>, method: &LoxCallable) {
+        let LoxCallable::LoxFunction { function } = method else {
+            return;
+        };
+
+        let mut cache = self.method_cache.borrow_mut();
+        if cache.is_empty() {
+            cache.resize(self.exprs.len(), None);
+        }
+        cache[self.position(id)] = Some((Rc::downgrade(class), Rc::downgrade(function)));
+    }
+
+    pub(crate) fn resolve(&self, expr: ExprId, depth: usize, slot: usize) {
+        self.resolution
+            .borrow_mut()
+            .locals
+            .insert(expr, (depth, slot));
+    }
+
+    // How many environments up a resolved local is, and its slot there.
+    pub(crate) fn local(&self, expr: ExprId) -> Option<(usize, usize)> {
+        self.resolution.borrow().locals.get(&expr).copied()
+    }
+
+    pub(crate) fn resolved_locals(&self) -> usize {
+        self.resolution.borrow().locals.len()
+    }
+
+    pub(crate) fn add_scope(&self, enclosing: Option<usize>) -> usize {
+        let scopes = &mut self.resolution.borrow_mut().scopes;
+        scopes.push((enclosing, Vec::new()));
+        scopes.len() - 1
+    }
+
+    pub(crate) fn add_scope_name(&self, scope: usize, name: Rc<str>) {
+        self.resolution.borrow_mut().scopes[scope].1.push(name);
+    }
+
+    pub(crate) fn resolve_global(&self, expr: ExprId, scope: usize) {
+        self.resolution
+            .borrow_mut()
+            .global_scopes
+            .insert(expr, scope);
+    }
+
+    // The locals visible where a global is used, innermost first.
+    pub(crate) fn locals_in_scope(&self, global: ExprId) -> Vec<Rc<str>> {
+        let resolution = self.resolution.borrow();
+        let mut names = Vec::new();
+        let mut scope = resolution.global_scopes.get(&global).copied();
+        while let Some(id) = scope {
+            let (enclosing, scope_names) = &resolution.scopes[id];
+            names.extend(scope_names.iter().cloned());
+            scope = *enclosing;
+        }
+
+        names
+    }
+
+    fn position(&self, id: ExprId) -> usize {
+        (id.0 - self.first_id) as usize
+    }
+
+    /// A function's parameters and body as they were written, starting at
+    /// the opening parenthesis. `function` is an `Expr::Lambda`.
+    pub fn function_source(&self, function: ExprId) -> Option<&str> {
+        let span = self.function_spans.get(&function)?;
+        self.source.as_deref()?.get(span.clone())
+    }
+
+    // The ID the next AST should start at.
+    pub(crate) fn next_id(&self) -> u32 {
+        self.first_id + self.exprs.len() as u32
+    }
+}
+
+impl Index<ExprId> for Ast {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[self.position(id)]
+    }
+}
+
 #[derive(Clone)]
 pub enum Object {
-    String(String),
+    // Shared, so copying a string value doesn't copy its contents.
+    String(Rc<str>),
     Number(f64),
     Boolean(bool),
     Nil,
     Callable(LoxCallable),
     Instance(Rc<RefCell<LoxInstance>>),
+    List(Rc<RefCell<Vec<Object>>>),
 }
 
+// The lists copied so far by a deep copy, and their copies.
+pub(crate) type ListCopies = HashMap<*const RefCell<Vec<Object>>, Rc<RefCell<Vec<Object>>>>;
+
+// Values are copied all the time, so they're kept to a tag and a pointer or
+// number. The largest payloads are strings, whose pointer also holds a length.
+const _: () = assert!(std::mem::size_of::<Object>() <= 24);
+
 impl Object {
+    // For instances, this is the name of their class.
+    pub fn type_name(&self) -> String {
+        let name = match self {
+            Object::String(_) => "string",
+            Object::Number(_) => "number",
+            Object::Boolean(_) => "boolean",
+            Object::Nil => "nil",
+            Object::Callable(LoxCallable::LoxClass { .. }) => "class",
+            Object::Callable(_) => "function",
+            Object::List(_) => "list",
+            Object::Instance(instance) => return instance.borrow().class_name().to_owned(),
+        };
+
+        String::from(name)
+    }
+
     pub fn equals(&self, other: &Object) -> bool {
         match (self, other) {
             (Object::Boolean(lhs), Object::Boolean(rhs)) => lhs == rhs,
@@ -110,25 +343,150 @@ impl Object {
             (Object::String(lhs), Object::String(rhs)) => lhs == rhs,
             (Object::Nil, Object::Nil) => true,
             (Object::Callable(lhs), Object::Callable(rhs)) => lhs.equals(rhs),
+            (Object::Instance(lhs), Object::Instance(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Object::List(lhs), Object::List(rhs)) => Rc::ptr_eq(lhs, rhs),
             _ => false,
         }
     }
+
+    /// A copy of the value that shares no lists with it. Lists nested in it
+    /// are copied too, once each, so a list that contains itself is copied
+    /// into one that contains the copy. Instances are still shared.
+    pub fn deep_copy(&self) -> Object {
+        self.deep_copy_with(&mut ListCopies::new())
+    }
+
+    // Copies several values with the same `copies`, so lists they share are
+    // shared by their copies too.
+    pub(crate) fn deep_copy_with(&self, copies: &mut ListCopies) -> Object {
+        let Object::List(list) = self else {
+            return self.clone();
+        };
+
+        if let Some(copy) = copies.get(&Rc::as_ptr(list)) {
+            return Object::List(copy.clone());
+        }
+
+        let copy = Rc::new(RefCell::new(Vec::with_capacity(list.borrow().len())));
+        copies.insert(Rc::as_ptr(list), copy.clone());
+        for element in list.borrow().iter() {
+            let element = element.deep_copy_with(copies);
+            copy.borrow_mut().push(element);
+        }
+
+        Object::List(copy)
+    }
 }
 
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Object::String(val) => write!(f, "{}", val.to_string()),
-            Object::Number(val) => write!(f, "{}", val.to_string()),
-            Object::Boolean(val) => write!(f, "{}", val.to_string()),
-            Object::Nil => write!(f, "nil"),
-            Object::Callable(val) => write!(f, "{}", val.to_string()),
-            Object::Instance(val) => write!(f, "{}", val.borrow().to_string()),
+        write!(f, "{}", render(self, Style::Plain))
+    }
+}
+
+impl From<f64> for Object {
+    fn from(value: f64) -> Self {
+        Object::Number(value)
+    }
+}
+
+impl From<bool> for Object {
+    fn from(value: bool) -> Self {
+        Object::Boolean(value)
+    }
+}
+
+impl From<&str> for Object {
+    fn from(value: &str) -> Self {
+        Object::String(value.into())
+    }
+}
+
+impl From<String> for Object {
+    fn from(value: String) -> Self {
+        Object::String(value.into())
+    }
+}
+
+impl From<Vec<Object>> for Object {
+    fn from(elements: Vec<Object>) -> Self {
+        Object::List(Rc::new(RefCell::new(elements)))
+    }
+}
+
+/// A value that isn't of the Rust type it was converted to.
+#[derive(Debug)]
+pub struct ConversionError {
+    expected: &'static str,
+    found: String,
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Expected a {}; got {}.", self.expected, self.found)
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+impl ConversionError {
+    fn new(expected: &'static str, found: &Object) -> Self {
+        ConversionError {
+            expected,
+            found: found.type_name(),
+        }
+    }
+}
+
+impl TryFrom<Object> for f64 {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Number(value) => Ok(value),
+            _ => Err(ConversionError::new("number", &value)),
+        }
+    }
+}
+
+impl TryFrom<Object> for bool {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Boolean(value) => Ok(value),
+            _ => Err(ConversionError::new("boolean", &value)),
+        }
+    }
+}
+
+impl TryFrom<Object> for String {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::String(value) => Ok(value.to_string()),
+            _ => Err(ConversionError::new("string", &value)),
+        }
+    }
+}
+
+// The elements are copied, so changing the vector doesn't change the list.
+impl TryFrom<Object> for Vec<Object> {
+    type Error = ConversionError;
+
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::List(elements) => Ok(elements.borrow().clone()),
+            _ => Err(ConversionError::new("list", &value)),
         }
     }
 }
 
+/// Walks a syntax tree, producing a `T` for each expression and a `U` for
+/// each statement. Implementations decide whether and how to visit
+/// children, usually holding the `Ast` to look expressions up in.
 pub trait AstVisitor<T, U> {
-    fn visit_expr(&mut self, expr: &Expr) -> T;
+    fn visit_expr(&mut self, expr: ExprId) -> T;
     fn visit_stmt(&mut self, stmt: &Stmt) -> U;
 }
@@ -0,0 +1,175 @@
+use crate::ast::{Ast, AstVisitor, Expr, ExprId, Object, Stmt};
+use crate::token::Token;
+
+// Prints the AST as parenthesized prefix expressions, one top level statement
+// per line.
+pub struct AstPrinter<'a> {
+    ast: &'a Ast,
+}
+
+impl<'a> AstPrinter<'a> {
+    pub fn print(ast: &Ast) -> String {
+        let mut printer = AstPrinter { ast };
+        ast.statements
+            .iter()
+            .map(|stmt| printer.visit_stmt(stmt) + "\n")
+            .collect()
+    }
+
+    fn parenthesize(&mut self, name: &str, exprs: &[ExprId]) -> String {
+        let mut result = format!("({name}");
+        for expr in exprs {
+            result.push(' ');
+            result.push_str(&self.visit_expr(*expr));
+        }
+        result.push(')');
+        result
+    }
+
+    fn statements(&mut self, name: &str, statements: &[Stmt]) -> String {
+        let mut result = format!("({name}");
+        for stmt in statements {
+            result.push(' ');
+            result.push_str(&self.visit_stmt(stmt));
+        }
+        result.push(')');
+        result
+    }
+
+    fn lambda(&mut self, name: &str, params: &[Token], body: &[Stmt]) -> String {
+        let params: Vec<&str> = params.iter().map(|param| param.lexeme.as_ref()).collect();
+        self.statements(&format!("{name} ({})", params.join(" ")), body)
+    }
+}
+
+impl<'a> AstVisitor<String, String> for AstPrinter<'a> {
+    fn visit_expr(&mut self, expr: ExprId) -> String {
+        match &self.ast[expr] {
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => self.parenthesize("?:", &[*condition, *then_branch, *else_branch]),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            }
+            | Expr::Logical {
+                left,
+                operator,
+                right,
+            } => self.parenthesize(&operator.lexeme, &[*left, *right]),
+            Expr::Grouping(expr) => self.parenthesize("group", &[*expr]),
+            Expr::Literal(Object::String(value)) => format!("{value:?}"),
+            Expr::Literal(value) => value.to_string(),
+            Expr::Unary { operator, right } => self.parenthesize(&operator.lexeme, &[*right]),
+            Expr::Variable(name) => name.lexeme.to_string(),
+            Expr::Assign { name, value } => {
+                self.parenthesize(&format!("= {}", name.lexeme), &[*value])
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                let mut exprs = vec![*callee];
+                exprs.extend(arguments);
+                self.parenthesize("call", &exprs)
+            }
+            Expr::Get { object, name } => {
+                self.parenthesize(&format!(". {}", name.lexeme), &[*object])
+            }
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => self.parenthesize(&format!(".= {}", name.lexeme), &[*object, *value]),
+            Expr::Super { method, .. } => format!("(super {})", method.lexeme),
+            Expr::This(_) => String::from("this"),
+            Expr::List(elements) => self.parenthesize("list", elements),
+            Expr::Spread { list, .. } => self.parenthesize("...", &[*list]),
+            Expr::Index { object, index, .. } => self.parenthesize("[]", &[*object, *index]),
+            Expr::IndexSet {
+                object,
+                index,
+                value,
+                ..
+            } => self.parenthesize("[]=", &[*object, *index, *value]),
+            Expr::Lambda { params, body } => self.lambda("fun", params, body),
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Expression {
+                expression: expr, ..
+            } => self.parenthesize(";", &[*expr]),
+            Stmt::Print { expression, .. } => self.parenthesize("print", &[*expression]),
+            Stmt::Var { name, initializer } => match initializer {
+                Some(initializer) => {
+                    self.parenthesize(&format!("var {}", name.lexeme), &[*initializer])
+                }
+                None => format!("(var {})", name.lexeme),
+            },
+            Stmt::Block(statements) => self.statements("block", statements),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                let mut result = format!(
+                    "(if {} {}",
+                    self.visit_expr(*condition),
+                    self.visit_stmt(then_branch)
+                );
+                if let Some(else_branch) = else_branch {
+                    result.push(' ');
+                    result.push_str(&self.visit_stmt(else_branch));
+                }
+                result.push(')');
+                result
+            }
+            Stmt::While {
+                condition, body, ..
+            } => format!(
+                "(while {} {})",
+                self.visit_expr(*condition),
+                self.visit_stmt(body)
+            ),
+            Stmt::Function { name, definition } => match &self.ast[*definition] {
+                Expr::Lambda { params, body } => {
+                    self.lambda(&format!("fun {}", name.lexeme), params, body)
+                }
+                _ => unreachable!(),
+            },
+            Stmt::Return { value, .. } => match value {
+                Some(value) => self.parenthesize("return", &[*value]),
+                None => String::from("(return)"),
+            },
+            Stmt::Delete { object, name } => {
+                self.parenthesize(&format!("delete {}", name.lexeme), &[*object])
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                statics,
+            } => {
+                let mut result = format!("(class {}", name.lexeme);
+                if let Some(superclass) = superclass {
+                    result.push_str(&format!(" < {}", self.visit_expr(*superclass)));
+                }
+                for stmt in statics {
+                    result.push_str(&format!(" (static {})", self.visit_stmt(stmt)));
+                }
+                for method in methods {
+                    result.push(' ');
+                    result.push_str(&self.visit_stmt(method));
+                }
+                result.push(')');
+                result
+            }
+        }
+    }
+}
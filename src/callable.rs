@@ -2,81 +2,74 @@ use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
 
-use crate::ast::{Expr, Object};
+use crate::ast::{Ast, Expr, ExprId, Object};
 use crate::class::{LoxClass, LoxInstance};
 use crate::environment::Environment;
 use crate::error::Error;
 use crate::interpreter::Interpreter;
+use crate::natives::{HostNative, NativeContext};
 use crate::token::Token;
 
+// Every variant is a single pointer, so copying a callable, and the `Object`
+// holding it, is cheap.
 #[derive(Clone)]
 pub enum LoxCallable {
-    LoxNative {
-        call_impl: fn(&Vec<Object>) -> Object,
-        arity: usize,
-    },
-    LoxFunction {
-        name: Option<Token>,
-        definition: Box<Expr>,
-        closure: Rc<RefCell<Environment>>,
-        is_initializer: bool,
-    },
-    LoxClass {
-        class: Rc<LoxClass>,
-    },
+    LoxNative { native: Rc<LoxNative> },
+    LoxFunction { function: Rc<LoxFunction> },
+    LoxClass { class: Rc<LoxClass> },
+}
+
+pub struct LoxNative {
+    pub name: Rc<str>,
+    pub call_impl: NativeImpl,
+    pub arity: usize,
+    // Variadic natives take `arity` or more arguments.
+    pub variadic: bool,
+}
+
+pub enum NativeImpl {
+    Builtin(fn(&mut Interpreter, &Token, &[Object]) -> Result<Object, Error>),
+    // Defined by the program embedding rustlox.
+    Host(HostNative),
+}
+
+pub struct LoxFunction {
+    pub name: Option<Token>,
+    pub ast: Rc<Ast>,
+    pub definition: ExprId,
+    pub closure: Rc<RefCell<Environment>>,
+    pub is_initializer: bool,
+    // The instance a bound method was accessed on.
+    pub this: Option<Object>,
 }
 
 impl LoxCallable {
     pub fn call(
         &self,
         interpreter: &mut Interpreter,
+        paren: &Token,
         arguments: &Vec<Object>,
     ) -> Result<Object, Error> {
         match self {
-            LoxCallable::LoxNative { call_impl, .. } => Ok((call_impl)(arguments)),
-            LoxCallable::LoxFunction {
-                definition,
-                closure,
-                is_initializer,
-                ..
-            } => match definition.as_ref() {
-                Expr::Lambda { params, body } => {
-                    let environment =
-                        Rc::new(RefCell::new(Environment::new_local(closure.clone())));
-
-                    for (param, argument) in params.iter().zip(arguments) {
-                        environment
-                            .borrow_mut()
-                            .define(param.lexeme.clone(), argument.clone())
-                    }
-
-                    match interpreter.execute_block(body, environment) {
-                        Ok(_) => {
-                            if *is_initializer {
-                                closure.borrow().get_at(0, "this")
-                            } else {
-                                Ok(Object::Nil)
-                            }
-                        }
-                        Err(Error::Return(value)) => {
-                            if *is_initializer {
-                                closure.borrow().get_at(0, "this")
-                            } else {
-                                Ok(value)
-                            }
-                        }
-                        Err(e) => Err(e),
-                    }
+            LoxCallable::LoxNative { native } => match &native.call_impl {
+                NativeImpl::Builtin(call_impl) => call_impl(interpreter, paren, arguments),
+                NativeImpl::Host(call_impl) => {
+                    let mut context = NativeContext { interpreter, paren };
+                    call_impl(&mut context, arguments).map_err(|error| error.at(paren))
                 }
-                _ => unreachable!(),
             },
+            LoxCallable::LoxFunction { function } => {
+                function.call(interpreter, function.this.clone(), arguments)
+            }
             LoxCallable::LoxClass { class } => {
                 let instance = Rc::new(RefCell::new(LoxInstance::new(class.clone())));
 
-                if let Some(initializer) = class.find_method(&String::from("init")) {
-                    initializer
-                        .bind(Object::Instance(instance.clone()))
-                        .call(interpreter, arguments)?;
+                if let Some(initializer) = class.find_method("init") {
+                    initializer.call_method(
+                        interpreter,
+                        Object::Instance(instance.clone()),
+                        arguments,
+                    )?;
                 }
 
                 Ok(Object::Instance(instance))
@@ -84,15 +77,30 @@ impl LoxCallable {
         }
     }
 
+    // Calls a method with `this` set to `instance`, without binding it first.
+    pub fn call_method(
+        &self,
+        interpreter: &mut Interpreter,
+        instance: Object,
+        arguments: &Vec<Object>,
+    ) -> Result<Object, Error> {
+        match self {
+            LoxCallable::LoxFunction { function } => {
+                function.call(interpreter, Some(instance), arguments)
+            }
+            _ => unreachable!(),
+        }
+    }
+
     pub fn arity(&self) -> usize {
         match self {
-            LoxCallable::LoxNative { arity, .. } => *arity,
-            LoxCallable::LoxFunction { definition, .. } => match definition.as_ref() {
+            LoxCallable::LoxNative { native } => native.arity,
+            LoxCallable::LoxFunction { function } => match &function.ast[function.definition] {
                 Expr::Lambda { params, .. } => params.len(),
                 _ => unreachable!(),
             },
             LoxCallable::LoxClass { class } => {
-                if let Some(initializer) = class.find_method(&String::from("init")) {
+                if let Some(initializer) = class.find_method("init") {
                     initializer.arity()
                 } else {
                     0
@@ -101,23 +109,22 @@ impl LoxCallable {
         }
     }
 
+    pub fn is_variadic(&self) -> bool {
+        matches!(self, LoxCallable::LoxNative { native } if native.variadic)
+    }
+
     pub fn bind(&self, instance: Object) -> LoxCallable {
         match self {
-            LoxCallable::LoxFunction {
-                name,
-                definition,
-                closure,
-                is_initializer,
-            } => {
-                let mut env = Environment::new_local(closure.clone());
-                env.define(String::from("this"), instance);
-                LoxCallable::LoxFunction {
-                    name: name.to_owned(),
-                    definition: definition.to_owned(),
-                    closure: Rc::new(RefCell::new(env)),
-                    is_initializer: is_initializer.to_owned(),
-                }
-            }
+            LoxCallable::LoxFunction { function } => LoxCallable::LoxFunction {
+                function: Rc::new(LoxFunction {
+                    name: function.name.to_owned(),
+                    ast: function.ast.clone(),
+                    definition: function.definition,
+                    closure: function.closure.clone(),
+                    is_initializer: function.is_initializer,
+                    this: Some(instance),
+                }),
+            },
             _ => unreachable!(),
         }
     }
@@ -134,11 +141,56 @@ impl LoxCallable {
     }
 }
 
+impl LoxFunction {
+    // Methods get `this` in the first slot of their environment, before the
+    // parameters.
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        this: Option<Object>,
+        arguments: &Vec<Object>,
+    ) -> Result<Object, Error> {
+        match &self.ast[self.definition] {
+            Expr::Lambda { params, body } => {
+                let environment = interpreter.new_environment(self.closure.clone());
+
+                if let Some(this) = &this {
+                    environment.borrow_mut().define_slot(this.clone());
+                }
+
+                for (param, argument) in params.iter().zip(arguments) {
+                    let argument = interpreter.pass_argument(argument);
+                    environment
+                        .borrow_mut()
+                        .define(param.lexeme.clone(), argument)
+                }
+
+                let enclosing = interpreter.set_ast(self.ast.clone());
+                let result = interpreter.execute_block(body, environment.clone());
+                interpreter.set_ast(enclosing);
+                interpreter.recycle_environment(environment);
+
+                match result {
+                    Ok(_) | Err(Error::Return(_)) if self.is_initializer => {
+                        // Can safely unwrap because initializers are always
+                        // called on an instance.
+                        Ok(this.unwrap())
+                    }
+                    Ok(_) => Ok(Object::Nil),
+                    Err(Error::Return(value)) => Ok(value),
+                    Err(e) => Err(e),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
 impl fmt::Display for LoxCallable {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             LoxCallable::LoxNative { .. } => write!(f, "<native fn>"),
-            LoxCallable::LoxFunction { name, .. } => match name {
+            LoxCallable::LoxFunction { function } => match &function.name {
                 Some(func_name) => write!(f, "<fn {}>", func_name.lexeme),
                 None => write!(f, "<fn>"),
             },
@@ -0,0 +1,85 @@
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+
+use crate::RustLox;
+
+/// An interpreter for C programs, with the result of the last evaluation.
+pub struct Lox {
+    lox: RustLox,
+    result: CString,
+}
+
+/// Creates an interpreter, to be freed with `lox_free`.
+#[no_mangle]
+pub extern "C" fn lox_new() -> *mut Lox {
+    Box::into_raw(Box::new(Lox {
+        lox: RustLox::new(),
+        result: CString::default(),
+    }))
+}
+
+/// Runs a script in the interpreter, keeping its globals for later calls.
+/// Returns 0 if it ran, with the value of its last expression statement as
+/// the result, or 1 if it failed, with the errors as the result.
+///
+/// # Safety
+///
+/// `lox` must come from `lox_new` and `source` must be a null-terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn lox_eval(lox: *mut Lox, source: *const c_char) -> c_int {
+    let Some(lox) = lox.as_mut() else {
+        return 1;
+    };
+    if source.is_null() {
+        lox.result = text("The source is null.");
+        return 1;
+    }
+
+    let (status, result) = match CStr::from_ptr(source).to_str() {
+        Ok(source) => match lox.lox.eval(source) {
+            Ok(value) => (0, value.to_string()),
+            Err(diagnostics) => {
+                let errors: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+                (1, errors.join("\n"))
+            }
+        },
+        Err(_) => (1, String::from("The source isn't valid UTF-8.")),
+    };
+
+    lox.result = text(&result);
+    status
+}
+
+/// The result of the last `lox_eval`, owned by the interpreter and valid
+/// until the next call to `lox_eval` or `lox_free`. Empty before the first
+/// evaluation.
+///
+/// # Safety
+///
+/// `lox` must come from `lox_new`.
+#[no_mangle]
+pub unsafe extern "C" fn lox_get_string_result(lox: *const Lox) -> *const c_char {
+    match lox.as_ref() {
+        Some(lox) => lox.result.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Frees an interpreter. Does nothing if `lox` is null.
+///
+/// # Safety
+///
+/// `lox` must come from `lox_new` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn lox_free(lox: *mut Lox) {
+    if !lox.is_null() {
+        drop(Box::from_raw(lox));
+    }
+}
+
+// C strings end at the first null character, so the text does too.
+fn text(text: &str) -> CString {
+    let end = text.find('\0').unwrap_or(text.len());
+    CString::new(&text[..end]).unwrap()
+}
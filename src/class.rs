@@ -6,35 +6,83 @@ use std::rc::Rc;
 use crate::ast::Object;
 use crate::callable::LoxCallable;
 use crate::error::Error;
+use crate::error_code::ErrorCode;
 use crate::token::Token;
 
 #[derive(Clone)]
 pub struct LoxClass {
     pub name: String,
     superclass: Option<Rc<LoxClass>>,
-    methods: HashMap<String, LoxCallable>,
+    methods: HashMap<Rc<str>, LoxCallable>,
+    statics: RefCell<HashMap<Rc<str>, Object>>,
 }
 
 #[derive(Clone)]
 pub struct LoxInstance {
     class: Rc<LoxClass>,
-    fields: HashMap<String, Object>,
+    fields: HashMap<Rc<str>, Object>,
 }
 
 impl LoxClass {
     pub fn new(
         name: String,
         superclass: Option<Rc<LoxClass>>,
-        methods: HashMap<String, LoxCallable>,
+        methods: HashMap<Rc<str>, LoxCallable>,
+        statics: HashMap<Rc<str>, Object>,
     ) -> Self {
         Self {
             name,
             superclass,
             methods,
+            statics: RefCell::new(statics),
         }
     }
 
-    pub fn find_method(&self, name: &String) -> Option<&LoxCallable> {
+    // Static fields are inherited, but assigning one through a subclass
+    // gives the subclass its own copy.
+    pub fn get(&self, name: &Token) -> Result<Object, Error> {
+        if let Some(value) = self.statics.borrow().get(&name.lexeme) {
+            Ok(value.to_owned())
+        } else if let Some(class) = &self.superclass {
+            class.get(name)
+        } else {
+            Err(Error::Runtime {
+                token: name.to_owned(),
+                code: ErrorCode::UndefinedProperty,
+                message: format!("Undefined property '{}'.", name.lexeme),
+            })
+        }
+    }
+
+    pub fn set(&self, name: Rc<str>, value: Object) {
+        self.statics.borrow_mut().insert(name, value);
+    }
+
+    pub fn superclass(&self) -> Option<&Rc<LoxClass>> {
+        self.superclass.as_ref()
+    }
+
+    // The methods declared in the class itself, without inherited ones.
+    pub fn methods(&self) -> impl Iterator<Item = (&Rc<str>, &LoxCallable)> {
+        self.methods.iter()
+    }
+
+    // A copy of the class's own static fields.
+    pub fn statics(&self) -> HashMap<Rc<str>, Object> {
+        self.statics.borrow().clone()
+    }
+
+    pub fn inherits_from(&self, class: &LoxClass) -> bool {
+        if std::ptr::eq(self, class) {
+            true
+        } else if let Some(superclass) = &self.superclass {
+            superclass.inherits_from(class)
+        } else {
+            false
+        }
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<&LoxCallable> {
         if self.methods.contains_key(name) {
             self.methods.get(name)
         } else if let Some(class) = &self.superclass {
@@ -53,24 +101,43 @@ impl LoxInstance {
         }
     }
 
-    pub fn get(&self, name: &Token, instance: &Rc<RefCell<LoxInstance>>) -> Result<Object, Error> {
-        if let Some(field) = self.fields.get(&name.lexeme) {
-            Ok(field.to_owned())
-        } else if let Some(method) = self.class.find_method(&name.lexeme) {
-            Ok(Object::Callable(
-                method.bind(Object::Instance(instance.clone())),
-            ))
-        } else {
-            Err(Error::Runtime {
-                token: name.to_owned(),
-                message: format!("Undefined property '{}'.", name.lexeme),
-            })
-        }
+    pub fn class_name(&self) -> &str {
+        &self.class.name
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = (&Rc<str>, &Object)> {
+        self.fields.iter()
+    }
+
+    pub fn method(&self, name: &str) -> Option<LoxCallable> {
+        self.class.find_method(name).cloned()
     }
 
-    pub fn set(&mut self, name: String, value: Object) {
+    pub fn is_instance_of(&self, class: &LoxClass) -> bool {
+        self.class.inherits_from(class)
+    }
+
+    pub fn class(&self) -> &Rc<LoxClass> {
+        &self.class
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Object> {
+        self.fields.get(name)
+    }
+
+    pub fn set(&mut self, name: Rc<str>, value: Object) {
         self.fields.insert(name, value);
     }
+
+    pub fn remove(&mut self, name: &Token) -> Result<Object, Error> {
+        self.fields
+            .remove(&name.lexeme)
+            .ok_or_else(|| Error::Runtime {
+                token: name.to_owned(),
+                code: ErrorCode::UndefinedField,
+                message: format!("Undefined field '{}'.", name.lexeme),
+            })
+    }
 }
 
 impl fmt::Display for LoxClass {
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::ast::{Ast, Expr, Stmt};
+
+/// The format of a coverage report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoverageFormat {
+    /// The source annotated with how many times each line ran, like gcov.
+    Text,
+    /// An lcov tracefile, which coverage tools such as genhtml read.
+    Lcov,
+}
+
+// How many times the statements starting on each line ran. Lines without
+// statements, like comments or a lone "}", aren't in the map.
+#[derive(Default)]
+pub struct Coverage {
+    hits: BTreeMap<u32, u64>,
+}
+
+impl Coverage {
+    // Adds the lines of every statement in `ast`, including those in
+    // function bodies, so statements that never run show up in the report.
+    pub fn add_program(&mut self, ast: &Ast) {
+        for statement in &ast.statements {
+            self.add_statement(statement);
+        }
+
+        for expr in ast.exprs() {
+            if let Expr::Lambda { body, .. } = expr {
+                for statement in body {
+                    self.add_statement(statement);
+                }
+            }
+        }
+    }
+
+    fn add_statement(&mut self, statement: &Stmt) {
+        if let Some(line) = line(statement) {
+            self.hits.entry(line).or_insert(0);
+        }
+
+        match statement {
+            Stmt::Block(statements) => {
+                for statement in statements {
+                    self.add_statement(statement);
+                }
+            }
+            Stmt::If {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.add_statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.add_statement(else_branch);
+                }
+            }
+            Stmt::While { body, .. } => self.add_statement(body),
+            _ => (),
+        }
+    }
+
+    pub fn record(&mut self, statement: &Stmt) {
+        if let Some(line) = line(statement) {
+            *self.hits.entry(line).or_insert(0) += 1;
+        }
+    }
+
+    pub fn write(
+        &self,
+        format: CoverageFormat,
+        file_path: &str,
+        source: &str,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        match format {
+            CoverageFormat::Text => self.write_text(source, out),
+            CoverageFormat::Lcov => self.write_lcov(file_path, out),
+        }
+    }
+
+    // "-" marks lines without statements, and "#####" the ones whose
+    // statements never ran.
+    fn write_text(&self, source: &str, out: &mut impl Write) -> io::Result<()> {
+        for (index, text) in source.lines().enumerate() {
+            let line = index as u32 + 1;
+            let count = match self.hits.get(&line) {
+                None => String::from("-"),
+                Some(0) => String::from("#####"),
+                Some(count) => count.to_string(),
+            };
+
+            writeln!(out, "{count:>9}:{line:>5}:{text}")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_lcov(&self, file_path: &str, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "TN:")?;
+        writeln!(out, "SF:{file_path}")?;
+        for (line, count) in &self.hits {
+            writeln!(out, "DA:{line},{count}")?;
+        }
+
+        let hit = self.hits.values().filter(|count| **count > 0).count();
+        writeln!(out, "LF:{}", self.hits.len())?;
+        writeln!(out, "LH:{hit}")?;
+        writeln!(out, "end_of_record")
+    }
+}
+
+// Blocks don't have a line of their own, the statements in them do.
+// Methods and static fields are declared by their class' statement.
+fn line(statement: &Stmt) -> Option<u32> {
+    let line = match statement {
+        Stmt::Expression { line, .. } => *line,
+        Stmt::Print { keyword, .. }
+        | Stmt::If { keyword, .. }
+        | Stmt::While { keyword, .. }
+        | Stmt::Return { keyword, .. } => keyword.line,
+        Stmt::Var { name, .. }
+        | Stmt::Function { name, .. }
+        | Stmt::Class { name, .. }
+        | Stmt::Delete { name, .. } => name.line,
+        Stmt::Block(_) => return None,
+    };
+
+    Some(line)
+}
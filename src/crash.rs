@@ -0,0 +1,89 @@
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::Once;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs, io, process};
+
+use crate::ast::Ast;
+use crate::ast_printer::AstPrinter;
+use crate::token::Token;
+
+thread_local! {
+    // Whether panics on this thread are caught by `catch`, which reports them
+    // itself instead of letting the default hook print them.
+    static CATCHING: Cell<bool> = const { Cell::new(false) };
+    static PANIC_MESSAGE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static INSTALL_HOOK: Once = Once::new();
+
+// What was known about the run when it crashed.
+pub struct Report<'a> {
+    pub message: String,
+    pub source: &'a str,
+    pub tokens: Option<&'a [Token]>,
+    pub ast: Option<&'a Ast>,
+    pub state: String,
+}
+
+// Runs `f`, returning the panic message if it panics.
+pub fn catch<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    INSTALL_HOOK.call_once(|| {
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if CATCHING.with(Cell::get) {
+                PANIC_MESSAGE.with(|message| *message.borrow_mut() = Some(info.to_string()));
+            } else {
+                default_hook(info);
+            }
+        }));
+    });
+
+    let was_catching = CATCHING.with(|catching| catching.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.with(|catching| catching.set(was_catching));
+
+    result.map_err(|_| {
+        PANIC_MESSAGE
+            .with(|message| message.borrow_mut().take())
+            .unwrap_or_else(|| String::from("Unknown panic."))
+    })
+}
+
+// Writes the report to a new file in the temporary directory and returns its
+// path.
+pub fn write_report(report: &Report) -> io::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis());
+    let path = env::temp_dir().join(format!("rustlox-crash-{}-{}.txt", process::id(), timestamp));
+
+    let mut contents = format!(
+        "rustlox {} crash report\n\n== Panic\n{}\n\n== Source\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        report.message,
+        report.source
+    );
+
+    contents.push_str("\n== Tokens\n");
+    match report.tokens {
+        Some(tokens) => {
+            for token in tokens {
+                contents.push_str(&format!("{token}\n"));
+            }
+        }
+        None => contents.push_str("(none, the scanner crashed or the program was cached)\n"),
+    }
+
+    contents.push_str("\n== AST\n");
+    match report.ast {
+        Some(ast) => contents.push_str(&AstPrinter::print(ast)),
+        None => contents.push_str("(crashed before parsing finished)\n"),
+    }
+
+    contents.push_str(&format!("\n== Interpreter state\n{}", report.state));
+
+    fs::write(&path, contents)?;
+    Ok(path)
+}
@@ -0,0 +1,37 @@
+// An example written in a doc comment:
+//
+// /// >>> add(1, 2)
+// /// 3
+pub struct Example {
+    pub line: u32,
+    pub source: String,
+    pub expected: String,
+}
+
+pub fn extract(source: &str) -> Vec<Example> {
+    let mut examples = Vec::new();
+    let mut pending: Option<(u32, String)> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index as u32 + 1;
+        let doc = match line.trim_start().strip_prefix("///") {
+            Some(doc) => doc.trim(),
+            None => {
+                pending = None;
+                continue;
+            }
+        };
+
+        if let Some(example) = doc.strip_prefix(">>>") {
+            pending = Some((line_number, example.trim().to_owned()));
+        } else if let Some((line, source)) = pending.take() {
+            examples.push(Example {
+                line,
+                source,
+                expected: doc.to_owned(),
+            });
+        }
+    }
+
+    examples
+}
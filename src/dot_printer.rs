@@ -0,0 +1,206 @@
+use crate::ast::{Ast, AstVisitor, Expr, ExprId, Object, Stmt};
+use crate::token::Token;
+
+// Prints the AST as a Graphviz graph, with a node for each statement and
+// expression and edges from each node to its children, in order.
+pub struct DotPrinter<'a> {
+    ast: &'a Ast,
+    output: String,
+    nodes: usize,
+}
+
+impl<'a> DotPrinter<'a> {
+    pub fn print(ast: &Ast) -> String {
+        let mut printer = DotPrinter {
+            ast,
+            output: String::from("digraph ast {\n  ordering=out;\n  node [shape=box];\n"),
+            nodes: 0,
+        };
+
+        let program = printer.node("program");
+        for stmt in &ast.statements {
+            let child = printer.visit_stmt(stmt);
+            printer.edge(program, child);
+        }
+
+        printer.output.push_str("}\n");
+        printer.output
+    }
+
+    fn node(&mut self, label: &str) -> usize {
+        let id = self.nodes;
+        self.nodes += 1;
+
+        let label = label
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        self.output
+            .push_str(&format!("  n{id} [label=\"{label}\"];\n"));
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        self.output.push_str(&format!("  n{from} -> n{to};\n"));
+    }
+
+    fn with_exprs(&mut self, label: &str, exprs: &[ExprId]) -> usize {
+        let id = self.node(label);
+        for expr in exprs {
+            let child = self.visit_expr(*expr);
+            self.edge(id, child);
+        }
+        id
+    }
+
+    fn with_statements(&mut self, label: &str, statements: &[Stmt]) -> usize {
+        let id = self.node(label);
+        for stmt in statements {
+            let child = self.visit_stmt(stmt);
+            self.edge(id, child);
+        }
+        id
+    }
+
+    fn lambda(&mut self, name: &str, params: &[Token], body: &[Stmt]) -> usize {
+        let params: Vec<&str> = params.iter().map(|param| param.lexeme.as_ref()).collect();
+        self.with_statements(&format!("{name}({})", params.join(", ")), body)
+    }
+}
+
+impl<'a> AstVisitor<usize, usize> for DotPrinter<'a> {
+    fn visit_expr(&mut self, expr: ExprId) -> usize {
+        let ast = self.ast;
+        match &ast[expr] {
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => self.with_exprs("?:", &[*condition, *then_branch, *else_branch]),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            }
+            | Expr::Logical {
+                left,
+                operator,
+                right,
+            } => self.with_exprs(&operator.lexeme, &[*left, *right]),
+            Expr::Grouping(expr) => self.with_exprs("group", &[*expr]),
+            Expr::Literal(Object::String(value)) => self.node(&format!("\"{value}\"")),
+            Expr::Literal(value) => self.node(&value.to_string()),
+            Expr::Unary { operator, right } => self.with_exprs(&operator.lexeme, &[*right]),
+            Expr::Variable(name) => self.node(&name.lexeme),
+            Expr::Assign { name, value } => {
+                self.with_exprs(&format!("{} =", name.lexeme), &[*value])
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                let mut exprs = vec![*callee];
+                exprs.extend(arguments);
+                self.with_exprs("call", &exprs)
+            }
+            Expr::Get { object, name } => self.with_exprs(&format!(".{}", name.lexeme), &[*object]),
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => self.with_exprs(&format!(".{} =", name.lexeme), &[*object, *value]),
+            Expr::Super { method, .. } => self.node(&format!("super.{}", method.lexeme)),
+            Expr::This(_) => self.node("this"),
+            Expr::List(elements) => self.with_exprs("list", elements),
+            Expr::Spread { list, .. } => self.with_exprs("...", &[*list]),
+            Expr::Index { object, index, .. } => self.with_exprs("[]", &[*object, *index]),
+            Expr::IndexSet {
+                object,
+                index,
+                value,
+                ..
+            } => self.with_exprs("[] =", &[*object, *index, *value]),
+            Expr::Lambda { params, body } => self.lambda("fun", params, body),
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) -> usize {
+        let ast = self.ast;
+        match stmt {
+            Stmt::Expression { expression, .. } => self.with_exprs(";", &[*expression]),
+            Stmt::Print { expression, .. } => self.with_exprs("print", &[*expression]),
+            Stmt::Var { name, initializer } => {
+                let initializer: Vec<ExprId> = initializer.iter().copied().collect();
+                self.with_exprs(&format!("var {}", name.lexeme), &initializer)
+            }
+            Stmt::Block(statements) => self.with_statements("block", statements),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                let id = self.with_exprs("if", &[*condition]);
+                let then_branch = self.visit_stmt(then_branch);
+                self.edge(id, then_branch);
+                if let Some(else_branch) = else_branch {
+                    let else_branch = self.visit_stmt(else_branch);
+                    self.edge(id, else_branch);
+                }
+                id
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                let id = self.with_exprs("while", &[*condition]);
+                let body = self.visit_stmt(body);
+                self.edge(id, body);
+                id
+            }
+            Stmt::Function { name, definition } => match &ast[*definition] {
+                Expr::Lambda { params, body } => {
+                    self.lambda(&format!("fun {}", name.lexeme), params, body)
+                }
+                _ => unreachable!(),
+            },
+            Stmt::Return { value, .. } => {
+                let value: Vec<ExprId> = value.iter().copied().collect();
+                self.with_exprs("return", &value)
+            }
+            Stmt::Delete { object, name } => {
+                self.with_exprs(&format!("delete .{}", name.lexeme), &[*object])
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                statics,
+            } => {
+                let label = match superclass {
+                    Some(superclass) => match &ast[*superclass] {
+                        Expr::Variable(superclass) => {
+                            format!("class {} < {}", name.lexeme, superclass.lexeme)
+                        }
+                        _ => unreachable!(),
+                    },
+                    None => format!("class {}", name.lexeme),
+                };
+
+                let id = self.node(&label);
+                for stmt in statics {
+                    if let Stmt::Var { name, initializer } = stmt {
+                        let initializer: Vec<ExprId> = initializer.iter().copied().collect();
+                        let field =
+                            self.with_exprs(&format!("static {}", name.lexeme), &initializer);
+                        self.edge(id, field);
+                    }
+                }
+                for method in methods {
+                    let method = self.visit_stmt(method);
+                    self.edge(id, method);
+                }
+                id
+            }
+        }
+    }
+}
@@ -4,11 +4,18 @@ use std::rc::Rc;
 
 use crate::ast::Object;
 use crate::error::Error;
+use crate::error_code::ErrorCode;
 use crate::token::Token;
 
 pub struct Environment {
     pub enclosing: Option<Rc<RefCell<Environment>>>,
-    values: HashMap<String, Object>,
+    // Globals are looked up by name. Locals are looked up by the slot the
+    // resolver gave them, which is their position in declaration order.
+    values: HashMap<Rc<str>, Object>,
+    slots: Vec<Object>,
+    // Names that were defined or assigned, and whether they were new, since
+    // the last call to take_changes. Only recorded while tracking is enabled.
+    changes: Option<Vec<(Rc<str>, bool)>>,
 }
 
 impl Environment {
@@ -16,6 +23,8 @@ impl Environment {
         Environment {
             enclosing: None,
             values: HashMap::new(),
+            slots: Vec::new(),
+            changes: None,
         }
     }
 
@@ -23,13 +32,64 @@ impl Environment {
         Environment {
             enclosing: Some(enclosing),
             values: HashMap::new(),
+            slots: Vec::new(),
+            changes: None,
         }
     }
 
-    pub fn define(&mut self, name: String, value: Object) {
+    // Empties a local environment so it can be reused.
+    pub fn clear(&mut self) {
+        self.enclosing = None;
+        self.slots.clear();
+    }
+
+    // A copy of the variables, for going back to them with `restore`. The
+    // values themselves are shared, not copied.
+    pub fn values(&self) -> HashMap<Rc<str>, Object> {
+        self.values.clone()
+    }
+
+    pub fn restore(&mut self, values: HashMap<Rc<str>, Object>) {
+        self.values = values;
+    }
+
+    pub fn track_changes(&mut self) {
+        self.changes = Some(Vec::new());
+    }
+
+    pub fn take_changes(&mut self) -> Vec<(Rc<str>, bool)> {
+        self.changes
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    pub fn define(&mut self, name: Rc<str>, value: Object) {
+        if self.enclosing.is_some() {
+            self.slots.push(value);
+            return;
+        }
+
+        if let Some(changes) = &mut self.changes {
+            changes.push((name.clone(), !self.values.contains_key(&name)));
+        }
+
         self.values.insert(name, value);
     }
 
+    // Defines a local that has no name in the source, like "this".
+    pub fn define_slot(&mut self, value: Object) {
+        self.slots.push(value);
+    }
+
+    pub fn value(&self, name: &str) -> Option<&Object> {
+        self.values.get(name)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&Rc<str>, &Object)> {
+        self.values.iter()
+    }
+
     fn ancestor(&self, distance: usize) -> Rc<RefCell<Environment>> {
         let mut environment = self
             .enclosing
@@ -48,27 +108,21 @@ impl Environment {
         environment
     }
 
-    pub fn get_at(&self, distance: usize, name: &str) -> Result<Object, Error> {
+    pub fn get_at(&self, distance: usize, slot: usize) -> Object {
         // We don't expect this to panic,
         // because the Resolver already found the scope of the variable
         if distance == 0 {
-            Ok(self.values.get(name).unwrap().to_owned())
+            self.slots[slot].to_owned()
         } else {
-            Ok(self
-                .ancestor(distance)
-                .borrow()
-                .values
-                .get(name)
-                .unwrap()
-                .to_owned())
+            self.ancestor(distance).borrow().slots[slot].to_owned()
         }
     }
 
-    pub fn assign_at(&mut self, distance: usize, name: &Token, value: Object) -> Result<(), Error> {
+    pub fn assign_at(&mut self, distance: usize, slot: usize, value: Object) {
         if distance == 0 {
-            self.assign(name, value)
+            self.slots[slot] = value;
         } else {
-            self.ancestor(distance).borrow_mut().assign(name, value)
+            self.ancestor(distance).borrow_mut().slots[slot] = value;
         }
     }
 
@@ -81,6 +135,7 @@ impl Environment {
             } else {
                 Err(Error::Runtime {
                     token: name.to_owned(),
+                    code: ErrorCode::UndefinedVariable,
                     message: format!("Undefined variable '{}'.", name.lexeme),
                 })
             }
@@ -89,6 +144,10 @@ impl Environment {
 
     pub fn assign(&mut self, name: &Token, value: Object) -> Result<(), Error> {
         if self.values.contains_key(&name.lexeme) {
+            if let Some(changes) = &mut self.changes {
+                changes.push((name.lexeme.clone(), false));
+            }
+
             self.values.insert(name.lexeme.clone(), value);
             Ok(())
         } else {
@@ -97,6 +156,7 @@ impl Environment {
             } else {
                 Err(Error::Runtime {
                     token: name.to_owned(),
+                    code: ErrorCode::UndefinedVariable,
                     message: format!("Undefined variable '{}'.", name.lexeme),
                 })
             }
@@ -1,32 +1,322 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::iter;
+use std::rc::Rc;
+
 use crate::ast::Object;
+use crate::error_code::ErrorCode;
 use crate::token::{Token, TokenType};
 
 pub enum Error {
     Lexical,
     Syntax,
     Semantic,
-    Runtime { token: Token, message: String },
+    Runtime {
+        token: Token,
+        code: ErrorCode,
+        message: String,
+    },
+    // The script was stopped through a `CancelHandle`.
+    Interrupted {
+        token: Token,
+    },
+    Internal,       // A panic, already reported with a crash report
     Return(Object), // Used to interrupt execution flow and propagate return value
 }
 
-pub fn error_line(line: &u32, message: &str) {
-    report(line, "", message);
+/// Why `RustLox::run_file` or another command working on a file failed.
+/// Errors in the script itself have already been reported.
+#[derive(Debug)]
+pub enum RunError {
+    /// The file couldn't be read or written.
+    Io(io::Error),
+    /// The script has errors that kept it from running, or from being
+    /// printed or formatted.
+    Invalid,
+    /// The script failed with a runtime error, was interrupted, or rustlox
+    /// itself failed while running it.
+    Failed,
+    /// The coverage report couldn't be written.
+    Coverage { path: String, error: io::Error },
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Io(error) => write!(f, "{error}"),
+            RunError::Invalid => write!(f, "The script has errors."),
+            RunError::Failed => write!(f, "The script failed while running."),
+            RunError::Coverage { path, error } => {
+                write!(f, "Can't write coverage report '{path}': {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RunError {}
+
+impl From<io::Error> for RunError {
+    fn from(error: io::Error) -> Self {
+        RunError::Io(error)
+    }
+}
+
+/// When diagnostics are colored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Only if standard error is a terminal.
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// Keeps the program from running, or stopped it.
+    Error,
+    Warning,
+}
+
+/// An error or warning about a program.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The line the code it's about ends on, or 0 if it isn't about any
+    /// code in particular, as with crashes.
+    pub line: u32,
+    /// The column the code it's about starts at, counted in characters
+    /// from 1.
+    pub column: u32,
+    /// Everything after the position, as in "Error at 'x': Expect ';'.".
+    pub message: String,
+    /// What kind of error it is. Warnings don't have codes.
+    pub code: Option<ErrorCode>,
+    /// The line of code it's about, if it's in the program's source.
+    pub snippet: Option<Snippet>,
 }
 
-pub fn error_token(token: &Token, message: &str) {
-    if token.token_type == TokenType::EOF {
-        report(&token.line, " at end", message);
-    } else {
-        report(&token.line, &format!(" at '{}'", token.lexeme), message);
+/// A line of a program's source, with `width` characters from `column` on
+/// underlined.
+#[derive(Clone, Debug)]
+pub struct Snippet {
+    pub line: u32,
+    pub text: String,
+    pub column: u32,
+    pub width: usize,
+}
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+impl Diagnostic {
+    /// The diagnostic as it's printed, a message and the snippet under it.
+    /// With `color`, the message is red for errors or yellow for warnings,
+    /// and so is the underline, beneath the quoted code in bold.
+    pub fn render(&self, color: bool) -> String {
+        let (style, bold, reset) = match (color, self.severity) {
+            (false, _) => ("", "", ""),
+            (true, Severity::Error) => (RED, BOLD, RESET),
+            (true, Severity::Warning) => (YELLOW, BOLD, RESET),
+        };
+
+        let code = match self.code {
+            Some(code) => format!(" [{}]", code.code()),
+            None => String::new(),
+        };
+        let location = match self.line {
+            0 => String::new(),
+            line => format!("[line {line}:{}] ", self.column),
+        };
+        let mut result = format!("{style}{location}{}{code}{reset}\n", self.message);
+
+        if let Some(snippet) = &self.snippet {
+            let start = snippet.column as usize - 1;
+            let before: String = snippet.text.chars().take(start).collect();
+            let code: String = snippet
+                .text
+                .chars()
+                .skip(start)
+                .take(snippet.width)
+                .collect();
+            let after: String = snippet.text.chars().skip(start + snippet.width).collect();
+
+            // Tabs are kept so the underline lines up however they're shown.
+            let indent: String = before
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .chain(iter::repeat(' '))
+                .take(start)
+                .collect();
+            let gutter = " ".repeat(snippet.line.to_string().len());
+
+            result.push_str(&format!(
+                "{} | {before}{bold}{code}{reset}{after}\n",
+                snippet.line
+            ));
+            result.push_str(&format!(
+                "{gutter} | {indent}{style}{}{reset}\n",
+                "^".repeat(snippet.width.max(1))
+            ));
+        }
+
+        result
     }
 }
 
-pub fn runtime_error(error: &Error) {
-    if let Error::Runtime { token, message } = error {
-        eprintln!("[line {}] {}", token.line, message);
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(false).trim_end())
     }
 }
 
-pub fn report(line: &u32, location: &str, message: &str) {
-    eprintln!("[line {}] Error{}: {}", line, location, message);
+/// Where errors and warnings go once a phase of running a script is done
+/// with them. `RustLox` prints them to standard error unless it's given
+/// another reporter with `set_reporter`.
+pub trait ErrorReporter {
+    fn report(&mut self, diagnostic: Diagnostic);
+}
+
+/// Prints diagnostics to standard error, in color if `color` is set.
+pub struct StderrReporter {
+    pub color: bool,
+}
+
+impl ErrorReporter for StderrReporter {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        eprint!("{}", diagnostic.render(self.color));
+    }
+}
+
+/// Keeps every diagnostic.
+impl ErrorReporter for Vec<Diagnostic> {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.push(diagnostic);
+    }
+}
+
+/// Lets the program that gave a reporter to `RustLox` keep a handle to it,
+/// for example to read what a `Vec<Diagnostic>` collected.
+impl<R: ErrorReporter> ErrorReporter for Rc<RefCell<R>> {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.borrow_mut().report(diagnostic);
+    }
+}
+
+// What the phases that run a script report errors and warnings through. It
+// quotes `source`, the script being scanned, parsed or run, and passes them
+// on to `reporter`.
+pub struct SourceReporter<'a> {
+    source: &'a str,
+    reporter: &'a mut dyn ErrorReporter,
+}
+
+impl<'a> SourceReporter<'a> {
+    pub fn new(source: &'a str, reporter: &'a mut dyn ErrorReporter) -> Self {
+        Self { source, reporter }
+    }
+
+    pub fn error_at(&mut self, line: &u32, column: &u32, code: ErrorCode, message: &str) {
+        let snippet = self.snippet(*line, *column, 1);
+        self.reporter.report(Diagnostic {
+            severity: Severity::Error,
+            line: *line,
+            column: *column,
+            message: format!("Error: {message}"),
+            code: Some(code),
+            snippet,
+        });
+    }
+
+    // Underlines `width` characters from the column on.
+    pub fn warning_at(&mut self, line: &u32, column: &u32, width: usize, message: &str) {
+        let snippet = self.snippet(*line, *column, width);
+        self.reporter.report(Diagnostic {
+            severity: Severity::Warning,
+            line: *line,
+            column: *column,
+            message: format!("Warning: {message}"),
+            code: None,
+            snippet,
+        });
+    }
+
+    pub fn warning_token(&mut self, token: &Token, message: &str) {
+        let snippet = self.token_snippet(token);
+        self.reporter.report(Diagnostic {
+            severity: Severity::Warning,
+            line: token.line,
+            column: token.column,
+            message: format!("Warning: {message}"),
+            code: None,
+            snippet,
+        });
+    }
+
+    pub fn error_token(&mut self, token: &Token, code: ErrorCode, message: &str) {
+        let location = if token.token_type == TokenType::EOF {
+            String::from(" at end")
+        } else {
+            format!(" at '{}'", token.lexeme)
+        };
+
+        let snippet = self.token_snippet(token);
+        self.reporter.report(Diagnostic {
+            severity: Severity::Error,
+            line: token.line,
+            column: token.column,
+            message: format!("Error{location}: {message}"),
+            code: Some(code),
+            snippet,
+        });
+    }
+
+    // Only runtime errors and interruptions are reported this way; the others
+    // have been reported by the phase that found them.
+    pub fn runtime_error(&mut self, error: &Error) {
+        let (token, code, message) = match error {
+            Error::Runtime {
+                token,
+                code,
+                message,
+            } => (token, *code, message.as_str()),
+            Error::Interrupted { token } => (token, ErrorCode::Interrupted, "Interrupted."),
+            _ => return,
+        };
+
+        let snippet = self.token_snippet(token);
+        self.reporter.report(Diagnostic {
+            severity: Severity::Error,
+            line: token.line,
+            column: token.column,
+            message: message.to_owned(),
+            code: Some(code),
+            snippet,
+        });
+    }
+
+    // The first line of the token. A token from another source, like a
+    // function declared by an earlier REPL input, isn't quoted.
+    fn token_snippet(&self, token: &Token) -> Option<Snippet> {
+        if self.source.get(token.span.clone()) != Some(&*token.lexeme) {
+            return None;
+        }
+
+        // Tokens record the line they end on.
+        let line = token.line - token.lexeme.matches('\n').count() as u32;
+        let width = token.lexeme.lines().next().unwrap_or("").chars().count();
+        self.snippet(line, token.column, width)
+    }
+
+    fn snippet(&self, line: u32, column: u32, width: usize) -> Option<Snippet> {
+        let text = self.source.lines().nth(line as usize - 1)?.to_owned();
+        Some(Snippet {
+            line,
+            text,
+            column,
+            width,
+        })
+    }
 }
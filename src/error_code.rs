@@ -0,0 +1,775 @@
+/// The kind of an error, with a stable code like "E2001" that's printed with
+/// it and that `rustlox --explain` describes. Codes in the 1000s are for the
+/// scanner, the 2000s for the parser, the 3000s for the resolver and lints,
+/// the 4000s for errors at runtime and the 5000s for bugs in rustlox itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    UnexpectedCharacter,
+    UnterminatedString,
+    UnterminatedComment,
+    MissingDirectiveFlag,
+    DuplicateElse,
+    UnmatchedElse,
+    UnmatchedEnd,
+    UnknownDirective,
+    UnterminatedIf,
+    UnreadableSource,
+    MisplacedDirective,
+
+    MissingSemicolon,
+    MissingLeftParen,
+    MissingRightParen,
+    MissingLeftBrace,
+    MissingRightBrace,
+    MissingRightBracket,
+    MissingName,
+    MissingExpression,
+    MissingDot,
+    MissingColon,
+    InvalidAssignmentTarget,
+    InvalidDeleteTarget,
+    TooManyArguments,
+    TooManyParameters,
+    TrailingTokens,
+
+    AlreadyDeclared,
+    ReadInOwnInitializer,
+    SuperOutsideClass,
+    SuperWithoutSuperclass,
+    ThisOutsideClass,
+    ReturnFromTopLevel,
+    ReturnValueFromInitializer,
+    InheritFromSelf,
+    UnusedVariable,
+    ShadowedVariable,
+    DuplicateMethod,
+
+    InvalidOperand,
+    InvalidComparison,
+    UndefinedVariable,
+    UndefinedProperty,
+    UndefinedField,
+    NotAnInstance,
+    NotCallable,
+    WrongArity,
+    StackOverflow,
+    SuperclassNotAClass,
+    NotIndexable,
+    InvalidIndex,
+    IndexOutOfRange,
+    InvalidClassOperand,
+    NonBooleanCondition,
+    ArithmeticOverflow,
+    InvalidMethodResult,
+    NativeNotAllowed,
+    NativeFailed,
+    Interrupted,
+    StepLimitExceeded,
+    TimeLimitExceeded,
+    DivisionByZero,
+    InvalidAddition,
+    NotSpreadable,
+
+    InternalError,
+}
+
+use ErrorCode::*;
+
+const ALL: [ErrorCode; 63] = [
+    UnexpectedCharacter,
+    UnterminatedString,
+    UnterminatedComment,
+    MissingDirectiveFlag,
+    DuplicateElse,
+    UnmatchedElse,
+    UnmatchedEnd,
+    UnknownDirective,
+    UnterminatedIf,
+    UnreadableSource,
+    MisplacedDirective,
+    MissingSemicolon,
+    MissingLeftParen,
+    MissingRightParen,
+    MissingLeftBrace,
+    MissingRightBrace,
+    MissingRightBracket,
+    MissingName,
+    MissingExpression,
+    MissingDot,
+    MissingColon,
+    InvalidAssignmentTarget,
+    InvalidDeleteTarget,
+    TooManyArguments,
+    TooManyParameters,
+    TrailingTokens,
+    AlreadyDeclared,
+    ReadInOwnInitializer,
+    SuperOutsideClass,
+    SuperWithoutSuperclass,
+    ThisOutsideClass,
+    ReturnFromTopLevel,
+    ReturnValueFromInitializer,
+    InheritFromSelf,
+    UnusedVariable,
+    ShadowedVariable,
+    DuplicateMethod,
+    InvalidOperand,
+    InvalidComparison,
+    UndefinedVariable,
+    UndefinedProperty,
+    UndefinedField,
+    NotAnInstance,
+    NotCallable,
+    WrongArity,
+    StackOverflow,
+    SuperclassNotAClass,
+    NotIndexable,
+    InvalidIndex,
+    IndexOutOfRange,
+    InvalidClassOperand,
+    NonBooleanCondition,
+    ArithmeticOverflow,
+    InvalidMethodResult,
+    NativeNotAllowed,
+    NativeFailed,
+    Interrupted,
+    StepLimitExceeded,
+    TimeLimitExceeded,
+    DivisionByZero,
+    InvalidAddition,
+    NotSpreadable,
+    InternalError,
+];
+
+impl ErrorCode {
+    /// The code called `code`, like "E2001".
+    pub fn from_code(code: &str) -> Option<ErrorCode> {
+        ALL.into_iter()
+            .find(|error| error.code().eq_ignore_ascii_case(code))
+    }
+
+    // Codes are never reused: a variant that goes away takes its code with it.
+    pub fn code(self) -> &'static str {
+        match self {
+            UnexpectedCharacter => "E1001",
+            UnterminatedString => "E1002",
+            UnterminatedComment => "E1003",
+            MissingDirectiveFlag => "E1004",
+            DuplicateElse => "E1005",
+            UnmatchedElse => "E1006",
+            UnmatchedEnd => "E1007",
+            UnknownDirective => "E1008",
+            UnterminatedIf => "E1009",
+            UnreadableSource => "E1010",
+            MisplacedDirective => "E1011",
+            MissingSemicolon => "E2001",
+            MissingLeftParen => "E2002",
+            MissingRightParen => "E2003",
+            MissingLeftBrace => "E2004",
+            MissingRightBrace => "E2005",
+            MissingRightBracket => "E2006",
+            MissingName => "E2007",
+            MissingExpression => "E2008",
+            MissingDot => "E2009",
+            MissingColon => "E2010",
+            InvalidAssignmentTarget => "E2011",
+            InvalidDeleteTarget => "E2012",
+            TooManyArguments => "E2013",
+            TooManyParameters => "E2014",
+            TrailingTokens => "E2015",
+            AlreadyDeclared => "E3001",
+            ReadInOwnInitializer => "E3002",
+            SuperOutsideClass => "E3003",
+            SuperWithoutSuperclass => "E3004",
+            ThisOutsideClass => "E3005",
+            ReturnFromTopLevel => "E3006",
+            ReturnValueFromInitializer => "E3007",
+            InheritFromSelf => "E3008",
+            UnusedVariable => "E3009",
+            ShadowedVariable => "E3010",
+            DuplicateMethod => "E3011",
+            InvalidOperand => "E4001",
+            InvalidComparison => "E4002",
+            UndefinedVariable => "E4003",
+            UndefinedProperty => "E4004",
+            UndefinedField => "E4005",
+            NotAnInstance => "E4006",
+            NotCallable => "E4007",
+            WrongArity => "E4008",
+            StackOverflow => "E4009",
+            SuperclassNotAClass => "E4010",
+            NotIndexable => "E4011",
+            InvalidIndex => "E4012",
+            IndexOutOfRange => "E4013",
+            InvalidClassOperand => "E4014",
+            NonBooleanCondition => "E4015",
+            ArithmeticOverflow => "E4016",
+            InvalidMethodResult => "E4017",
+            NativeNotAllowed => "E4018",
+            NativeFailed => "E4019",
+            Interrupted => "E4020",
+            StepLimitExceeded => "E4021",
+            TimeLimitExceeded => "E4022",
+            DivisionByZero => "E4023",
+            InvalidAddition => "E4024",
+            NotSpreadable => "E4025",
+            InternalError => "E5001",
+        }
+    }
+
+    /// What the error means and how to fix it, with an example.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            UnexpectedCharacter => {
+                "A character that can't start any token was found outside of a string or
+comment. Lox has no '@', '$' or '`' tokens, for example.
+
+    var price = $5; // Error: Unexpected character: \"$\".
+
+Remove the character, or put it in a string."
+            }
+            UnterminatedString => {
+                "A string was opened with '\"' but the file ended before its closing '\"'.
+Strings can span lines, so the missing quote may be far from where the
+error is reported.
+
+    print \"hello;
+
+Add the closing quote."
+            }
+            UnterminatedComment => {
+                "A block comment was opened with '/*' but the file ended before its
+closing '*/'. Block comments nest, so each '/*' needs its own '*/'.
+
+    /* outer /* inner */
+    print 1;
+
+Close every comment that was opened."
+            }
+            MissingDirectiveFlag => {
+                "An '#if' directive must name the flag it tests, which is set with
+'--cfg'.
+
+    #if
+    print 1;
+    #end
+
+Write the flag after '#if', as in '#if debug'."
+            }
+            DuplicateElse => {
+                "An '#if' directive can have at most one '#else'.
+
+    #if debug
+    print 1;
+    #else
+    print 2;
+    #else
+    print 3;
+    #end
+
+Remove the extra '#else', or nest another '#if' in the first one."
+            }
+            UnmatchedElse => {
+                "An '#else' directive must come between an '#if' and its '#end'.
+
+    print 1;
+    #else
+    print 2;
+
+Add the '#if' it belongs to, or remove it."
+            }
+            UnmatchedEnd => {
+                "An '#end' directive closes an '#if', and there's no open '#if' here.
+
+    #if debug
+    print 1;
+    #end
+    #end
+
+Remove the extra '#end'."
+            }
+            UnknownDirective => {
+                "Lines starting with '#' are directives, and the only directives are
+'#if', '#else' and '#end'.
+
+    #ifdef debug
+
+Use one of the known directives."
+            }
+            UnterminatedIf => {
+                "An '#if' directive was never closed with '#end'.
+
+    #if debug
+    print 1;
+
+Add '#end' after the code the directive applies to."
+            }
+            UnreadableSource => {
+                "Reading the script failed partway, usually because it isn't valid
+UTF-8. The code before the failure is scanned, but the script isn't run.
+
+    [line 3:1] Error: Can't read the script: stream did not contain valid UTF-8.
+
+Save the script as UTF-8, or check the stream it's read from."
+            }
+            MisplacedDirective => {
+                "A directive takes a line of its own: it must start the line, and only a
+comment may follow it.
+
+    print 1; #if debug // Error: Directives must start a line.
+    #if debug print 2; // Error: Expect end of line after directive.
+
+Move the directive, or the code after it, to a line of its own."
+            }
+            MissingSemicolon => {
+                "Statements end with ';', and one is missing after a value, an
+expression, a variable declaration, a delete target or a 'for' loop's
+condition.
+
+    print \"hello\" // Error at end: Expect ';' after value
+
+Add the ';'. The error points at the token after the statement, so the
+semicolon usually belongs at the end of the line before."
+            }
+            MissingLeftParen => {
+                "A '(' is required after 'if', 'while' and 'for', and before the
+parameters of a function or method.
+
+    if x > 1 print x; // Error at 'x': Expect '(' after 'if'.
+
+Add the '(' and the matching ')'."
+            }
+            MissingRightParen => {
+                "A '(' was never closed: after a condition, the clauses of a 'for' loop,
+the arguments of a call, the parameters of a function or a grouped
+expression.
+
+    print (1 + 2; // Error at ';': Expect ')' after expression.
+
+Add the missing ')'."
+            }
+            MissingLeftBrace => {
+                "The bodies of classes, functions and methods are blocks, which start
+with '{'.
+
+    fun greet() print \"hi\"; // Error at 'print': Expect '{' before function body.
+
+Put the body in braces."
+            }
+            MissingRightBrace => {
+                "A block or class body was opened with '{' and never closed.
+
+    fun greet() {
+      print \"hi\";
+    // Error at end: Expect '}' after block.
+
+Add the missing '}'."
+            }
+            MissingRightBracket => {
+                "A '[' was never closed, in a list literal or an index.
+
+    var list = [1, 2; // Error at ';': Expect ']' after list elements.
+
+Add the missing ']'."
+            }
+            MissingName => {
+                "A name is required after 'var', 'fun', 'class', '<' in a class
+declaration, '.' and 'super.', and for each parameter.
+
+    var 1 = 2; // Error at '1': Expect variable name.
+
+Names start with a letter or '_' and can't be keywords."
+            }
+            MissingExpression => {
+                "An expression was expected, but the next token can't start one.
+
+    print 1 +; // Error at ';': Expect expression.
+
+Complete the expression, or remove the operator."
+            }
+            MissingDot => {
+                "'super' can only be used to access a method of the superclass, as in
+'super.method'.
+
+    class B < A { init() { super(); } } // Error at '(': Expect '.' after 'super'.
+
+Name the method, as in 'super.init()'."
+            }
+            MissingColon => {
+                "A conditional expression has the form 'condition ? then : else', and
+its ':' is missing.
+
+    print x > 0 ? \"positive\"; // Expect ':' in ternary expression
+
+Add the ':' and the value for when the condition is false."
+            }
+            InvalidAssignmentTarget => {
+                "Only variables, fields and list elements can be assigned to.
+
+    1 + 2 = 3; // Error at '=': Invalid assignment target.
+
+Assign to a variable, 'object.field' or 'list[index]'."
+            }
+            InvalidDeleteTarget => {
+                "'delete' removes a field from an instance, so it must be followed by a
+field access.
+
+    delete point; // Error at 'delete': Invalid delete target.
+
+Write the field to remove, as in 'delete point.x;'."
+            }
+            TooManyArguments => {
+                "A call can have at most 255 arguments.
+
+Pass the values in a list instead."
+            }
+            TooManyParameters => {
+                "A function or method can have at most 255 parameters.
+
+Take the values in a list instead."
+            }
+            TrailingTokens => {
+                "An example in a documentation comment must be a single expression, and
+there's more code after it.
+
+    /// > 1 + 2; 3
+
+Write one expression per example."
+            }
+            AlreadyDeclared => {
+                "A local variable, parameter, function or class was declared twice in
+the same scope. Globals can be redeclared, but locals can't.
+
+    {
+      var a = 1;
+      var a = 2; // Error at 'a': Already a variable with this name in this scope.
+    }
+
+Rename one of them, or assign to the first instead."
+            }
+            ReadInOwnInitializer => {
+                "A local variable was used in the expression that initializes it, when
+it doesn't have a value yet.
+
+    var a = 1;
+    {
+      var a = a + 1; // Error at 'a': Can't read local variable in its own initializer.
+    }
+
+Give the new variable a different name than the one it's computed from."
+            }
+            SuperOutsideClass => {
+                "'super' refers to the superclass of the class around it, and there's
+no class around it here.
+
+    fun f() { super.init(); }
+
+Only use 'super' in methods."
+            }
+            SuperWithoutSuperclass => {
+                "'super' was used in a class that doesn't inherit from another one.
+
+    class A { init() { super.init(); } }
+
+Make the class inherit from another with 'class A < Base', or remove the
+call."
+            }
+            ThisOutsideClass => {
+                "'this' refers to the instance a method was called on, and there's no
+method around it here.
+
+    fun f() { print this; }
+
+Only use 'this' in methods, or pass the instance as a parameter."
+            }
+            ReturnFromTopLevel => {
+                "'return' leaves a function, and it's outside of any function.
+
+    return 1;
+
+Only use 'return' in functions and methods."
+            }
+            ReturnValueFromInitializer => {
+                "An 'init' method always returns the instance, so it can't return
+another value. A bare 'return;' is allowed.
+
+    class A { init() { return 1; } }
+
+Remove the value, or move the code to another method."
+            }
+            InheritFromSelf => {
+                "A class can't be its own superclass.
+
+    class A < A {}
+
+Inherit from another class."
+            }
+            UnusedVariable => {
+                "A local variable, parameter, function or class is never read. This is
+reported by the 'unused' lint, or 'unused-parameter' for parameters, as a
+warning unless '-W error=unused' or '-W error=unused-parameter' makes it
+an error.
+
+    fun f(b) { // Warning: Parameter 'b' is never used.
+      var a = 1; // Warning: Variable 'a' is never used.
+    }
+
+Remove the declaration, or use it. Names that start with '_', like '_b',
+are never reported. They still can't be declared twice in one scope, so
+two ignored parameters need different names, like '_a' and '_b'."
+            }
+            ShadowedVariable => {
+                "A local variable has the same name as a local variable of an enclosing
+scope, and hides it. This is reported by the 'shadow' lint, which
+'--lint shadow' turns on.
+
+    {
+      var a = 1;
+      { var a = 2; print a; }
+      print a;
+    }
+
+Rename the inner variable."
+            }
+            DuplicateMethod => {
+                "A class declares two methods with the same name, and only the last one
+would be kept.
+
+    class A {
+      f() { return 1; }
+      f() { return 2; } // Already a method with this name in this class.
+    }
+
+Rename or remove one of them."
+            }
+            InvalidOperand => {
+                "An arithmetic or comparison operator got an operand that isn't a
+number, or a unary '-' got something other than a number.
+
+    print \"1\" * 2; // Operands must be numbers.
+
+Convert the value to a number first."
+            }
+            InvalidComparison => {
+                "'<', '<=', '>' and '>=' compare two numbers or two strings. They got
+something else, or a number and a string.
+
+    print \"a\" < 1; // Operands must be two numbers or two strings; got string and number.
+
+Convert one of the operands, as in 'str(1)'."
+            }
+            UndefinedVariable => {
+                "A global variable was used or assigned to before it was declared, or it
+was never declared.
+
+    print count; // Undefined variable 'count'.
+
+Declare it with 'var', and check its spelling. If a variable in scope has a
+similar name, the error suggests it:
+
+    var count = 1;
+    print cuont; // Undefined variable 'cuont'. Did you mean 'count'?"
+            }
+            UndefinedProperty => {
+                "An instance has no field or method with this name, or a class has no
+static field or method with it.
+
+    class A {}
+    print A().x; // Undefined property 'x'.
+
+Set the field before reading it, or define the method."
+            }
+            UndefinedField => {
+                "'delete' was used on a field the instance doesn't have.
+
+    class A {}
+    delete A().x; // Undefined field 'x'.
+
+Only delete fields that were set."
+            }
+            NotAnInstance => {
+                "Only instances and classes have properties, and only instances have
+fields that can be set or deleted.
+
+    var a = 1;
+    print a.x; // Only instances have properties.
+
+Check what the value is before using its properties."
+            }
+            NotCallable => {
+                "Only functions, methods and classes can be called.
+
+    var a = 1;
+    a(); // Can only call functions and classes.
+
+Check what the value is before calling it."
+            }
+            WrongArity => {
+                "A function, method or class was called with a different number of
+arguments than it has parameters. A class takes as many as its 'init'
+method.
+
+    fun add(a, b) { return a + b; }
+    add(1); // Expected 2 arguments but got 1.
+
+Pass one argument per parameter."
+            }
+            StackOverflow => {
+                "Calls were nested deeper than '--max-call-depth' allows, which usually
+means a recursive function never reaches the case that stops it.
+
+    fun f() { f(); }
+    f(); // Stack overflow.
+
+Make sure the recursion ends, or raise the limit."
+            }
+            SuperclassNotAClass => {
+                "A class can only inherit from a class.
+
+    var Base = 1;
+    class A < Base {} // Superclass must be a class.
+
+Inherit from a class."
+            }
+            NotIndexable => {
+                "Only lists can be indexed with '[]'.
+
+    var a = 1;
+    print a[0]; // Only lists can be indexed.
+
+Check what the value is before indexing it."
+            }
+            InvalidIndex => {
+                "List indices are whole numbers from 0.
+
+    print [1, 2][1.5]; // Index must be a non-negative integer.
+
+Use 'floor' to round the index down."
+            }
+            IndexOutOfRange => {
+                "A list was indexed past its end. The last element of a list is at one
+less than its length.
+
+    print [1, 2][2]; // Index out of range.
+
+Check the index against 'len(list)'."
+            }
+            InvalidClassOperand => {
+                "The right operand of 'is' is the class to check the left operand
+against, and it isn't a class.
+
+    print 1 is 2; // Right operand of 'is' must be a class.
+
+Use a class, like 'x is Point'."
+            }
+            NonBooleanCondition => {
+                "With '--strict-booleans', conditions, the operands of 'and', 'or'
+and '!', and the results of filter()'s callback must be 'true' or
+'false', rather than any truthy or falsey value.
+
+    if (1) print \"one\"; // Condition must be a boolean; got number.
+
+Compare the value, as in 'if (n != 0)'."
+            }
+            ArithmeticOverflow => {
+                "With '--checked-arithmetic', an arithmetic operation produced infinity
+or NaN instead of a number, like a multiplication that overflows or, with
+'--ieee-division', a division by zero.
+
+    print 1 / 0; // 1 / 0 produced inf.
+
+Check the operands before the operation."
+            }
+            InvalidMethodResult => {
+                "A class's 'toString' method must return a string, and its 'equals'
+method a boolean.
+
+    class A { toString() { return 1; } }
+    print A(); // toString() must return a string; got number.
+
+Return a value of the right type."
+            }
+            NativeNotAllowed => {
+                "A native function was called that '--allow-natives' or
+'--deny-natives' doesn't allow.
+
+    // rustlox --deny-natives readFile script.lox
+    readFile(\"secrets.txt\"); // Native function 'readFile' isn't allowed.
+
+Change the policy, or don't call the function."
+            }
+            NativeFailed => {
+                "A native function got an argument it can't use, or failed, like
+'readFile' on a file that doesn't exist. The message says what went
+wrong.
+
+    print len(1); // Expected a list or a string.
+
+Check the function's arguments."
+            }
+            Interrupted => {
+                "The program embedding rustlox stopped the script while it ran, usually
+because it took too long. Scripts are stopped at the next loop iteration
+or call after they're cancelled.
+
+    while (true) {} // Interrupted.
+
+Make the script finish sooner."
+            }
+            StepLimitExceeded => {
+                "The script ran more statements than '--max-steps' allows, which usually
+means it's stuck in a loop or does far more work than expected.
+
+    // rustlox --max-steps 1000 script.lox
+    while (true) {} // Ran more than 1000 statements.
+
+Make the loop end, or raise the limit."
+            }
+            TimeLimitExceeded => {
+                "The script ran for longer than '--timeout' allows.
+
+    // rustlox --timeout 100 script.lox
+    while (true) {} // Ran for more than 100 ms.
+
+Make the script finish sooner, or raise the limit."
+            }
+            DivisionByZero => {
+                "The right operand of '/' or '%' was zero.
+
+    var count = 0;
+    print 10 / count; // Division by zero.
+
+Check the divisor first, or run with '--ieee-division' to get infinity or
+NaN instead, as IEEE 754 defines."
+            }
+            InvalidAddition => {
+                "'+' adds two numbers or concatenates two strings. It got something else,
+or a number and a string.
+
+    print \"a\" + 1; // Operands must be two numbers or two strings.
+
+Convert one of the operands, as in 'str(1)'."
+            }
+            NotSpreadable => {
+                "'...' in a call's arguments or a list's elements stands for the elements
+of a list, but the value after it isn't a list.
+
+    fun add(a, b) { return a + b; }
+    print add(...3); // Only lists can be spread.
+
+Spread a list, as in 'add(...[1, 2])', or pass the value without '...'."
+            }
+            InternalError => {
+                "rustlox itself failed while running the script. This is a bug in rustlox,
+not in the script, and the interpreter was reset to the global scope.
+
+    Error: Internal error, this is a bug in rustlox. A crash report was written
+    to /tmp/rustlox-crash-1234-1700000000000.txt, please attach it to your bug
+    report.
+
+Please report the bug with the crash report attached. It holds the script,
+so remove anything private from it first."
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::token::{Token, TokenType};
+
+const INDENT: &str = "  ";
+
+// Reprints a token stream, including its comments and directives, with
+// canonical indentation, spacing and brace placement. Each statement goes on
+// its own line, and a single blank line is kept where the source had one or
+// more between two lines.
+pub struct Formatter<'a> {
+    output: String,
+    indent: usize,
+    // Open parentheses in the current block, so the semicolons of a "for"
+    // clause don't end the line.
+    parens: usize,
+    // The parentheses open around each enclosing block, as in a function
+    // expression passed as an argument.
+    blocks: Vec<usize>,
+    newline: bool,
+    previous: Option<&'a Token>,
+    // The one before `previous`, which tells if a minus is unary.
+    before_previous: Option<&'a Token>,
+}
+
+impl<'a> Formatter<'a> {
+    pub fn format(tokens: &'a [Token]) -> String {
+        let mut formatter = Formatter {
+            output: String::new(),
+            indent: 0,
+            parens: 0,
+            blocks: Vec::new(),
+            newline: false,
+            previous: None,
+            before_previous: None,
+        };
+
+        for token in tokens {
+            if token.token_type != TokenType::EOF {
+                formatter.token(token);
+            }
+        }
+
+        if !formatter.output.is_empty() {
+            formatter.output.push('\n');
+        }
+        formatter.output
+    }
+
+    fn token(&mut self, token: &'a Token) {
+        match token.token_type {
+            TokenType::RightBrace => {
+                self.indent = self.indent.saturating_sub(1);
+                self.parens = self.blocks.pop().unwrap_or(0);
+            }
+            TokenType::RightParen => self.parens = self.parens.saturating_sub(1),
+            _ => (),
+        }
+
+        let trailing_comment = token.token_type == TokenType::Comment
+            && self
+                .previous
+                .is_some_and(|previous| first_line(token) == previous.line);
+        let empty_block =
+            token.token_type == TokenType::RightBrace && self.previous_is(&TokenType::LeftBrace);
+
+        if self.previous.is_none() || empty_block {
+            // Nothing goes before the first token, or inside "{}".
+        } else if trailing_comment {
+            self.output.push(' ');
+        } else if self.newline || self.starts_line(token) {
+            self.line_break(token);
+        } else if self.space_before(token) {
+            self.output.push(' ');
+        }
+
+        self.output.push_str(&token.lexeme);
+        self.newline = if trailing_comment {
+            // The line break due before the comment comes after it instead.
+            self.newline || token.lexeme.starts_with("//")
+        } else {
+            self.ends_line(token)
+        };
+
+        match token.token_type {
+            TokenType::LeftBrace => {
+                self.indent += 1;
+                self.blocks.push(self.parens);
+                self.parens = 0;
+            }
+            TokenType::LeftParen => self.parens += 1,
+            _ => (),
+        }
+
+        self.before_previous = self.previous;
+        self.previous = Some(token);
+    }
+
+    fn line_break(&mut self, token: &Token) {
+        let previous = self.previous.unwrap();
+        let blank_line = first_line(token) > previous.line + 1
+            && previous.token_type != TokenType::LeftBrace
+            && token.token_type != TokenType::RightBrace;
+
+        self.output.push('\n');
+        if blank_line {
+            self.output.push('\n');
+        }
+        self.output.push_str(&INDENT.repeat(self.indent));
+    }
+
+    // Comments and directives that don't follow code on their line stay on a
+    // line of their own, and so does the code after them. A "}" ends its line
+    // unless the statement goes on, as in "} else" or "});".
+    fn starts_line(&self, token: &Token) -> bool {
+        match self.previous.map(|previous| &previous.token_type) {
+            Some(TokenType::Comment) if first_line(token) > self.previous.unwrap().line => {
+                return true
+            }
+            Some(TokenType::RightBrace) if !continues_after_brace(token) => return true,
+            _ => (),
+        }
+
+        matches!(
+            token.token_type,
+            TokenType::Comment | TokenType::Directive | TokenType::RightBrace
+        )
+    }
+
+    fn ends_line(&self, token: &Token) -> bool {
+        match token.token_type {
+            TokenType::Semicolon => self.parens == 0,
+            TokenType::LeftBrace | TokenType::Directive => true,
+            TokenType::Comment => token.lexeme.starts_with("//"),
+            _ => false,
+        }
+    }
+
+    fn previous_is(&self, token_type: &TokenType) -> bool {
+        self.previous.map(|previous| &previous.token_type) == Some(token_type)
+    }
+
+    fn space_before(&self, token: &Token) -> bool {
+        let previous = self.previous.unwrap();
+
+        match (&previous.token_type, &token.token_type) {
+            (_, TokenType::Semicolon | TokenType::Comma | TokenType::Dot) => false,
+            (_, TokenType::RightParen | TokenType::RightBracket) => false,
+            (TokenType::LeftParen | TokenType::LeftBracket | TokenType::Dot, _) => false,
+            (TokenType::Bang | TokenType::Ellipsis, _) => false,
+            (TokenType::Minus, _) => !self.is_unary_minus(),
+            (TokenType::Fun, TokenType::LeftParen) => true,
+            (_, TokenType::LeftParen | TokenType::LeftBracket) => !ends_operand(previous),
+            _ => true,
+        }
+    }
+
+    // A minus is unary unless it follows an operand.
+    fn is_unary_minus(&self) -> bool {
+        !self.before_previous.is_some_and(ends_operand)
+    }
+}
+
+fn continues_after_brace(token: &Token) -> bool {
+    matches!(
+        token.token_type,
+        TokenType::Else
+            | TokenType::RightParen
+            | TokenType::RightBracket
+            | TokenType::Comma
+            | TokenType::Semicolon
+            | TokenType::Dot
+            | TokenType::Comment
+    )
+}
+
+// Whether a token can end an operand, so that a "(" or "[" after it is a call
+// or an index.
+fn ends_operand(token: &Token) -> bool {
+    matches!(
+        token.token_type,
+        TokenType::Identifier
+            | TokenType::String(_)
+            | TokenType::Number(_)
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Nil
+            | TokenType::This
+            | TokenType::Super
+            | TokenType::RightParen
+            | TokenType::RightBracket
+    )
+}
+
+// Tokens record the line they end on.
+fn first_line(token: &Token) -> u32 {
+    token.line - token.lexeme.matches('\n').count() as u32
+}
@@ -1,59 +1,416 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::mem::replace;
+use std::ops::Range;
 use std::rc::Rc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::ast::{AstVisitor, Expr, Object, Stmt};
-use crate::callable::LoxCallable;
+use crate::ast::{Ast, AstVisitor, Expr, ExprId, Object, Stmt};
+use crate::callable::{LoxCallable, LoxFunction, NativeImpl};
 use crate::class::LoxClass;
+use crate::coverage::Coverage;
 use crate::environment::Environment;
-use crate::error::{runtime_error, Error};
+use crate::error::{Error, ErrorReporter, SourceReporter};
+use crate::error_code::ErrorCode;
+use crate::natives::{self, HostNative};
+use crate::render::{render, Style};
+use crate::suggestion;
 use crate::token::{Token, TokenType};
 
+const ENVIRONMENT_POOL_SIZE: usize = 64;
+
+#[derive(Clone)]
+pub struct InterpreterConfig {
+    /// Conditions of if, while, ternary and logical operators, the operand
+    /// of '!' and the results of filter()'s callback must be booleans, instead of following
+    /// Lox's truthiness rules.
+    pub strict_booleans: bool,
+    /// "and" and "or" produce the truthiness of the operand that decided
+    /// the result, instead of the operand itself.
+    pub boolean_logic: bool,
+    /// Arithmetic that produces NaN or an infinity is a runtime error.
+    pub checked_arithmetic: bool,
+    /// Dividing by zero with `/` or `%` produces an infinity or NaN, as in
+    /// IEEE 754, instead of being a runtime error.
+    pub ieee_division: bool,
+    /// Which natives scripts may use.
+    pub natives: NativePolicy,
+    /// How many calls may be in progress at once before the call that
+    /// exceeds it fails with a "Stack overflow." runtime error. Each call
+    /// uses the host's stack, which must be large enough for this many
+    /// calls.
+    pub max_call_depth: usize,
+    /// How many statements a script may run before failing with a runtime
+    /// error. Checked at each loop iteration and call, so a script can run a
+    /// few statements past it.
+    pub max_steps: Option<u64>,
+    /// How long a script may run before failing with a runtime error,
+    /// checked like `max_steps`.
+    pub timeout: Option<Duration>,
+    /// Whether the natives that read and write files and standard input,
+    /// readLine(), readFile() and writeFile(), are defined.
+    pub allow_io: bool,
+    /// Whether clock() and sleep() are defined.
+    pub allow_clock: bool,
+    /// Whether getenv() is defined.
+    pub allow_env: bool,
+    /// Lists passed to functions, methods and initializers are deep copied,
+    /// so changing a parameter's list doesn't change the caller's.
+    /// Natives like push() still change the lists they're given.
+    pub copy_arguments: bool,
+}
+
+impl Default for InterpreterConfig {
+    fn default() -> Self {
+        Self {
+            strict_booleans: false,
+            boolean_logic: false,
+            checked_arithmetic: false,
+            ieee_division: false,
+            natives: NativePolicy::All,
+            max_call_depth: 1000,
+            max_steps: None,
+            timeout: None,
+            allow_io: true,
+            allow_clock: true,
+            allow_env: true,
+            copy_arguments: false,
+        }
+    }
+}
+
+/// Restricts the natives scripts can use. Calling a forbidden native is a
+/// runtime error.
+#[derive(Clone, Default)]
+pub enum NativePolicy {
+    #[default]
+    All,
+    /// Only the natives with these names.
+    Allow(HashSet<String>),
+    /// Every native except the ones with these names.
+    Deny(HashSet<String>),
+}
+
+impl NativePolicy {
+    pub fn allows(&self, name: &str) -> bool {
+        match self {
+            NativePolicy::All => true,
+            NativePolicy::Allow(names) => names.contains(name),
+            NativePolicy::Deny(names) => !names.contains(name),
+        }
+    }
+}
+
+/// Stops the script an interpreter is running, from any thread. The script
+/// ends with an "Interrupted." error at the next loop iteration or call.
+#[derive(Clone)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    /// If no script is running, the next one is stopped as soon as it starts
+    /// looping or calling.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
 pub struct Interpreter {
     pub globals: Rc<RefCell<Environment>>,
     environment: Rc<RefCell<Environment>>,
-    locals: HashMap<Token, usize>,
+    config: InterpreterConfig,
+    script_args: Vec<String>,
+    argument_pool: Vec<Vec<Object>>,
+    environment_pool: Vec<Rc<RefCell<Environment>>>,
+    events: Option<Box<dyn Write>>,
+    // Where `print` and write() go.
+    output: Box<dyn Write>,
+    // The AST of the program or function being run.
+    ast: Rc<Ast>,
+    call_depth: usize,
+    coverage: Option<Coverage>,
+    cancelled: Arc<AtomicBool>,
+    // The statements run by the current script, and when it must end.
+    steps: u64,
+    deadline: Option<Instant>,
 }
 
 impl Interpreter {
-    pub fn new() -> Self {
+    pub fn new(config: InterpreterConfig) -> Self {
         let globals = Rc::new(RefCell::new(Environment::new_global()));
 
-        globals.borrow_mut().define(
-            String::from("clock"),
-            Object::Callable(LoxCallable::LoxNative {
-                call_impl: |_| -> Object {
-                    Object::Number(
-                        SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap() // Can safely unwrap here because SystemTime::now() will not be before EPOCH
-                            .as_micros() as f64,
-                    )
-                },
-                arity: 0,
-            }),
-        );
+        natives::define_natives(&mut globals.borrow_mut(), &config);
 
         Self {
             globals: globals.clone(),
             environment: globals.clone(),
-            locals: HashMap::new(),
+            config,
+            script_args: Vec::new(),
+            argument_pool: Vec::new(),
+            environment_pool: Vec::new(),
+            events: None,
+            output: Box::new(io::stdout()),
+            ast: Rc::new(Ast::default()),
+            call_depth: 0,
+            coverage: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            steps: 0,
+            deadline: None,
         }
     }
 
-    pub fn interpret(&mut self, statements: &Vec<Stmt>) -> Result<(), Error> {
-        for statement in statements {
-            if let Err(e) = self.visit_stmt(statement) {
-                runtime_error(&e);
-                return Err(e);
+    // Defines a global native function, like the built-in ones.
+    pub fn define_native(&mut self, name: &str, arity: usize, call_impl: HostNative) {
+        natives::define_native(
+            &mut self.globals.borrow_mut(),
+            name,
+            arity,
+            false,
+            NativeImpl::Host(call_impl),
+        );
+    }
+
+    pub fn set_native_policy(&mut self, policy: NativePolicy) {
+        self.config.natives = policy;
+    }
+
+    pub fn set_event_writer(&mut self, writer: Box<dyn Write>) {
+        self.events = Some(writer);
+    }
+
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle(self.cancelled.clone())
+    }
+
+    // Checked often enough that any script stops soon after being cancelled
+    // or running out of steps or time: every loop iteration and call, and
+    // while sleep() waits. A cancellation stops one script.
+    pub fn checkpoint(&self, token: &Token) -> Result<(), Error> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            self.cancelled.store(false, Ordering::Relaxed);
+            return Err(Error::Interrupted {
+                token: token.to_owned(),
+            });
+        }
+
+        if let Some(max_steps) = self.config.max_steps {
+            if self.steps > max_steps {
+                return Err(Error::Runtime {
+                    token: token.to_owned(),
+                    code: ErrorCode::StepLimitExceeded,
+                    message: format!("Ran more than {max_steps} statements."),
+                });
+            }
+        }
+
+        if let Some(deadline) = self.deadline {
+            if Instant::now() > deadline {
+                return Err(Error::Runtime {
+                    token: token.to_owned(),
+                    code: ErrorCode::TimeLimitExceeded,
+                    message: format!(
+                        "Ran for more than {} ms.",
+                        self.config.timeout.unwrap().as_millis()
+                    ),
+                });
             }
         }
 
         Ok(())
     }
 
-    fn is_truthy(object: &Object) -> bool {
+    // Defines the global, or replaces its value if it exists.
+    pub fn set_global(&mut self, name: &str, value: Object) {
+        self.globals.borrow_mut().define(name.into(), value);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        self.globals.borrow().value(name).cloned()
+    }
+
+    pub fn set_output(&mut self, writer: Box<dyn Write>) {
+        self.output = writer;
+    }
+
+    pub fn output(&mut self) -> &mut dyn Write {
+        &mut self.output
+    }
+
+    // Starts counting the statements that run on each line.
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(Coverage::default());
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    // Writes an event to the event stream, as a line of JSON. The values of
+    // `fields` must already be JSON.
+    fn emit(&mut self, event: &str, fields: &[(&str, String)]) {
+        if let Some(events) = &mut self.events {
+            let mut line = format!("{{\"event\": \"{event}\"");
+            for (name, value) in fields {
+                line.push_str(&format!(", \"{name}\": {value}"));
+            }
+            line.push('}');
+
+            // A visualizer that stopped reading shouldn't stop the script.
+            writeln!(events, "{line}").ok();
+        }
+    }
+
+    fn emit_variable(&mut self, event: &str, name: &Token, value: &Object) {
+        if self.events.is_some() {
+            self.emit(
+                event,
+                &[
+                    ("name", json_string(&name.lexeme)),
+                    ("value", render(value, Style::Json)),
+                    ("line", name.line.to_string()),
+                    ("column", name.column.to_string()),
+                    ("span", json_span(name)),
+                ],
+            );
+        }
+    }
+
+    fn emit_scope(&mut self, event: &str) {
+        if self.events.is_some() {
+            let depth = self.scope_depth();
+            self.emit(event, &[("depth", depth.to_string())]);
+        }
+    }
+
+    fn scope_depth(&self) -> usize {
+        let mut depth = 0;
+        let mut environment = self.environment.clone();
+        while let Some(enclosing) = environment.clone().borrow().enclosing.clone() {
+            depth += 1;
+            environment = enclosing;
+        }
+
+        depth
+    }
+
+    pub fn script_args(&self) -> &[String] {
+        &self.script_args
+    }
+
+    pub fn set_script_args(&mut self, args: Vec<String>) {
+        self.script_args = args;
+    }
+
+    // Describes the current scope and globals, for crash reports.
+    pub fn state_summary(&self) -> String {
+        let depth = self.scope_depth();
+        let globals = self.globals.borrow();
+        let mut names: Vec<String> = globals
+            .entries()
+            .map(|(name, value)| format!("{name}: {}", value.type_name()))
+            .collect();
+        names.sort();
+
+        format!(
+            "Scope depth: {depth}\nResolved locals: {}\nGlobals:\n    {}\n",
+            self.ast.resolved_locals(),
+            names.join("\n    ")
+        )
+    }
+
+    // Goes back to the global scope, after a run was interrupted without
+    // leaving its blocks and calls.
+    pub fn reset_scope(&mut self) {
+        self.environment = self.globals.clone();
+        self.call_depth = 0;
+        // A crash may have left them partly filled or still in use.
+        self.environment_pool.clear();
+        self.argument_pool.clear();
+    }
+
+    // Makes `ast` the one being run, returning the previous one so it can be
+    // restored.
+    pub fn set_ast(&mut self, ast: Rc<Ast>) -> Rc<Ast> {
+        replace(&mut self.ast, ast)
+    }
+
+    // A program's value is the value of the expression statement it ends
+    // with, or nil. The runtime error that stops it, if any, goes to
+    // `reporter` too.
+    pub fn interpret(
+        &mut self,
+        ast: &Rc<Ast>,
+        reporter: &mut dyn ErrorReporter,
+    ) -> Result<Object, Error> {
+        self.interpret_statements(ast, 0..ast.statements.len(), reporter)
+    }
+
+    // Runs some of the program's top-level statements. The step and time
+    // limits apply to each call on their own.
+    pub fn interpret_statements(
+        &mut self,
+        ast: &Rc<Ast>,
+        statements: Range<usize>,
+        reporter: &mut dyn ErrorReporter,
+    ) -> Result<Object, Error> {
+        if statements.start == 0 {
+            if let Some(coverage) = &mut self.coverage {
+                coverage.add_program(ast);
+            }
+        }
+
+        self.steps = 0;
+        self.deadline = self.config.timeout.map(|timeout| Instant::now() + timeout);
+
+        let enclosing = self.set_ast(ast.clone());
+        let mut result = Ok(Object::Nil);
+        for statement in &ast.statements[statements] {
+            let executed = match statement {
+                Stmt::Expression { expression, .. } => {
+                    self.start_statement(statement);
+                    self.visit_expr(*expression)
+                }
+                _ => self.visit_stmt(statement).map(|_| Object::Nil),
+            };
+
+            result = executed;
+            if result.is_err() {
+                break;
+            }
+        }
+
+        // The process may exit right after a failed run, so buffered events
+        // are written now.
+        if let Some(events) = &mut self.events {
+            events.flush().ok();
+        }
+
+        self.set_ast(enclosing);
+        if let Err(error) = &result {
+            let source = ast.source.as_deref().unwrap_or("");
+            SourceReporter::new(source, reporter).runtime_error(error);
+        }
+
+        result
+    }
+
+    pub fn evaluate(&mut self, ast: &Rc<Ast>, expr: ExprId) -> Result<Object, Error> {
+        let enclosing = self.set_ast(ast.clone());
+        let result = self.visit_expr(expr);
+        self.set_ast(enclosing);
+        result
+    }
+
+    fn start_statement(&mut self, stmt: &Stmt) {
+        self.steps += 1;
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(stmt);
+        }
+    }
+
+    pub fn is_truthy(object: &Object) -> bool {
         match object {
             Object::Nil => false,
             Object::Boolean(value) => *value,
@@ -61,13 +418,114 @@ impl Interpreter {
         }
     }
 
+    // Whether a condition holds, which with strict booleans must be a
+    // boolean. The operand of '!' and the results of filter()'s callback are
+    // conditions too.
+    pub fn is_condition_true(&self, token: &Token, condition: &Object) -> Result<bool, Error> {
+        match condition {
+            Object::Boolean(value) => Ok(*value),
+            _ if self.config.strict_booleans => Err(Error::Runtime {
+                token: token.to_owned(),
+                code: ErrorCode::NonBooleanCondition,
+                message: format!(
+                    "Condition must be a boolean; got {}.",
+                    condition.type_name()
+                ),
+            }),
+            _ => Ok(Interpreter::is_truthy(condition)),
+        }
+    }
+
+    fn arithmetic_result(
+        &self,
+        operator: &Token,
+        lhs: f64,
+        rhs: f64,
+        result: f64,
+    ) -> Result<Object, Error> {
+        if self.config.checked_arithmetic && !result.is_finite() {
+            return Err(Error::Runtime {
+                token: operator.to_owned(),
+                code: ErrorCode::ArithmeticOverflow,
+                message: format!("{lhs} {} {rhs} produced {result}.", operator.lexeme),
+            });
+        }
+
+        Ok(Object::Number(result))
+    }
+
+    fn check_divisor(&self, operator: &Token, divisor: f64) -> Result<(), Error> {
+        if divisor == 0.0 && !self.config.ieee_division {
+            return Err(Error::Runtime {
+                token: operator.to_owned(),
+                code: ErrorCode::DivisionByZero,
+                message: String::from("Division by zero."),
+            });
+        }
+
+        Ok(())
+    }
+
+    // Evaluates a call argument or list element into `values`, splicing in
+    // the elements of a spread list.
+    fn push_element(&mut self, values: &mut Vec<Object>, expr: ExprId) -> Result<(), Error> {
+        let ast = self.ast.clone();
+        let Expr::Spread { ellipsis, list } = &ast[expr] else {
+            values.push(self.visit_expr(expr)?);
+            return Ok(());
+        };
+
+        match self.visit_expr(*list)? {
+            Object::List(list) => {
+                values.extend(list.borrow().iter().cloned());
+                Ok(())
+            }
+            _ => Err(Error::Runtime {
+                token: ellipsis.to_owned(),
+                code: ErrorCode::NotSpreadable,
+                message: String::from("Only lists can be spread."),
+            }),
+        }
+    }
+
     fn number_operand_err(operator: &Token) -> Result<Object, Error> {
         Err(Error::Runtime {
             token: operator.to_owned(),
+            code: ErrorCode::InvalidOperand,
             message: String::from("Operands must be numbers."),
         })
     }
 
+    fn compare(operator: &Token, left: &Object, right: &Object) -> Result<Object, Error> {
+        let ordering = match (left, right) {
+            (Object::Number(lhs), Object::Number(rhs)) => lhs.partial_cmp(rhs),
+            (Object::String(lhs), Object::String(rhs)) => Some(lhs.cmp(rhs)),
+            _ => {
+                return Err(Error::Runtime {
+                    token: operator.to_owned(),
+                    code: ErrorCode::InvalidComparison,
+                    message: format!(
+                        "Operands must be two numbers or two strings; got {} and {}.",
+                        left.type_name(),
+                        right.type_name()
+                    ),
+                })
+            }
+        };
+
+        // NaN isn't ordered, so every comparison with it is false.
+        let result = match (&operator.token_type, ordering) {
+            (_, None) => false,
+            (TokenType::Greater, Some(ordering)) => ordering.is_gt(),
+            (TokenType::GreaterEqual, Some(ordering)) => ordering.is_ge(),
+            (TokenType::Less, Some(ordering)) => ordering.is_lt(),
+            (TokenType::LessEqual, Some(ordering)) => ordering.is_le(),
+            _ => unreachable!(),
+        };
+
+        Ok(Object::Boolean(result))
+    }
+
     pub fn execute_block(
         &mut self,
         statements: &Vec<Stmt>,
@@ -80,6 +538,7 @@ impl Interpreter {
         // that has a try-finally syntax.
         let result = || -> Result<(), Error> {
             self.environment = environment;
+            self.emit_scope("enter_scope");
 
             for statement in statements {
                 self.visit_stmt(statement)?;
@@ -88,31 +547,413 @@ impl Interpreter {
             Ok(())
         }();
 
+        self.emit_scope("exit_scope");
         self.environment = previous;
 
         result
     }
 
-    pub fn resolve(&mut self, name: Token, depth: usize) {
-        self.locals.insert(name, depth);
+    // Converts a value to the text print, concatenation, write() and format()
+    // show, which for instances with a toString() method is what it returns,
+    // also inside lists.
+    pub fn stringify(&mut self, value: &Object, token: &Token) -> Result<String, Error> {
+        self.stringify_in(value, token, &mut Vec::new())
     }
 
-    fn look_up_variable(&self, name: &Token) -> Result<Object, Error> {
-        if let Some(distance) = self.locals.get(name) {
-            self.environment.borrow().get_at(*distance, &name.lexeme)
+    // `visiting` holds the lists being converted, to stop at cycles like
+    // `render` does.
+    fn stringify_in(
+        &mut self,
+        value: &Object,
+        token: &Token,
+        visiting: &mut Vec<*const ()>,
+    ) -> Result<String, Error> {
+        if let Object::List(list) = value {
+            let id = Rc::as_ptr(list) as *const ();
+            if visiting.contains(&id) {
+                return Ok(String::from("..."));
+            }
+
+            // A copy, since toString() may change the list.
+            let elements = list.borrow().clone();
+            visiting.push(id);
+            let strings = elements
+                .iter()
+                .map(|element| self.stringify_in(element, token, visiting))
+                .collect::<Result<Vec<_>, _>>();
+            visiting.pop();
+            return Ok(format!("[{}]", strings?.join(", ")));
+        }
+
+        if let Object::Instance(instance) = value {
+            let to_string = instance.borrow().method("toString");
+            if let Some(to_string) = to_string {
+                let to_string = Object::Callable(to_string);
+                return match self.call_with_this(
+                    &to_string,
+                    Some(value.clone()),
+                    token,
+                    &Vec::new(),
+                )? {
+                    Object::String(string) => Ok(string.to_string()),
+                    other => Err(Error::Runtime {
+                        token: token.to_owned(),
+                        code: ErrorCode::InvalidMethodResult,
+                        message: format!(
+                            "toString() must return a string; got {}.",
+                            other.type_name()
+                        ),
+                    }),
+                };
+            }
+        }
+
+        Ok(value.to_string())
+    }
+
+    // Instances whose class defines an equals() method compare with it, when
+    // they're the left operand. Otherwise they're only equal to themselves.
+    fn values_equal(
+        &mut self,
+        left: &Object,
+        right: &Object,
+        token: &Token,
+    ) -> Result<bool, Error> {
+        if let Object::Instance(instance) = left {
+            let equals = instance.borrow().method("equals");
+            if let Some(equals) = equals {
+                let equals = Object::Callable(equals);
+                return match self.call_with_this(
+                    &equals,
+                    Some(left.clone()),
+                    token,
+                    &vec![right.clone()],
+                )? {
+                    Object::Boolean(result) => Ok(result),
+                    other => Err(Error::Runtime {
+                        token: token.to_owned(),
+                        code: ErrorCode::InvalidMethodResult,
+                        message: format!(
+                            "equals() must return a boolean; got {}.",
+                            other.type_name()
+                        ),
+                    }),
+                };
+            }
+        }
+
+        Ok(left.equals(right))
+    }
+
+    // Strings can be concatenated with instances that have a toString() method.
+    fn concatenates(lhs: &Object, rhs: &Object) -> bool {
+        match (lhs, rhs) {
+            (Object::String(_), Object::Instance(instance))
+            | (Object::Instance(instance), Object::String(_)) => {
+                instance.borrow().method("toString").is_some()
+            }
+            _ => false,
+        }
+    }
+
+    pub fn call_value(
+        &mut self,
+        callee: &Object,
+        paren: &Token,
+        arguments: &Vec<Object>,
+    ) -> Result<Object, Error> {
+        self.call_with_this(callee, None, paren, arguments)
+    }
+
+    // Calls `callee`, which is an unbound method when `this` is given.
+    fn call_with_this(
+        &mut self,
+        callee: &Object,
+        this: Option<Object>,
+        paren: &Token,
+        arguments: &Vec<Object>,
+    ) -> Result<Object, Error> {
+        if let Object::Callable(function) = callee {
+            // Checked when called, since a native can be reached through any
+            // variable, field or list it was stored in before the policy
+            // changed.
+            if let LoxCallable::LoxNative { native } = function {
+                if !self.config.natives.allows(&native.name) {
+                    return Err(Error::Runtime {
+                        token: paren.to_owned(),
+                        code: ErrorCode::NativeNotAllowed,
+                        message: format!("Native function '{}' isn't allowed.", native.name),
+                    });
+                }
+            }
+
+            let arity = function.arity();
+            if arguments.len() == arity || (function.is_variadic() && arguments.len() > arity) {
+                self.checkpoint(paren)?;
+                if self.call_depth == self.config.max_call_depth {
+                    return Err(Error::Runtime {
+                        token: paren.to_owned(),
+                        code: ErrorCode::StackOverflow,
+                        message: String::from("Stack overflow."),
+                    });
+                }
+
+                self.call_depth += 1;
+                let result = if self.events.is_none() {
+                    self.invoke(function, this, paren, arguments)
+                } else {
+                    self.call_with_events(function, this, paren, arguments)
+                };
+                self.call_depth -= 1;
+
+                result
+            } else if function.is_variadic() {
+                Err(Error::Runtime {
+                    token: paren.to_owned(),
+                    code: ErrorCode::WrongArity,
+                    message: format!(
+                        "Expected at least {} arguments but got {}.",
+                        arity,
+                        arguments.len()
+                    ),
+                })
+            } else {
+                Err(Error::Runtime {
+                    token: paren.to_owned(),
+                    code: ErrorCode::WrongArity,
+                    message: format!("Expected {} arguments but got {}.", arity, arguments.len()),
+                })
+            }
         } else {
-            self.globals.borrow().get(name)
+            Err(Error::Runtime {
+                token: paren.to_owned(),
+                code: ErrorCode::NotCallable,
+                message: String::from("Can only call functions and classes."),
+            })
+        }
+    }
+
+    fn get_property(
+        &mut self,
+        expr: ExprId,
+        object: Object,
+        name: &Token,
+    ) -> Result<Object, Error> {
+        match object {
+            Object::Instance(instance) => {
+                let borrowed = instance.borrow();
+                if let Some(field) = borrowed.field(&name.lexeme) {
+                    Ok(field.clone())
+                } else if let Some(method) = self.find_method(expr, borrowed.class(), &name.lexeme)
+                {
+                    Ok(Object::Callable(
+                        method.bind(Object::Instance(instance.clone())),
+                    ))
+                } else {
+                    Err(Error::Runtime {
+                        token: name.to_owned(),
+                        code: ErrorCode::UndefinedProperty,
+                        message: format!("Undefined property '{}'.", name.lexeme),
+                    })
+                }
+            }
+            Object::Callable(LoxCallable::LoxClass { class }) => class.get(name),
+            _ => Err(Error::Runtime {
+                token: name.to_owned(),
+                code: ErrorCode::NotAnInstance,
+                message: String::from("Only instances have properties."),
+            }),
+        }
+    }
+
+    // A method that is called right away isn't bound to the instance; the
+    // instance is returned along with it, to be passed as "this" by the call.
+    fn method_callee(
+        &mut self,
+        expr: ExprId,
+        object: Object,
+        name: &Token,
+    ) -> Result<(Object, Option<Object>), Error> {
+        if let Object::Instance(instance) = &object {
+            let borrowed = instance.borrow();
+            if borrowed.field(&name.lexeme).is_none() {
+                if let Some(method) = self.find_method(expr, borrowed.class(), &name.lexeme) {
+                    drop(borrowed);
+                    return Ok((Object::Callable(method), Some(object)));
+                }
+            }
+        }
+
+        Ok((self.get_property(expr, object, name)?, None))
+    }
+
+    fn find_method(
+        &mut self,
+        expr: ExprId,
+        class: &Rc<LoxClass>,
+        name: &str,
+    ) -> Option<LoxCallable> {
+        if let Some(method) = self.ast.cached_method(expr, class) {
+            return Some(method);
+        }
+
+        let method = class.find_method(name)?.clone();
+        self.ast.cache_method(expr, class, &method);
+        Some(method)
+    }
+
+    fn invoke(
+        &mut self,
+        function: &LoxCallable,
+        this: Option<Object>,
+        paren: &Token,
+        arguments: &Vec<Object>,
+    ) -> Result<Object, Error> {
+        match this {
+            Some(this) => function.call_method(self, this, arguments),
+            None => function.call(self, paren, arguments),
+        }
+    }
+
+    fn call_with_events(
+        &mut self,
+        function: &LoxCallable,
+        this: Option<Object>,
+        paren: &Token,
+        arguments: &Vec<Object>,
+    ) -> Result<Object, Error> {
+        let rendered: Vec<String> = arguments
+            .iter()
+            .map(|argument| render(argument, Style::Json))
+            .collect();
+        self.emit(
+            "call",
+            &[
+                ("callee", json_string(&function.to_string())),
+                ("arguments", format!("[{}]", rendered.join(", "))),
+                ("line", paren.line.to_string()),
+                ("column", paren.column.to_string()),
+                ("span", json_span(paren)),
+            ],
+        );
+
+        let result = self.invoke(function, this, paren, arguments);
+        let outcome = match &result {
+            Ok(value) => ("value", render(value, Style::Json)),
+            Err(_) => ("error", String::from("true")),
+        };
+        self.emit(
+            "return",
+            &[
+                outcome,
+                ("line", paren.line.to_string()),
+                ("column", paren.column.to_string()),
+                ("span", json_span(paren)),
+            ],
+        );
+
+        result
+    }
+
+    fn list_index(list: &[Object], bracket: &Token, index: &Object) -> Result<usize, Error> {
+        match index {
+            Object::Number(index) if index.fract() == 0.0 && *index >= 0.0 => {
+                if (*index as usize) < list.len() {
+                    Ok(*index as usize)
+                } else {
+                    Err(Error::Runtime {
+                        token: bracket.to_owned(),
+                        code: ErrorCode::IndexOutOfRange,
+                        message: String::from("Index out of range."),
+                    })
+                }
+            }
+            _ => Err(Error::Runtime {
+                token: bracket.to_owned(),
+                code: ErrorCode::InvalidIndex,
+                message: String::from("Index must be a non-negative integer."),
+            }),
+        }
+    }
+
+    // The value a parameter gets for an argument.
+    pub fn pass_argument(&self, argument: &Object) -> Object {
+        if self.config.copy_arguments {
+            argument.deep_copy()
+        } else {
+            argument.clone()
+        }
+    }
+
+    pub fn new_environment(
+        &mut self,
+        enclosing: Rc<RefCell<Environment>>,
+    ) -> Rc<RefCell<Environment>> {
+        if let Some(environment) = self.environment_pool.pop() {
+            environment.borrow_mut().enclosing = Some(enclosing);
+            environment
+        } else {
+            Rc::new(RefCell::new(Environment::new_local(enclosing)))
+        }
+    }
+
+    // Block and call environments are reused once they're done, unless
+    // something else, like a closure, still holds on to them.
+    pub fn recycle_environment(&mut self, environment: Rc<RefCell<Environment>>) {
+        if Rc::strong_count(&environment) == 1
+            && self.environment_pool.len() < ENVIRONMENT_POOL_SIZE
+        {
+            environment.borrow_mut().clear();
+            self.environment_pool.push(environment);
+        }
+    }
+
+    // Adds the closest visible name to an undefined variable error.
+    fn suggest_name(&self, error: Error, name: &Token, expr: ExprId) -> Error {
+        let Error::Runtime {
+            code: ErrorCode::UndefinedVariable,
+            message,
+            ..
+        } = &error
+        else {
+            return error;
+        };
+
+        let globals = self.globals.borrow();
+        let locals = self.ast.locals_in_scope(expr);
+        let mut candidates: Vec<&str> = globals.entries().map(|(name, _)| &**name).collect();
+        candidates.extend(locals.iter().map(|name| &**name));
+
+        match suggestion::closest(&name.lexeme, candidates.into_iter()) {
+            Some(candidate) => Error::Runtime {
+                token: name.to_owned(),
+                code: ErrorCode::UndefinedVariable,
+                message: format!("{message} Did you mean '{candidate}'?"),
+            },
+            None => error,
+        }
+    }
+
+    fn look_up_variable(&self, name: &Token, expr: ExprId) -> Result<Object, Error> {
+        if let Some((distance, slot)) = self.ast.local(expr) {
+            Ok(self.environment.borrow().get_at(distance, slot))
+        } else {
+            let value = self.globals.borrow().get(name);
+            value.map_err(|error| self.suggest_name(error, name, expr))
         }
     }
 }
 
 impl AstVisitor<Result<Object, Error>, Result<(), Error>> for Interpreter {
-    fn visit_expr(&mut self, expr: &Expr) -> Result<Object, Error> {
-        match expr {
+    fn visit_expr(&mut self, expr: ExprId) -> Result<Object, Error> {
+        // Holding our own reference lets the arms borrow from the AST while
+        // calls switch `self.ast` to the AST of the function they run.
+        let ast = self.ast.clone();
+        match &ast[expr] {
             Expr::Literal(value) => Ok(value.to_owned()),
-            Expr::Grouping(expression) => self.visit_expr(expression),
+            Expr::Grouping(expression) => self.visit_expr(*expression),
             Expr::Unary { operator, right } => {
-                let right = self.visit_expr(right)?;
+                let right = self.visit_expr(*right)?;
 
                 match operator.token_type {
                     TokenType::Minus => {
@@ -122,7 +963,9 @@ impl AstVisitor<Result<Object, Error>, Result<(), Error>> for Interpreter {
                             Interpreter::number_operand_err(operator)
                         }
                     }
-                    TokenType::Bang => Ok(Object::Boolean(!Interpreter::is_truthy(&right))),
+                    TokenType::Bang => {
+                        Ok(Object::Boolean(!self.is_condition_true(operator, &right)?))
+                    }
                     _ => unreachable!(),
                 }
             }
@@ -131,190 +974,226 @@ impl AstVisitor<Result<Object, Error>, Result<(), Error>> for Interpreter {
                 operator,
                 right,
             } => {
-                let left = self.visit_expr(left)?;
-                let right = self.visit_expr(right)?;
+                let left = self.visit_expr(*left)?;
+                let right = self.visit_expr(*right)?;
 
                 match operator.token_type {
                     TokenType::Minus => match (left, right) {
-                        (Object::Number(lhs), Object::Number(rhs)) => Ok(Object::Number(lhs - rhs)),
+                        (Object::Number(lhs), Object::Number(rhs)) => {
+                            self.arithmetic_result(operator, lhs, rhs, lhs - rhs)
+                        }
                         _ => Interpreter::number_operand_err(operator),
                     },
                     TokenType::Plus => match (left, right) {
-                        (Object::Number(lhs), Object::Number(rhs)) => Ok(Object::Number(lhs + rhs)),
+                        (Object::Number(lhs), Object::Number(rhs)) => {
+                            self.arithmetic_result(operator, lhs, rhs, lhs + rhs)
+                        }
                         (Object::String(lhs), Object::String(rhs)) => {
-                            Ok(Object::String(format!("{}{}", lhs, rhs)))
+                            Ok(Object::String(format!("{}{}", lhs, rhs).into()))
                         }
+                        (lhs, rhs) if Interpreter::concatenates(&lhs, &rhs) => Ok(Object::String(
+                            format!(
+                                "{}{}",
+                                self.stringify(&lhs, operator)?,
+                                self.stringify(&rhs, operator)?
+                            )
+                            .into(),
+                        )),
                         _ => Err(Error::Runtime {
                             token: operator.to_owned(),
+                            code: ErrorCode::InvalidAddition,
                             message: String::from("Operands must be two numbers or two strings."),
                         }),
                     },
                     TokenType::Slash => match (left, right) {
-                        (Object::Number(lhs), Object::Number(rhs)) => Ok(Object::Number(lhs / rhs)),
-                        _ => Interpreter::number_operand_err(operator),
-                    },
-                    TokenType::Star => match (left, right) {
-                        (Object::Number(lhs), Object::Number(rhs)) => Ok(Object::Number(lhs * rhs)),
-                        _ => Interpreter::number_operand_err(operator),
-                    },
-                    TokenType::Percent => match (left, right) {
-                        (Object::Number(lhs), Object::Number(rhs)) => Ok(Object::Number(lhs % rhs)),
-                        _ => Interpreter::number_operand_err(operator),
-                    },
-                    TokenType::Greater => match (left, right) {
                         (Object::Number(lhs), Object::Number(rhs)) => {
-                            Ok(Object::Boolean(lhs > rhs))
+                            self.check_divisor(operator, rhs)?;
+                            self.arithmetic_result(operator, lhs, rhs, lhs / rhs)
                         }
                         _ => Interpreter::number_operand_err(operator),
                     },
-                    TokenType::GreaterEqual => match (left, right) {
+                    TokenType::Star => match (left, right) {
                         (Object::Number(lhs), Object::Number(rhs)) => {
-                            Ok(Object::Boolean(lhs >= rhs))
+                            self.arithmetic_result(operator, lhs, rhs, lhs * rhs)
                         }
                         _ => Interpreter::number_operand_err(operator),
                     },
-                    TokenType::Less => match (left, right) {
+                    TokenType::Percent => match (left, right) {
                         (Object::Number(lhs), Object::Number(rhs)) => {
-                            Ok(Object::Boolean(lhs < rhs))
+                            self.check_divisor(operator, rhs)?;
+                            self.arithmetic_result(operator, lhs, rhs, lhs % rhs)
                         }
                         _ => Interpreter::number_operand_err(operator),
                     },
-                    TokenType::LessEqual => match (left, right) {
-                        (Object::Number(lhs), Object::Number(rhs)) => {
-                            Ok(Object::Boolean(lhs <= rhs))
+                    TokenType::Greater
+                    | TokenType::GreaterEqual
+                    | TokenType::Less
+                    | TokenType::LessEqual => Interpreter::compare(operator, &left, &right),
+                    TokenType::Is => match (left, right) {
+                        (
+                            Object::Instance(instance),
+                            Object::Callable(LoxCallable::LoxClass { class }),
+                        ) => Ok(Object::Boolean(instance.borrow().is_instance_of(&class))),
+                        (_, Object::Callable(LoxCallable::LoxClass { .. })) => {
+                            Ok(Object::Boolean(false))
                         }
-                        _ => Interpreter::number_operand_err(operator),
+                        _ => Err(Error::Runtime {
+                            token: operator.to_owned(),
+                            code: ErrorCode::InvalidClassOperand,
+                            message: String::from("Right operand of 'is' must be a class."),
+                        }),
                     },
-                    TokenType::BangEqual => Ok(Object::Boolean(!left.equals(&right))),
-                    TokenType::EqualEqual => Ok(Object::Boolean(left.equals(&right))),
+                    TokenType::BangEqual => Ok(Object::Boolean(
+                        !self.values_equal(&left, &right, operator)?,
+                    )),
+                    TokenType::EqualEqual => {
+                        Ok(Object::Boolean(self.values_equal(&left, &right, operator)?))
+                    }
                     _ => unreachable!(),
                 }
             }
             Expr::Ternary {
+                question,
                 condition,
                 then_branch,
                 else_branch,
             } => {
-                let cond_val = self.visit_expr(&condition)?;
+                let cond_val = self.visit_expr(*condition)?;
 
-                Ok(if Interpreter::is_truthy(&cond_val) {
-                    self.visit_expr(then_branch)?
+                Ok(if self.is_condition_true(question, &cond_val)? {
+                    self.visit_expr(*then_branch)?
                 } else {
-                    self.visit_expr(else_branch)?
+                    self.visit_expr(*else_branch)?
                 })
             }
-            Expr::Variable(name) => self.look_up_variable(name),
+            Expr::Variable(name) => self.look_up_variable(name, expr),
             Expr::Assign { name, value } => {
-                let value = self.visit_expr(value)?;
+                let value = self.visit_expr(*value)?;
 
-                if let Some(distance) = self.locals.get(name) {
+                if let Some((distance, slot)) = self.ast.local(expr) {
                     self.environment
                         .borrow_mut()
-                        .assign_at(*distance, name, value.clone())?;
+                        .assign_at(distance, slot, value.clone());
                 } else {
-                    self.globals.borrow_mut().assign(name, value.clone())?;
+                    let assigned = self.globals.borrow_mut().assign(name, value.clone());
+                    assigned.map_err(|error| self.suggest_name(error, name, expr))?;
                 }
 
+                self.emit_variable("assign", name, &value);
                 Ok(value)
             }
             Expr::Lambda { .. } => Ok(Object::Callable(LoxCallable::LoxFunction {
-                name: None,
-                definition: Box::new(expr.to_owned()),
-                closure: self.environment.clone(),
-                is_initializer: false,
+                function: Rc::new(LoxFunction {
+                    name: None,
+                    ast: ast.clone(),
+                    definition: expr,
+                    closure: self.environment.clone(),
+                    is_initializer: false,
+                    this: None,
+                }),
             })),
             Expr::Logical {
                 left,
                 operator,
                 right,
             } => {
-                let left = self.visit_expr(&left)?;
-                if operator.token_type == TokenType::Or {
-                    if Interpreter::is_truthy(&left) {
-                        return Ok(left);
-                    }
+                let left = self.visit_expr(*left)?;
+                let left_is_true = self.is_condition_true(operator, &left)?;
+                let short_circuits = if operator.token_type == TokenType::Or {
+                    left_is_true
                 } else {
-                    if !Interpreter::is_truthy(&left) {
-                        return Ok(left);
-                    }
+                    !left_is_true
+                };
+
+                if short_circuits {
+                    return Ok(if self.config.boolean_logic {
+                        Object::Boolean(left_is_true)
+                    } else {
+                        left
+                    });
                 }
 
-                Ok(self.visit_expr(&right)?)
+                let right = self.visit_expr(*right)?;
+                if self.config.boolean_logic {
+                    Ok(Object::Boolean(self.is_condition_true(operator, &right)?))
+                } else {
+                    if self.config.strict_booleans {
+                        self.is_condition_true(operator, &right)?;
+                    }
+
+                    Ok(right)
+                }
             }
             Expr::Call {
                 callee,
                 paren,
                 arguments,
             } => {
-                let callee = self.visit_expr(callee)?;
+                let (callee, this) = match &ast[*callee] {
+                    Expr::Get { object, name } => {
+                        let object = self.visit_expr(*object)?;
+                        self.method_callee(*callee, object, name)?
+                    }
+                    _ => (self.visit_expr(*callee)?, None),
+                };
 
-                let mut evaluated_arguments = Vec::new();
+                // Argument vectors are recycled instead of allocating a new
+                // one for every call.
+                let mut evaluated_arguments = self.argument_pool.pop().unwrap_or_default();
                 for argument in arguments {
-                    evaluated_arguments.push(self.visit_expr(argument)?);
+                    self.push_element(&mut evaluated_arguments, *argument)?;
                 }
 
-                if let Object::Callable(function) = callee {
-                    if evaluated_arguments.len() == function.arity() {
-                        Ok(function.call(self, &evaluated_arguments)?)
-                    } else {
-                        Err(Error::Runtime {
-                            token: paren.to_owned(),
-                            message: format!(
-                                "Expected {} arguments but got {}.",
-                                function.arity(),
-                                evaluated_arguments.len()
-                            ),
-                        })
-                    }
-                } else {
-                    Err(Error::Runtime {
-                        token: paren.to_owned(),
-                        message: String::from("Can only call functions and classes."),
-                    })
-                }
+                let result = self.call_with_this(&callee, this, paren, &evaluated_arguments);
+
+                evaluated_arguments.clear();
+                self.argument_pool.push(evaluated_arguments);
+
+                result
             }
             Expr::Get { object, name } => {
-                if let Object::Instance(instance) = self.visit_expr(&object)? {
-                    instance.borrow().get(name, &instance)
-                } else {
-                    Err(Error::Runtime {
-                        token: name.to_owned(),
-                        message: String::from("Only instances have properties."),
-                    })
-                }
+                let object = self.visit_expr(*object)?;
+                self.get_property(expr, object, name)
             }
             Expr::Set {
                 object,
                 name,
                 value,
-            } => {
-                if let Object::Instance(instance) = self.visit_expr(&object)? {
-                    let value = self.visit_expr(&value)?;
+            } => match self.visit_expr(*object)? {
+                Object::Instance(instance) => {
+                    let value = self.visit_expr(*value)?;
                     instance
                         .borrow_mut()
                         .set(name.lexeme.clone(), value.clone());
                     Ok(value)
-                } else {
-                    Err(Error::Runtime {
-                        token: name.to_owned(),
-                        message: String::from("Only instances have fields."),
-                    })
                 }
-            }
+                Object::Callable(LoxCallable::LoxClass { class }) => {
+                    let value = self.visit_expr(*value)?;
+                    class.set(name.lexeme.clone(), value.clone());
+                    Ok(value)
+                }
+                _ => Err(Error::Runtime {
+                    token: name.to_owned(),
+                    code: ErrorCode::NotAnInstance,
+                    message: String::from("Only instances have fields."),
+                }),
+            },
             Expr::Super { keyword, method } => {
                 // Can safely unwrap because the resolver guarantees that "super"
                 // is only used when there is a superclass
-                let distance = self.locals.get(keyword).unwrap().to_owned();
+                let (distance, _) = self.ast.local(expr).unwrap();
 
-                let superclass = self.environment.borrow().get_at(distance, "super")?;
-                let object = self.environment.borrow().get_at(distance - 1, "this")?;
+                // Both are alone in their environments, so they're in slot 0.
+                let superclass = self.environment.borrow().get_at(distance, 0);
+                let object = self.environment.borrow().get_at(distance - 1, 0);
 
                 if let Object::Callable(LoxCallable::LoxClass { class }) = superclass {
-                    if let Some(method) = class.find_method(&method.lexeme) {
-                        Ok(Object::Callable(method.bind(object)))
+                    if let Some(found) = self.find_method(expr, &class, &method.lexeme) {
+                        Ok(Object::Callable(found.bind(object)))
                     } else {
                         Err(Error::Runtime {
                             token: keyword.to_owned(),
+                            code: ErrorCode::UndefinedProperty,
                             message: format!("Undefined property '{}'.", method.lexeme),
                         })
                     }
@@ -322,28 +1201,89 @@ impl AstVisitor<Result<Object, Error>, Result<(), Error>> for Interpreter {
                     unreachable!()
                 }
             }
-            Expr::This(keyword) => self.look_up_variable(keyword),
+            Expr::This(keyword) => self.look_up_variable(keyword, expr),
+            Expr::List(elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    self.push_element(&mut values, *element)?;
+                }
+
+                Ok(Object::List(Rc::new(RefCell::new(values))))
+            }
+            // Spreads are evaluated by push_element(), as part of the call
+            // or list they're in.
+            Expr::Spread { .. } => unreachable!(),
+            Expr::Index {
+                object,
+                bracket,
+                index,
+            } => {
+                let object = self.visit_expr(*object)?;
+                let index = self.visit_expr(*index)?;
+
+                if let Object::List(list) = object {
+                    let list = list.borrow();
+                    let index = Interpreter::list_index(&list, bracket, &index)?;
+                    Ok(list[index].clone())
+                } else {
+                    Err(Error::Runtime {
+                        token: bracket.to_owned(),
+                        code: ErrorCode::NotIndexable,
+                        message: String::from("Only lists can be indexed."),
+                    })
+                }
+            }
+            Expr::IndexSet {
+                object,
+                bracket,
+                index,
+                value,
+            } => {
+                let object = self.visit_expr(*object)?;
+                let index = self.visit_expr(*index)?;
+                let value = self.visit_expr(*value)?;
+
+                if let Object::List(list) = object {
+                    let mut list = list.borrow_mut();
+                    let index = Interpreter::list_index(&list, bracket, &index)?;
+                    list[index] = value.clone();
+                    Ok(value)
+                } else {
+                    Err(Error::Runtime {
+                        token: bracket.to_owned(),
+                        code: ErrorCode::NotIndexable,
+                        message: String::from("Only lists can be indexed."),
+                    })
+                }
+            }
         }
     }
 
     fn visit_stmt(&mut self, stmt: &Stmt) -> Result<(), Error> {
+        self.start_statement(stmt);
+
         match stmt {
-            Stmt::Expression(expression) => {
-                self.visit_expr(expression)?;
+            Stmt::Expression { expression, .. } => {
+                self.visit_expr(*expression)?;
                 Ok(())
             }
-            Stmt::Print(expression) => {
-                let value = self.visit_expr(expression)?;
-                println!("{value}");
+            Stmt::Print {
+                keyword,
+                expression,
+            } => {
+                let value = self.visit_expr(*expression)?;
+                let text = self.stringify(&value, keyword)?;
+                writeln!(self.output, "{text}").ok();
                 Ok(())
             }
             Stmt::Var { name, initializer } => {
                 let value = if let Some(expr) = initializer {
-                    self.visit_expr(expr)?
+                    self.visit_expr(*expr)?
                 } else {
                     Object::Nil
                 };
 
+                self.emit_variable("define", name, &value);
                 self.environment
                     .borrow_mut()
                     .define(name.lexeme.clone(), value);
@@ -351,21 +1291,19 @@ impl AstVisitor<Result<Object, Error>, Result<(), Error>> for Interpreter {
                 Ok(())
             }
             Stmt::Block(statements) => {
-                self.execute_block(
-                    statements,
-                    Rc::new(RefCell::new(Environment::new_local(
-                        self.environment.clone(),
-                    ))),
-                )?;
-                Ok(())
+                let environment = self.new_environment(self.environment.clone());
+                let result = self.execute_block(statements, environment.clone());
+                self.recycle_environment(environment);
+                result
             }
             Stmt::If {
+                keyword,
                 condition,
                 then_branch,
                 else_branch,
             } => {
-                let condition = self.visit_expr(condition)?;
-                if Interpreter::is_truthy(&condition) {
+                let condition = self.visit_expr(*condition)?;
+                if self.is_condition_true(keyword, &condition)? {
                     self.visit_stmt(then_branch)?;
                 } else if let Some(statement) = else_branch {
                     self.visit_stmt(statement)?;
@@ -373,8 +1311,18 @@ impl AstVisitor<Result<Object, Error>, Result<(), Error>> for Interpreter {
 
                 Ok(())
             }
-            Stmt::While { condition, body } => {
-                while Interpreter::is_truthy(&self.visit_expr(condition)?) {
+            Stmt::While {
+                keyword,
+                condition,
+                body,
+            } => {
+                loop {
+                    self.checkpoint(keyword)?;
+                    let condition = self.visit_expr(*condition)?;
+                    if !self.is_condition_true(keyword, &condition)? {
+                        break;
+                    }
+
                     self.visit_stmt(body)?;
                 }
 
@@ -382,40 +1330,60 @@ impl AstVisitor<Result<Object, Error>, Result<(), Error>> for Interpreter {
             }
             Stmt::Function { name, definition } => {
                 let function = LoxCallable::LoxFunction {
-                    name: Some(name.to_owned()),
-                    definition: Box::new(definition.to_owned()),
-                    closure: self.environment.clone(),
-                    is_initializer: false,
+                    function: Rc::new(LoxFunction {
+                        name: Some(name.to_owned()),
+                        ast: self.ast.clone(),
+                        definition: *definition,
+                        closure: self.environment.clone(),
+                        is_initializer: false,
+                        this: None,
+                    }),
                 };
 
+                let function = Object::Callable(function);
+                self.emit_variable("define", name, &function);
                 self.environment
                     .borrow_mut()
-                    .define(name.lexeme.to_owned(), Object::Callable(function));
+                    .define(name.lexeme.to_owned(), function);
 
                 Ok(())
             }
             Stmt::Return { value, .. } => {
                 let value = if let Some(return_value) = value {
-                    self.visit_expr(return_value)?
+                    self.visit_expr(*return_value)?
                 } else {
                     Object::Nil
                 };
 
                 Err(Error::Return(value))
             }
+            Stmt::Delete { object, name } => {
+                if let Object::Instance(instance) = self.visit_expr(*object)? {
+                    instance.borrow_mut().remove(name)?;
+                    Ok(())
+                } else {
+                    Err(Error::Runtime {
+                        token: name.to_owned(),
+                        code: ErrorCode::NotAnInstance,
+                        message: String::from("Only instances have fields."),
+                    })
+                }
+            }
             Stmt::Class {
                 name,
                 superclass,
                 methods,
+                statics,
             } => {
                 let superclass_ref = if let Some(class_expr) = superclass {
                     if let Object::Callable(LoxCallable::LoxClass { class }) =
-                        self.visit_expr(class_expr)?
+                        self.visit_expr(*class_expr)?
                     {
                         Some(class.clone())
-                    } else if let Expr::Variable(var) = class_expr {
+                    } else if let Expr::Variable(var) = &self.ast[*class_expr] {
                         return Err(Error::Runtime {
                             token: var.to_owned(),
+                            code: ErrorCode::SuperclassNotAClass,
                             message: String::from("Superclass must be a class."),
                         });
                     } else {
@@ -425,33 +1393,33 @@ impl AstVisitor<Result<Object, Error>, Result<(), Error>> for Interpreter {
                     None
                 };
 
-                self.environment
-                    .borrow_mut()
-                    .define(name.lexeme.clone(), Object::Nil);
-
                 if let Some(class) = &superclass_ref {
                     self.environment = Rc::new(RefCell::new(Environment::new_local(
                         self.environment.clone(),
                     )));
 
                     self.environment.borrow_mut().define(
-                        String::from("super"),
+                        "super".into(),
                         Object::Callable(LoxCallable::LoxClass {
                             class: class.clone(),
                         }),
                     )
                 }
 
-                let mut method_map: HashMap<String, LoxCallable> = HashMap::new();
+                let mut method_map: HashMap<Rc<str>, LoxCallable> = HashMap::new();
                 for method in methods {
                     if let Stmt::Function { name, definition } = method {
                         let func = LoxCallable::LoxFunction {
-                            name: Some(name.to_owned()),
-                            definition: Box::new(definition.to_owned()),
-                            closure: self.environment.clone(),
-                            is_initializer: name.lexeme.eq("init"),
+                            function: Rc::new(LoxFunction {
+                                name: Some(name.to_owned()),
+                                ast: self.ast.clone(),
+                                definition: *definition,
+                                closure: self.environment.clone(),
+                                is_initializer: &*name.lexeme == "init",
+                                this: None,
+                            }),
                         };
-                        method_map.insert(name.lexeme.to_owned(), func);
+                        method_map.insert(name.lexeme.clone(), func);
                     }
                 }
 
@@ -460,19 +1428,45 @@ impl AstVisitor<Result<Object, Error>, Result<(), Error>> for Interpreter {
                     self.environment = enclosing;
                 }
 
-                self.environment.borrow_mut().assign(
-                    name,
-                    Object::Callable(LoxCallable::LoxClass {
-                        class: Rc::new(LoxClass::new(
-                            name.lexeme.clone(),
-                            superclass_ref,
-                            method_map,
-                        )),
-                    }),
-                )?;
+                let class = Rc::new(LoxClass::new(
+                    name.lexeme.to_string(),
+                    superclass_ref,
+                    method_map,
+                    HashMap::new(),
+                ));
+                let object = Object::Callable(LoxCallable::LoxClass {
+                    class: class.clone(),
+                });
+                self.emit_variable("define", name, &object);
+                // Methods look the class up when they run, so it's only
+                // defined once it's built. Static initializers run after, as
+                // the resolver expects, so they can refer to the class.
+                self.environment
+                    .borrow_mut()
+                    .define(name.lexeme.clone(), object);
+
+                for field in statics {
+                    if let Stmt::Var { name, initializer } = field {
+                        let value = if let Some(expr) = initializer {
+                            self.visit_expr(*expr)?
+                        } else {
+                            Object::Nil
+                        };
+                        class.set(name.lexeme.clone(), value);
+                    }
+                }
 
                 Ok(())
             }
         }
     }
 }
+
+fn json_string(string: &str) -> String {
+    render(&Object::String(string.into()), Style::Json)
+}
+
+// The token's byte offsets in the source, as `[start, end]`.
+fn json_span(token: &Token) -> String {
+    format!("[{}, {}]", token.span.start, token.span.end)
+}
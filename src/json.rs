@@ -0,0 +1,225 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::ast::Object;
+
+// Deeper arrays would overflow the stack of the recursive parser.
+const MAX_DEPTH: usize = 512;
+
+// Parses a JSON document into Lox values: `null` is nil and arrays are lists.
+// Lox has no maps yet, so objects are an error instead of a value.
+pub fn parse(text: &str) -> Result<Object, String> {
+    let mut parser = JsonParser { text, position: 0 };
+    parser.skip_whitespace();
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.position < text.len() {
+        return Err(parser.unexpected());
+    }
+
+    Ok(value)
+}
+
+struct JsonParser<'a> {
+    text: &'a str,
+    // A byte offset into `text`.
+    position: usize,
+}
+
+impl JsonParser<'_> {
+    fn value(&mut self, depth: usize) -> Result<Object, String> {
+        match self.peek() {
+            Some('n') => self.keyword("null", Object::Nil),
+            Some('t') => self.keyword("true", Object::Boolean(true)),
+            Some('f') => self.keyword("false", Object::Boolean(false)),
+            Some('"') => Ok(Object::String(self.string()?.into())),
+            Some('-' | '0'..='9') => self.number(),
+            Some('[') => self.array(depth),
+            Some('{') => Err(String::from("JSON objects aren't supported yet.")),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Object) -> Result<Object, String> {
+        if self.text[self.position..].starts_with(keyword) {
+            self.position += keyword.len();
+            Ok(value)
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Object, String> {
+        if depth == MAX_DEPTH {
+            return Err(format!(
+                "JSON arrays can't be nested more than {MAX_DEPTH} deep."
+            ));
+        }
+
+        self.position += 1;
+        self.skip_whitespace();
+        let mut elements = Vec::new();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Object::List(Rc::new(RefCell::new(elements))));
+        }
+
+        loop {
+            elements.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.position += 1;
+                    self.skip_whitespace();
+                }
+                Some(']') => {
+                    self.position += 1;
+                    break;
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+
+        Ok(Object::List(Rc::new(RefCell::new(elements))))
+    }
+
+    // JSON numbers are a subset of what f64's parser accepts, so their
+    // syntax is checked before converting them.
+    fn number(&mut self) -> Result<Object, String> {
+        let start = self.position;
+        self.eat('-');
+        if !self.eat('0') && !self.digits() {
+            return Err(self.unexpected());
+        }
+        if self.eat('.') && !self.digits() {
+            return Err(self.unexpected());
+        }
+        if self.eat('e') || self.eat('E') {
+            let _ = self.eat('+') || self.eat('-');
+            if !self.digits() {
+                return Err(self.unexpected());
+            }
+        }
+
+        Ok(Object::Number(
+            self.text[start..self.position].parse().unwrap(),
+        ))
+    }
+
+    // Consumes a run of digits, returning whether there was one.
+    fn digits(&mut self) -> bool {
+        let start = self.position;
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.position += 1;
+        }
+
+        self.position > start
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.position += 1;
+        let mut string = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.position += 1;
+                    return Ok(string);
+                }
+                Some('\\') => {
+                    self.position += 1;
+                    string.push(self.escape()?);
+                }
+                // Control characters must be escaped.
+                Some(c) if c >= ' ' => {
+                    self.position += c.len_utf8();
+                    string.push(c);
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    // The character escaped after a backslash.
+    fn escape(&mut self) -> Result<char, String> {
+        let c = match self.peek() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                self.position += 1;
+                return self.unicode_escape();
+            }
+            _ => return Err(self.unexpected()),
+        };
+
+        self.position += 1;
+        Ok(c)
+    }
+
+    // Characters outside the Basic Multilingual Plane are escaped as a pair
+    // of surrogates, as in "\ud83d\ude00".
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let start = self.position - 2;
+        let high = self.hex_digits()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if !self.text[self.position..].starts_with("\\u") {
+                return Err(self.invalid_escape(start));
+            }
+            self.position += 2;
+            let low = self.hex_digits()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.invalid_escape(start));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+
+        char::from_u32(code).ok_or_else(|| self.invalid_escape(start))
+    }
+
+    fn hex_digits(&mut self) -> Result<u32, String> {
+        let digits = self
+            .text
+            .get(self.position..self.position + 4)
+            .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| self.unexpected())?;
+        self.position += 4;
+        Ok(u32::from_str_radix(digits, 16).unwrap())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn unexpected(&self) -> String {
+        match self.peek() {
+            Some(c) => format!("Unexpected {c:?} in JSON at byte {}.", self.position),
+            None => String::from("Unexpected end of JSON."),
+        }
+    }
+
+    fn invalid_escape(&self, start: usize) -> String {
+        format!("Invalid Unicode escape in JSON at byte {start}.")
+    }
+}
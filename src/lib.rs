@@ -1,76 +1,824 @@
 mod ast;
+mod ast_printer;
 mod callable;
+#[cfg(feature = "capi")]
+mod capi;
 mod class;
+mod coverage;
+mod crash;
+mod doctest;
+mod dot_printer;
 mod environment;
 mod error;
+mod error_code;
+mod formatter;
 mod interpreter;
+mod json;
+mod lint;
+mod natives;
 mod parser;
+mod render;
 mod resolver;
 mod scanner;
+mod snapshot;
+mod suggestion;
 mod token;
+#[cfg(feature = "wasm")]
+mod wasm;
 
+use ast::ListCopies;
+use ast_printer::AstPrinter;
+use dot_printer::DotPrinter;
 use error::Error;
+use formatter::Formatter;
+use lint::Linter;
 use parser::Parser;
 use resolver::Resolver;
 use scanner::Scanner;
 use std::{
-    fs,
-    io::{self, Write},
-    process,
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::{self, BufWriter, IsTerminal, Read, Write},
+    rc::Rc,
 };
 
+pub use crate::ast::{Ast, AstVisitor, ConversionError, Expr, ExprId, Object, Stmt};
+#[cfg(feature = "capi")]
+pub use crate::capi::{lox_eval, lox_free, lox_get_string_result, lox_new, Lox};
+pub use crate::coverage::CoverageFormat;
+pub use crate::error::{
+    ColorChoice, Diagnostic, ErrorReporter, RunError, Severity, Snippet, StderrReporter,
+};
+pub use crate::error_code::ErrorCode;
 use crate::interpreter::Interpreter;
+pub use crate::interpreter::{CancelHandle, InterpreterConfig, NativePolicy};
+pub use crate::lint::LintLevel;
+pub use crate::natives::{NativeContext, NativeError};
+pub use crate::render::{render, Style};
+pub use crate::snapshot::SnapshotError;
+pub use crate::token::{Token, TokenType};
+#[cfg(feature = "wasm")]
+pub use crate::wasm::{run, RunResult};
 
+/// Scans, parses, checks and runs Lox programs, keeping the globals they
+/// define between runs.
+///
+/// It isn't `Send`: values and environments are shared through `Rc`, so an
+/// instance must be created on the thread that uses it, as the command line
+/// does to run scripts with a larger stack.
 pub struct RustLox {
     interpreter: Interpreter,
+    linter: Linter,
+    flags: HashSet<String>,
+    show_changes: bool,
+    // Where the phases that run a script report errors and warnings.
+    reporting: Reporting,
+    // Where the REPL loads its globals from and saves them to.
+    session: Option<String>,
+    // Where `run_file` writes a coverage report, and in which format.
+    coverage_report: Option<(String, CoverageFormat)>,
+    // Parsed and resolved scripts, so running a file again skips those steps
+    // if it hasn't changed. The least recently run one is dropped to make
+    // room past PROGRAM_CACHE_SIZE.
+    programs: HashMap<u64, CachedProgram>,
+    programs_run: u64,
+    // Where the IDs of the next parsed program's expressions start.
+    next_expr_id: u32,
+    // The program `run_steps` goes through, and how many of its top-level
+    // statements have run.
+    stepping: Option<(Rc<Ast>, usize)>,
+}
+
+/// Whether a program started with `RustLox::start` has finished.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Some of its top-level statements haven't run yet.
+    Pending,
+    /// All of them ran, or there's no program to run.
+    Done,
+}
+
+struct Reporting {
+    // Whether diagnostics are colored.
+    color: bool,
+    // Where diagnostics go instead of standard error while `run` runs.
+    collected: Option<Vec<Diagnostic>>,
+    // Where diagnostics go otherwise, if not to standard error.
+    reporter: Option<Box<dyn ErrorReporter>>,
+}
+
+impl ErrorReporter for Reporting {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        match (&mut self.collected, &mut self.reporter) {
+            (Some(collected), _) => collected.push(diagnostic),
+            (None, Some(reporter)) => reporter.report(diagnostic),
+            (None, None) => StderrReporter { color: self.color }.report(diagnostic),
+        }
+    }
+}
+
+struct CachedProgram {
+    source: String,
+    ast: Rc<Ast>,
+    // The value of `programs_run` when it last ran.
+    last_run: u64,
+}
+
+const PROGRAM_CACHE_SIZE: usize = 16;
+
+/// Configures a `RustLox` before it is created, such as which natives scripts
+/// can use. Start one with `RustLox::builder()`.
+pub struct RustLoxBuilder {
+    config: InterpreterConfig,
+}
+
+impl RustLoxBuilder {
+    /// Replaces the whole configuration, including anything set before.
+    pub fn config(mut self, config: InterpreterConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Whether readLine(), readFile() and writeFile() are defined.
+    pub fn allow_io(mut self, allow: bool) -> Self {
+        self.config.allow_io = allow;
+        self
+    }
+
+    /// Whether clock() and sleep() are defined.
+    pub fn allow_clock(mut self, allow: bool) -> Self {
+        self.config.allow_clock = allow;
+        self
+    }
+
+    /// Whether getenv() is defined.
+    pub fn allow_env(mut self, allow: bool) -> Self {
+        self.config.allow_env = allow;
+        self
+    }
+
+    /// Leaves out every native that reaches outside the interpreter, for
+    /// running untrusted scripts.
+    pub fn sandboxed(self) -> Self {
+        self.allow_io(false).allow_clock(false).allow_env(false)
+    }
+
+    pub fn build(self) -> RustLox {
+        RustLox::with_config(self.config)
+    }
 }
 
 impl RustLox {
     pub fn new() -> Self {
+        RustLox::with_config(InterpreterConfig::default())
+    }
+
+    pub fn builder() -> RustLoxBuilder {
+        RustLoxBuilder {
+            config: InterpreterConfig::default(),
+        }
+    }
+
+    pub fn with_config(config: InterpreterConfig) -> Self {
         Self {
-            interpreter: Interpreter::new(),
+            interpreter: Interpreter::new(config),
+            linter: Linter::new(),
+            flags: HashSet::new(),
+            show_changes: false,
+            reporting: Reporting {
+                color: io::stderr().is_terminal(),
+                collected: None,
+                reporter: None,
+            },
+            session: None,
+            coverage_report: None,
+            programs: HashMap::new(),
+            programs_run: 0,
+            next_expr_id: 0,
+            stepping: None,
+        }
+    }
+
+    /// Enables a flag for "#if" directives in the scripts run afterwards.
+    pub fn set_flag(&mut self, flag: &str) {
+        self.flags.insert(flag.to_owned());
+    }
+
+    /// Sets the arguments scripts can read through the argCount() and arg()
+    /// natives.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.interpreter.set_script_args(args);
+    }
+
+    /// Makes the REPL print the globals created (`+`) or modified (`~`) by
+    /// each input.
+    pub fn show_changes(&mut self) {
+        self.show_changes = true;
+        self.interpreter.globals.borrow_mut().track_changes();
+    }
+
+    /// Sets how the lint called `name`, or every lint for "all", reports
+    /// its findings. Returns false if there's no such lint.
+    pub fn set_lint_level(&mut self, name: &str, level: LintLevel) -> bool {
+        self.linter.set_level(name, level)
+    }
+
+    /// Turns on the lint called `name`, or every lint for "all", as a
+    /// warning. Lints that are already on keep their level. Returns false if
+    /// there's no such lint.
+    pub fn enable_lint(&mut self, name: &str) -> bool {
+        self.linter.enable(name)
+    }
+
+    /// Defines a global native function called `name`, which takes `arity`
+    /// arguments. Defining it again, or defining a global of the same name
+    /// later, replaces it. `call_impl` can be a closure holding state for the
+    /// native; since natives can be called again from the Lox code they call,
+    /// state that changes must be in a `Cell` or `RefCell`.
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        call_impl: impl Fn(&mut NativeContext, &[Object]) -> Result<Object, NativeError> + 'static,
+    ) {
+        self.interpreter
+            .define_native(name, arity, Rc::new(call_impl));
+    }
+
+    /// Sets when errors and warnings are colored. By default, they are if
+    /// standard error is a terminal.
+    pub fn set_color(&mut self, choice: ColorChoice) {
+        self.reporting.color = match choice {
+            ColorChoice::Auto => io::stderr().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        };
+    }
+
+    /// Sends the errors and warnings found from now on to `reporter`
+    /// instead of standard error. `run` and `eval` still return theirs.
+    pub fn set_reporter(&mut self, reporter: impl ErrorReporter + 'static) {
+        self.reporting.reporter = Some(Box::new(reporter));
+    }
+
+    /// Makes `run_file` write a report of how many times the statements on
+    /// each line of the script ran to `path`, even if the script fails.
+    pub fn set_coverage_report(&mut self, path: &str, format: CoverageFormat) {
+        self.coverage_report = Some((path.to_owned(), format));
+        self.interpreter.enable_coverage();
+    }
+
+    /// Restricts the natives available to the scripts run afterwards.
+    pub fn set_native_policy(&mut self, policy: NativePolicy) {
+        self.interpreter.set_native_policy(policy);
+    }
+
+    /// Makes the interpreter write an event to `writer` whenever a scope is
+    /// entered or exited, a variable is defined or assigned, or a function
+    /// is called or returns. Each event is a line of JSON.
+    pub fn set_event_writer(&mut self, writer: impl Write + 'static) {
+        self.interpreter.set_event_writer(Box::new(writer));
+    }
+
+    /// A handle that stops the script this interpreter is running, which
+    /// can be sent to another thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.interpreter.cancel_handle()
+    }
+
+    /// Sets the global variable `name` for the scripts run afterwards,
+    /// defining it if it doesn't exist.
+    pub fn set_global(&mut self, name: &str, value: impl Into<Object>) {
+        self.interpreter.set_global(name, value.into());
+    }
+
+    /// The value of the global variable `name`, if it's defined.
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        self.interpreter.get_global(name)
+    }
+
+    /// The global variables as a script that defines them again, to be
+    /// passed to `restore`, possibly in another process. Natives aren't
+    /// saved, and lists shared between variables are restored as copies.
+    pub fn snapshot(&self) -> Result<String, SnapshotError> {
+        snapshot::snapshot(&self.interpreter.globals)
+    }
+
+    /// Defines the global variables saved by `snapshot`, replacing ones with
+    /// the same names.
+    pub fn restore(&mut self, snapshot: &str) -> Result<(), Vec<Diagnostic>> {
+        self.run(snapshot).map(|_| ())
+    }
+
+    /// Makes the REPL start with the globals saved in `path`, if it exists,
+    /// and save them there when it ends.
+    pub fn set_session(&mut self, path: &str) {
+        self.session = Some(path.to_owned());
+    }
+
+    /// Sends what scripts print, with `print` or write(), to `writer`
+    /// instead of standard output.
+    pub fn set_output(&mut self, writer: impl Write + 'static) {
+        self.interpreter.set_output(Box::new(writer));
+    }
+
+    fn print_changes(&mut self) {
+        let changes = self.interpreter.globals.borrow_mut().take_changes();
+
+        let mut printed = HashSet::new();
+        for (name, created) in &changes {
+            // The first change to a name tells whether this input created it,
+            // but it's shown with its latest value.
+            if !printed.insert(name) {
+                continue;
+            }
+
+            let marker = if *created { "+" } else { "~" };
+            let value = self.interpreter.globals.borrow().value(name).cloned();
+            if let Some(value) = value {
+                let output = self.interpreter.output();
+                writeln!(
+                    output,
+                    "{marker} {name} = {}",
+                    render(&value, Style::Quoted)
+                )
+                .ok();
+            }
+        }
+        self.interpreter.output().flush().ok();
+    }
+
+    /// Forgets the scripts kept to speed up running them again.
+    pub fn clear_program_cache(&mut self) {
+        self.programs.clear();
+    }
+
+    /// Runs `source` as a program. The errors and warnings found while
+    /// scanning, parsing, resolving and running it are returned instead of
+    /// printed, as an `Err` if there's an error among them.
+    pub fn run(&mut self, source: &str) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+        let (result, diagnostics) = self.collecting(|lox| lox.run_source(source, false));
+        match result {
+            Ok(_) => Ok(diagnostics),
+            Err(_) => Err(diagnostics),
         }
     }
 
-    fn run(&mut self, source: &str) -> Result<(), Error> {
-        let mut scanner = Scanner::new(source);
-        let (tokens, lexical_error) = scanner.scan_tokens();
+    /// Runs `source` as a program and returns its value, the value of the
+    /// expression statement it ends with or nil otherwise, so
+    /// `eval("var a = 2; a * 3;")` is 6. Errors are returned with the
+    /// warnings found along with them instead of printed; the program's
+    /// output still goes to standard output.
+    pub fn eval(&mut self, source: &str) -> Result<Object, Vec<Diagnostic>> {
+        let (result, diagnostics) = self.collecting(|lox| lox.run_source(source, false));
+        result.map_err(|_| diagnostics)
+    }
+
+    /// Scans and parses a script without running it, for tools built on its
+    /// syntax tree. Fails with the errors found instead.
+    pub fn parse(&mut self, source: &str) -> Result<Ast, Vec<Diagnostic>> {
+        let (parsed, diagnostics) = self.collecting(|lox| {
+            let (tokens, lexical_error) = lox.scan(source);
+            let parsed = Parser::new(&tokens, source, 0, &mut lox.reporting).parse();
 
-        let mut parser = Parser::new(tokens);
-        let statements = parser.parse()?;
+            match parsed {
+                Ok(mut ast) if !lexical_error => {
+                    ast.source = Some(source.into());
+                    Some(ast)
+                }
+                _ => None,
+            }
+        });
+
+        parsed.ok_or(diagnostics)
+    }
+
+    /// Scans, parses and checks `source` to run it a few statements at a
+    /// time with `run_steps`, replacing the program started before. The
+    /// errors and warnings found are returned like `run` does.
+    pub fn start(&mut self, source: &str) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+        self.stepping = None;
+        let (result, diagnostics) = self.collecting(|lox| {
+            lox.guarded(source, |lox, tokens, ast| {
+                lox.compile(source, None, tokens, ast)
+            })
+        });
+
+        match result {
+            Ok(program) => {
+                self.stepping = Some((program, 0));
+                Ok(diagnostics)
+            }
+            Err(_) => Err(diagnostics),
+        }
+    }
+
+    /// Runs at most `n` more top-level statements of the program given to
+    /// `start`, so a host without threads can run it between its own work.
+    /// A statement runs to its end, loops and calls included; the step and
+    /// time limits apply to each call on their own and can bound it. A
+    /// runtime error ends the program and is returned.
+    pub fn run_steps(&mut self, n: usize) -> Result<Step, Vec<Diagnostic>> {
+        let Some((program, next)) = self.stepping.take() else {
+            return Ok(Step::Done);
+        };
+
+        let end = next.saturating_add(n).min(program.statements.len());
+        let source = program.source.clone().unwrap_or_default();
+        let (result, diagnostics) = self.collecting(|lox| {
+            lox.guarded(&source, |lox, _, ast| {
+                let program = ast.insert(program.clone());
+                lox.interpreter
+                    .interpret_statements(program, next..end, &mut lox.reporting)
+            })
+        });
+
+        match result {
+            Err(_) => Err(diagnostics),
+            Ok(_) if end == program.statements.len() => Ok(Step::Done),
+            Ok(_) => {
+                self.stepping = Some((program, end));
+                Ok(Step::Pending)
+            }
+        }
+    }
+
+    fn collecting<T>(&mut self, run: impl FnOnce(&mut Self) -> T) -> (T, Vec<Diagnostic>) {
+        self.reporting.collected = Some(Vec::new());
+        let result = run(self);
+        (result, self.reporting.collected.take().unwrap())
+    }
+
+    fn run_source(&mut self, source: &str, cache: bool) -> Result<Object, Error> {
+        self.run_scanned(source, None, cache)
+    }
+
+    // Runs a source that may have been scanned already, as it was read,
+    // along with whether it had lexical errors.
+    fn run_scanned(
+        &mut self,
+        source: &str,
+        scanned: Option<(Vec<Token>, bool)>,
+        cache: bool,
+    ) -> Result<Object, Error> {
+        self.guarded(source, |lox, tokens, ast| {
+            lox.run_stages(source, scanned, cache, tokens, ast)
+        })
+    }
+
+    // Runs stages of running `source`, reporting it if they crash. They keep
+    // the tokens and AST they get to for the crash report.
+    fn guarded<T>(
+        &mut self,
+        source: &str,
+        stages: impl FnOnce(
+            &mut Self,
+            &mut Option<Vec<Token>>,
+            &mut Option<Rc<Ast>>,
+        ) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut tokens = None;
+        let mut ast = None;
+        let result = crash::catch(|| stages(self, &mut tokens, &mut ast));
+
+        result.unwrap_or_else(|message| {
+            let report = crash::Report {
+                message,
+                source,
+                tokens: tokens.as_deref(),
+                ast: ast.as_deref(),
+                state: self.interpreter.state_summary(),
+            };
+
+            let notice = match crash::write_report(&report) {
+                Ok(path) => format!(
+                    "A crash report was written to {}, please attach it to your bug report.",
+                    path.display()
+                ),
+                Err(err) => format!("{}\nCouldn't write a crash report: {err}", report.message),
+            };
+            self.reporting.report(Diagnostic {
+                severity: Severity::Error,
+                line: 0,
+                column: 0,
+                message: format!("Error: Internal error, this is a bug in rustlox. {notice}"),
+                code: Some(ErrorCode::InternalError),
+                snippet: None,
+            });
+
+            self.interpreter.reset_scope();
+            Err(Error::Internal)
+        })
+    }
+
+    // The tokens and AST are kept for the crash report. With `cache`,
+    // the program is looked up in and added to the program cache.
+    fn run_stages(
+        &mut self,
+        source: &str,
+        scanned: Option<(Vec<Token>, bool)>,
+        cache: bool,
+        tokens: &mut Option<Vec<Token>>,
+        ast: &mut Option<Rc<Ast>>,
+    ) -> Result<Object, Error> {
+        let key = cache.then(|| self.program_key(source));
+        self.programs_run += 1;
+        if let Some(program) = key.and_then(|key| self.programs.get_mut(&key)) {
+            if program.source == source {
+                program.last_run = self.programs_run;
+                let program = ast.insert(program.ast.clone()).clone();
+                return self.interpreter.interpret(&program, &mut self.reporting);
+            }
+        }
+
+        let program = self.compile(source, scanned, tokens, ast)?;
+
+        if let Some(key) = key {
+            if self.programs.len() >= PROGRAM_CACHE_SIZE && !self.programs.contains_key(&key) {
+                let oldest = self
+                    .programs
+                    .iter()
+                    .min_by_key(|(_, program)| program.last_run);
+                if let Some((&oldest, _)) = oldest {
+                    self.programs.remove(&oldest);
+                }
+            }
+
+            let source = source.to_owned();
+            let ast = program.clone();
+            let last_run = self.programs_run;
+            self.programs.insert(
+                key,
+                CachedProgram {
+                    source,
+                    ast,
+                    last_run,
+                },
+            );
+        }
+
+        self.interpreter.interpret(&program, &mut self.reporting)
+    }
+
+    // Scans, parses and checks the source, reporting the errors found.
+    fn compile(
+        &mut self,
+        source: &str,
+        scanned: Option<(Vec<Token>, bool)>,
+        tokens: &mut Option<Vec<Token>>,
+        ast: &mut Option<Rc<Ast>>,
+    ) -> Result<Rc<Ast>, Error> {
+        let (scanned, lexical_error) = match scanned {
+            Some(scanned) => scanned,
+            None => self.scan(source),
+        };
+        // Kept for the crash report.
+        let scanned = tokens.insert(scanned);
+
+        let mut parser = Parser::new(scanned, source, self.next_expr_id, &mut self.reporting);
+        let mut parsed = parser.parse()?;
+        parsed.source = Some(source.into());
+        let program = ast.insert(Rc::new(parsed)).clone();
+        self.next_expr_id = program.next_id();
 
         if lexical_error {
             return Err(Error::Lexical);
         }
 
-        let mut resolver = Resolver::new(&mut self.interpreter);
-        resolver.resolve(&statements);
+        if !self.check(&program) {
+            return Err(Error::Semantic);
+        }
+
+        Ok(program)
+    }
+
+    // Scans the source, reporting the errors found. Returns the tokens, moved
+    // out of the scanner rather than copied, and whether there were errors.
+    fn scan(&mut self, source: &str) -> (Vec<Token>, bool) {
+        let mut scanner = Scanner::new(source, &self.flags, &mut self.reporting);
+        let (_, lexical_error) = scanner.scan_tokens();
+        (scanner.into_tokens(), lexical_error)
+    }
+
+    // Resolves and lints the program, reporting what's found. Returns
+    // whether it can run.
+    fn check(&mut self, program: &Ast) -> bool {
+        let mut resolver = Resolver::new(program, &mut self.reporting);
+        resolver.resolve(&program.statements);
+        let had_error = resolver.had_error;
+
+        let lint_error = self
+            .linter
+            .check(program, &program.statements, &mut self.reporting);
+        !had_error && !lint_error
+    }
+
+    // The flags are part of the key since "#if" directives depend on them.
+    fn program_key(&self, source: &str) -> u64 {
+        let mut flags: Vec<&String> = self.flags.iter().collect();
+        flags.sort();
 
-        if resolver.had_error {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        flags.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // Runtime errors are left for the caller to report.
+    fn evaluate(&mut self, source: &str) -> Result<Object, Error> {
+        let (tokens, lexical_error) = self.scan(source);
+
+        let mut parser = Parser::new(&tokens, source, self.next_expr_id, &mut self.reporting);
+        let (mut ast, expr) = parser.parse_expression()?;
+        ast.source = Some(source.into());
+        self.next_expr_id = ast.next_id();
+
+        if lexical_error {
+            return Err(Error::Lexical);
+        }
+
+        if !self.check(&ast) {
             return Err(Error::Semantic);
         }
 
-        self.interpreter.interpret(&statements)?;
+        self.interpreter.evaluate(&Rc::new(ast), expr)
+    }
+
+    /// Runs the script and then checks every example in its doc comments,
+    /// returning whether all of them passed.
+    pub fn run_doctests(&mut self, file_path: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let source = read_source(file_path)?;
+        if self.run_source(&source, true).is_err() {
+            return Ok(false);
+        }
+
+        let mut failed = 0;
+        let examples = doctest::extract(&source);
+        let globals = self.interpreter.globals.borrow().values();
+        for example in &examples {
+            // Every example starts from the globals the script left, whatever
+            // the ones before it assigned or changed in the lists they hold.
+            let mut copies = ListCopies::new();
+            let fresh = globals
+                .iter()
+                .map(|(name, value)| (name.clone(), value.deep_copy_with(&mut copies)))
+                .collect();
+            self.interpreter.globals.borrow_mut().restore(fresh);
+
+            let result = self.evaluate(&example.source);
+            let output = self.interpreter.output();
+            let location = format!("{}:{}", file_path, example.line);
+            match result {
+                Ok(value) if value.to_string() == example.expected => {
+                    writeln!(output, "test {location} ... ok")?;
+                }
+                Ok(value) => {
+                    failed += 1;
+                    writeln!(output, "test {location} ... FAILED")?;
+                    writeln!(output, "    >>> {}", example.source)?;
+                    writeln!(output, "    expected: {}", example.expected)?;
+                    writeln!(output, "    got: {value}")?;
+                }
+                Err(Error::Runtime { message, .. }) => {
+                    failed += 1;
+                    writeln!(output, "test {location} ... FAILED")?;
+                    writeln!(output, "    >>> {}", example.source)?;
+                    writeln!(output, "    runtime error: {message}")?;
+                }
+                Err(_) => {
+                    failed += 1;
+                    writeln!(output, "test {location} ... FAILED")?;
+                }
+            }
+        }
+        self.interpreter.globals.borrow_mut().restore(globals);
+
+        let output = self.interpreter.output();
+        writeln!(
+            output,
+            "\n{} passed; {} failed",
+            examples.len() - failed,
+            failed
+        )?;
+        output.flush()?;
+
+        Ok(failed == 0)
+    }
+
+    /// Prints the syntax tree of the script as parenthesized prefix
+    /// expressions, one top level statement per line, instead of running it.
+    pub fn print_ast(&mut self, file_path: &str) -> Result<(), RunError> {
+        self.print_program(file_path, AstPrinter::print)
+    }
+
+    /// Prints the syntax tree of the script as a Graphviz graph, which
+    /// `dot -Tpng` can render, instead of running it.
+    pub fn print_dot(&mut self, file_path: &str) -> Result<(), RunError> {
+        self.print_program(file_path, DotPrinter::print)
+    }
+
+    fn print_program(
+        &mut self,
+        file_path: &str,
+        print: fn(&Ast) -> String,
+    ) -> Result<(), RunError> {
+        let source = read_source(file_path)?;
+        let (tokens, lexical_error) = self.scan(&source);
+
+        let mut parser = Parser::new(&tokens, &source, self.next_expr_id, &mut self.reporting);
+        match parser.parse() {
+            Ok(ast) if !lexical_error => print!("{}", print(&ast)),
+            _ => return Err(RunError::Invalid),
+        }
 
         Ok(())
     }
 
-    pub fn run_file(&mut self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let source = fs::read_to_string(file_path)?;
-        if let Err(error) = self.run(&source) {
-            match error {
-                Error::Runtime { .. } => process::exit(70),
-                _ => process::exit(65),
-            }
+    /// Reformats the script with canonical indentation, spacing and brace
+    /// placement, keeping its comments. The script is rewritten in place, or
+    /// printed if it was read from standard input.
+    pub fn format_file(&mut self, file_path: &str) -> Result<(), RunError> {
+        let source = read_source(file_path)?;
+        let mut scanner = Scanner::with_trivia(&source, &self.flags, &mut self.reporting);
+        let (_, lexical_error) = scanner.scan_tokens();
+        let tokens = scanner.into_tokens();
+
+        // Only code that parses is formatted. Directives aren't applied, so
+        // every branch is checked.
+        let code: Vec<Token> = tokens
+            .iter()
+            .filter(|token| !matches!(token.token_type, TokenType::Comment | TokenType::Directive))
+            .cloned()
+            .collect();
+        let mut parser = Parser::new(&code, &source, self.next_expr_id, &mut self.reporting);
+        if lexical_error || parser.parse().is_err() {
+            return Err(RunError::Invalid);
+        }
+
+        let formatted = Formatter::format(&tokens);
+        if file_path == "-" {
+            print!("{formatted}");
+        } else if formatted != source {
+            fs::write(file_path, formatted)?;
         }
 
         Ok(())
     }
 
+    /// Runs a script, or the one read from standard input if `file_path` is
+    /// "-".
+    pub fn run_file(&mut self, file_path: &str) -> Result<(), RunError> {
+        // Standard input is scanned as it's read, rather than read first.
+        let (source, result) = if file_path == "-" {
+            let stdin = io::stdin().lock();
+            let mut scanner = Scanner::from_reader(stdin, &self.flags, &mut self.reporting);
+            let (_, lexical_error) = scanner.scan_tokens();
+            let (tokens, source) = scanner.into_parts();
+            let result = self.run_scanned(&source, Some((tokens, lexical_error)), true);
+            (source, result)
+        } else {
+            let source = fs::read_to_string(file_path)?;
+            let result = self.run_source(&source, true);
+            (source, result)
+        };
+
+        if let Some((path, format)) = &self.coverage_report {
+            let coverage = self.interpreter.coverage().unwrap();
+            let written = File::create(path).and_then(|file| {
+                let mut out = BufWriter::new(file);
+                coverage.write(*format, file_path, &source, &mut out)?;
+                out.flush()
+            });
+
+            if let Err(error) = written {
+                return Err(RunError::Coverage {
+                    path: path.clone(),
+                    error,
+                });
+            }
+        }
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(Error::Runtime { .. } | Error::Interrupted { .. } | Error::Internal) => {
+                Err(RunError::Failed)
+            }
+            Err(_) => Err(RunError::Invalid),
+        }
+    }
+
     pub fn run_prompt(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         let mut line = String::new();
         let stdin = io::stdin();
         let mut stdout = io::stdout();
+
+        if let Some(path) = &self.session {
+            if let Ok(snapshot) = fs::read_to_string(path) {
+                self.run_source(&snapshot, false).ok();
+            }
+        }
+
         loop {
             print!("> ");
             stdout.flush()?;
@@ -80,10 +828,32 @@ impl RustLox {
                 break;
             }
 
-            self.run(&line).ok();
+            self.run_source(&line, false).ok();
             line.clear();
+
+            if self.show_changes {
+                self.print_changes();
+            }
+        }
+
+        if let Some(path) = &self.session {
+            match self.snapshot() {
+                Ok(snapshot) => fs::write(path, snapshot)?,
+                Err(err) => eprintln!("{err}"),
+            }
         }
 
         Ok(())
     }
 }
+
+// Reads a script, from standard input if its path is "-".
+fn read_source(file_path: &str) -> io::Result<String> {
+    if file_path == "-" {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        Ok(source)
+    } else {
+        fs::read_to_string(file_path)
+    }
+}
@@ -0,0 +1,443 @@
+use crate::ast::{Ast, AstVisitor, Expr, ExprId, Stmt};
+use crate::error::{ErrorReporter, SourceReporter};
+use crate::error_code::ErrorCode;
+use crate::token::Token;
+
+/// How the findings of a lint are reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LintLevel {
+    /// The lint doesn't run.
+    Allow,
+    /// Findings are reported as warnings and the program still runs.
+    Warn,
+    /// Findings are errors, which keep the program from running.
+    Deny,
+}
+
+// A local variable, parameter, function or class.
+pub struct Declaration {
+    pub name: Token,
+    pub used: bool,
+    pub parameter: bool,
+}
+
+impl Declaration {
+    // Whether it was left unused on purpose, by starting its name with '_'.
+    fn ignored(&self) -> bool {
+        self.name.lexeme.starts_with('_')
+    }
+}
+
+pub struct Finding {
+    pub token: Token,
+    pub message: String,
+}
+
+// A check over a program, run after it's parsed and before it's run. The
+// linter walks the program once for all lints, and tells them about the local
+// scopes it goes through and what's declared and used in them.
+pub trait Lint {
+    // The name that turns the lint on and off.
+    fn name(&self) -> &'static str;
+
+    // The code of its findings when they're errors.
+    fn code(&self) -> ErrorCode;
+
+    fn default_level(&self) -> LintLevel;
+
+    // Called before `name` is declared in the innermost of `scopes`.
+    fn declared(&self, _name: &Token, _scopes: &[Vec<Declaration>], _findings: &mut Vec<Finding>) {}
+
+    // Called when a local scope ends, with what was declared in it.
+    fn scope_ended(&self, _scope: &[Declaration], _findings: &mut Vec<Finding>) {}
+}
+
+// Locals that are never read, other than parameters. Assigning to a variable
+// doesn't use it.
+struct Unused;
+
+impl Lint for Unused {
+    fn name(&self) -> &'static str {
+        "unused"
+    }
+
+    fn code(&self) -> ErrorCode {
+        ErrorCode::UnusedVariable
+    }
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+
+    fn scope_ended(&self, scope: &[Declaration], findings: &mut Vec<Finding>) {
+        let unused = scope
+            .iter()
+            .filter(|declaration| !declaration.used && !declaration.parameter);
+        for declaration in unused.filter(|declaration| !declaration.ignored()) {
+            findings.push(Finding {
+                token: declaration.name.clone(),
+                message: format!("Variable '{}' is never used.", declaration.name.lexeme),
+            });
+        }
+    }
+}
+
+// Parameters that are never read, as their own lint since a function may have
+// to take arguments it doesn't need.
+struct UnusedParameter;
+
+impl Lint for UnusedParameter {
+    fn name(&self) -> &'static str {
+        "unused-parameter"
+    }
+
+    fn code(&self) -> ErrorCode {
+        ErrorCode::UnusedVariable
+    }
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Warn
+    }
+
+    fn scope_ended(&self, scope: &[Declaration], findings: &mut Vec<Finding>) {
+        let unused = scope
+            .iter()
+            .filter(|declaration| !declaration.used && declaration.parameter);
+        for declaration in unused.filter(|declaration| !declaration.ignored()) {
+            findings.push(Finding {
+                token: declaration.name.clone(),
+                message: format!("Parameter '{}' is never used.", declaration.name.lexeme),
+            });
+        }
+    }
+}
+
+// Locals that hide another local of an enclosing scope.
+struct Shadow;
+
+impl Lint for Shadow {
+    fn name(&self) -> &'static str {
+        "shadow"
+    }
+
+    fn code(&self) -> ErrorCode {
+        ErrorCode::ShadowedVariable
+    }
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Allow
+    }
+
+    fn declared(&self, name: &Token, scopes: &[Vec<Declaration>], findings: &mut Vec<Finding>) {
+        let enclosing = &scopes[..scopes.len() - 1];
+        if enclosing
+            .iter()
+            .flatten()
+            .any(|declaration| declaration.name.lexeme == name.lexeme)
+        {
+            findings.push(Finding {
+                token: name.clone(),
+                message: format!(
+                    "Variable '{}' shadows a variable of an enclosing scope.",
+                    name.lexeme
+                ),
+            });
+        }
+    }
+}
+
+pub struct Linter {
+    lints: Vec<(Box<dyn Lint>, LintLevel)>,
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        let lints: Vec<Box<dyn Lint>> = vec![
+            Box::new(Unused),
+            Box::new(UnusedParameter),
+            Box::new(Shadow),
+        ];
+        Self {
+            lints: lints
+                .into_iter()
+                .map(|lint| {
+                    let level = lint.default_level();
+                    (lint, level)
+                })
+                .collect(),
+        }
+    }
+
+    // Sets the level of the lint called `name`, or of every lint for "all".
+    // Returns whether there was such a lint.
+    pub fn set_level(&mut self, name: &str, level: LintLevel) -> bool {
+        let mut found = false;
+        for (lint, lint_level) in &mut self.lints {
+            if name == "all" || lint.name() == name {
+                *lint_level = level;
+                found = true;
+            }
+        }
+
+        found
+    }
+
+    // Turns on the lint called `name`, or every lint for "all", as a warning
+    // if it was allowed. Lints that are already on keep their level.
+    pub fn enable(&mut self, name: &str) -> bool {
+        let mut found = false;
+        for (lint, level) in &mut self.lints {
+            if name == "all" || lint.name() == name {
+                if *level == LintLevel::Allow {
+                    *level = LintLevel::Warn;
+                }
+                found = true;
+            }
+        }
+
+        found
+    }
+
+    // Reports the findings of every lint that isn't allowed, quoting the
+    // source kept by the AST, and returns whether any of them was an error.
+    pub fn check(&self, ast: &Ast, statements: &[Stmt], reporter: &mut dyn ErrorReporter) -> bool {
+        let mut walk = Walk {
+            ast,
+            lints: self
+                .lints
+                .iter()
+                .filter(|(_, level)| *level != LintLevel::Allow)
+                .map(|(lint, level)| (lint.as_ref(), *level))
+                .collect(),
+            scopes: Vec::new(),
+            reporter: SourceReporter::new(ast.source.as_deref().unwrap_or(""), reporter),
+            had_error: false,
+        };
+
+        if !walk.lints.is_empty() {
+            for statement in statements {
+                walk.visit_stmt(statement);
+            }
+        }
+
+        walk.had_error
+    }
+}
+
+struct Walk<'a> {
+    ast: &'a Ast,
+    lints: Vec<(&'a dyn Lint, LintLevel)>,
+    // Only local scopes, since globals can be used before they're declared.
+    scopes: Vec<Vec<Declaration>>,
+    reporter: SourceReporter<'a>,
+    had_error: bool,
+}
+
+impl<'a> Walk<'a> {
+    fn report(&mut self, lint: &dyn Lint, level: LintLevel, findings: Vec<Finding>) {
+        for finding in findings {
+            if level == LintLevel::Deny {
+                self.reporter
+                    .error_token(&finding.token, lint.code(), &finding.message);
+                self.had_error = true;
+            } else {
+                self.reporter
+                    .warning_token(&finding.token, &finding.message);
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Token, parameter: bool) {
+        if self.scopes.is_empty() {
+            return;
+        }
+
+        // Declaring a name again in the same scope is an error the resolver
+        // reports, and the new declaration replaces the old one.
+        let scope = self.scopes.last_mut().unwrap();
+        scope.retain(|declaration| declaration.name.lexeme != name.lexeme);
+        scope.push(Declaration {
+            name: name.clone(),
+            used: false,
+            parameter,
+        });
+
+        for index in 0..self.lints.len() {
+            let (lint, level) = self.lints[index];
+            let mut findings = Vec::new();
+            lint.declared(name, &self.scopes, &mut findings);
+            self.report(lint, level, findings);
+        }
+    }
+
+    fn use_variable(&mut self, name: &Token) {
+        let declaration = self.scopes.iter_mut().rev().find_map(|scope| {
+            scope
+                .iter_mut()
+                .find(|declaration| declaration.name.lexeme == name.lexeme)
+        });
+
+        if let Some(declaration) = declaration {
+            declaration.used = true;
+        }
+    }
+
+    fn end_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+        for index in 0..self.lints.len() {
+            let (lint, level) = self.lints[index];
+            let mut findings = Vec::new();
+            lint.scope_ended(&scope, &mut findings);
+            self.report(lint, level, findings);
+        }
+    }
+
+    fn function(&mut self, params: &[Token], body: &[Stmt]) {
+        self.scopes.push(Vec::new());
+        for param in params {
+            self.declare(param, true);
+        }
+        for statement in body {
+            self.visit_stmt(statement);
+        }
+        self.end_scope();
+    }
+}
+
+impl<'a> AstVisitor<(), ()> for Walk<'a> {
+    fn visit_expr(&mut self, expr: ExprId) {
+        let ast = self.ast;
+        match &ast[expr] {
+            Expr::Variable(name) => self.use_variable(name),
+            Expr::Assign { value, .. } => self.visit_expr(*value),
+            Expr::Lambda { params, body } => self.function(params, body),
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.visit_expr(*condition);
+                self.visit_expr(*then_branch);
+                self.visit_expr(*else_branch);
+            }
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.visit_expr(*left);
+                self.visit_expr(*right);
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.visit_expr(*callee);
+                for argument in arguments {
+                    self.visit_expr(*argument);
+                }
+            }
+            Expr::Grouping(expr)
+            | Expr::Unary { right: expr, .. }
+            | Expr::Spread { list: expr, .. } => self.visit_expr(*expr),
+            Expr::Get { object, .. } => self.visit_expr(*object),
+            Expr::Set { object, value, .. } => {
+                self.visit_expr(*object);
+                self.visit_expr(*value);
+            }
+            Expr::List(elements) => {
+                for element in elements {
+                    self.visit_expr(*element);
+                }
+            }
+            Expr::Index { object, index, .. } => {
+                self.visit_expr(*object);
+                self.visit_expr(*index);
+            }
+            Expr::IndexSet {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                self.visit_expr(*object);
+                self.visit_expr(*index);
+                self.visit_expr(*value);
+            }
+            Expr::Literal(_) | Expr::Super { .. } | Expr::This(_) => (),
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(statements) => {
+                self.scopes.push(Vec::new());
+                for statement in statements {
+                    self.visit_stmt(statement);
+                }
+                self.end_scope();
+            }
+            Stmt::Var { name, initializer } => {
+                self.declare(name, false);
+                if let Some(expr) = initializer {
+                    self.visit_expr(*expr);
+                }
+            }
+            Stmt::Function { name, definition } => {
+                self.declare(name, false);
+                self.visit_expr(*definition);
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                statics,
+            } => {
+                self.declare(name, false);
+                for field in statics {
+                    if let Stmt::Var {
+                        initializer: Some(expr),
+                        ..
+                    } = field
+                    {
+                        self.visit_expr(*expr);
+                    }
+                }
+                if let Some(superclass) = superclass {
+                    self.visit_expr(*superclass);
+                }
+                for method in methods {
+                    if let Stmt::Function { definition, .. } = method {
+                        self.visit_expr(*definition);
+                    }
+                }
+            }
+            Stmt::Expression {
+                expression: expr, ..
+            }
+            | Stmt::Print {
+                expression: expr, ..
+            } => self.visit_expr(*expr),
+            Stmt::Return { value, .. } => {
+                if let Some(expr) = value {
+                    self.visit_expr(*expr);
+                }
+            }
+            Stmt::Delete { object, .. } => self.visit_expr(*object),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.visit_expr(*condition);
+                self.visit_stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.visit_stmt(else_branch);
+                }
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.visit_expr(*condition);
+                self.visit_stmt(body);
+            }
+        }
+    }
+}
@@ -1,27 +1,339 @@
-use std::{env, process};
+use std::{env, fs::File, io::BufWriter, process, thread, time::Duration};
 
-use rustlox::RustLox;
+use rustlox::{
+    ColorChoice, CoverageFormat, ErrorCode, InterpreterConfig, LintLevel, NativePolicy, RunError,
+    RustLox,
+};
+
+// The stack reserved for each Lox call. Measured with recursive functions in
+// release builds, a call takes about 5 KiB, plus about 1 KiB for each level
+// of nesting in the expression it's called from, so 32 KiB leaves room for
+// some 25 levels. Debug builds take over ten times as much, and may still
+// overflow on deeply nested expressions.
+const STACK_PER_CALL: usize = if cfg!(debug_assertions) {
+    128 * 1024
+} else {
+    32 * 1024
+};
+const BASE_STACK_SIZE: usize = 8 * 1024 * 1024;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let mut rustlox = RustLox::new();
+    let mut args = env::args().skip(1);
+    let mut config = InterpreterConfig::default();
+    let mut flags = Vec::new();
+    let mut positional = Vec::new();
+    let mut script_args = Vec::new();
+    let mut doc = false;
+    let mut ast = false;
+    let mut dot = false;
+    let mut show_changes = false;
+    let mut events = None;
+    let mut session = None;
+    let mut coverage = None;
+    let mut color = ColorChoice::Auto;
+    let mut coverage_format = CoverageFormat::Text;
+    // Applied in order, so "--lint all --allow shadow" leaves shadow off.
+    // "--lint" has no level, since it keeps the level of lints that are on.
+    let mut lints = Vec::new();
 
-    match args.as_slice() {
-        [_, file_path] => {
-            if let Err(err) = rustlox.run_file(file_path) {
-                eprintln!("An error occurred: {err}");
-                process::exit(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--cfg" => match args.next() {
+                Some(flag) => flags.push(flag),
+                None => {
+                    eprintln!("Expected a flag name after '--cfg'.");
+                    process::exit(64);
+                }
+            },
+            "--allow-natives" | "--deny-natives" => match args.next() {
+                Some(names) => {
+                    let names = names.split(',').map(String::from).collect();
+                    config.natives = if arg == "--allow-natives" {
+                        NativePolicy::Allow(names)
+                    } else {
+                        NativePolicy::Deny(names)
+                    };
+                }
+                None => {
+                    eprintln!("Expected a list of natives after '{arg}'.");
+                    process::exit(64);
+                }
+            },
+            "--sandbox" => {
+                config.allow_io = false;
+                config.allow_clock = false;
+                config.allow_env = false;
+            }
+            "--ast" => ast = true,
+            "--doc" => doc = true,
+            "--emit" => match args.next().as_deref() {
+                Some("dot") => dot = true,
+                _ => {
+                    eprintln!("Expected 'dot' after '--emit'.");
+                    process::exit(64);
+                }
+            },
+            "--explain" => match args.next() {
+                Some(code) => match ErrorCode::from_code(&code) {
+                    Some(error) => {
+                        println!("{}", error.explanation());
+                        process::exit(0);
+                    }
+                    None => {
+                        eprintln!("Unknown error code '{code}'.");
+                        process::exit(64);
+                    }
+                },
+                None => {
+                    eprintln!("Expected an error code after '--explain'.");
+                    process::exit(64);
+                }
+            },
+            "--events" => match args.next() {
+                Some(path) => events = Some(path),
+                None => {
+                    eprintln!("Expected a file path after '--events'.");
+                    process::exit(64);
+                }
+            },
+            "--session" => match args.next() {
+                Some(path) => session = Some(path),
+                None => {
+                    eprintln!("Expected a file path after '--session'.");
+                    process::exit(64);
+                }
+            },
+            "--lint" | "--allow" => match args.next() {
+                Some(name) if arg == "--lint" => lints.push((None, name)),
+                Some(name) => lints.push((Some(LintLevel::Allow), name)),
+                None => {
+                    eprintln!("Expected a lint name after '{arg}'.");
+                    process::exit(64);
+                }
+            },
+            "-W" => match args.next().as_deref().and_then(lint_setting) {
+                Some((level, name)) => lints.push((Some(level), name)),
+                None => {
+                    eprintln!("Expected 'error=NAME', 'warn=NAME' or 'allow=NAME' after '-W'.");
+                    process::exit(64);
+                }
+            },
+            "--color" => match args.next().as_deref().and_then(color_choice) {
+                Some(choice) => color = choice,
+                None => {
+                    eprintln!("Expected 'always', 'never' or 'auto' after '--color'.");
+                    process::exit(64);
+                }
+            },
+            _ if arg.starts_with("--color=") => match color_choice(&arg["--color=".len()..]) {
+                Some(choice) => color = choice,
+                None => {
+                    eprintln!("Expected 'always', 'never' or 'auto' after '--color'.");
+                    process::exit(64);
+                }
+            },
+            "--coverage" => match args.next() {
+                Some(path) => coverage = Some(path),
+                None => {
+                    eprintln!("Expected a file path after '--coverage'.");
+                    process::exit(64);
+                }
+            },
+            "--coverage-format" => match args.next().as_deref() {
+                Some("text") => coverage_format = CoverageFormat::Text,
+                Some("lcov") => coverage_format = CoverageFormat::Lcov,
+                _ => {
+                    eprintln!("Expected 'text' or 'lcov' after '--coverage-format'.");
+                    process::exit(64);
+                }
+            },
+            "--show-changes" => show_changes = true,
+            "--strict-booleans" => config.strict_booleans = true,
+            "--boolean-logic" => config.boolean_logic = true,
+            "--checked-arithmetic" => config.checked_arithmetic = true,
+            "--ieee-division" => config.ieee_division = true,
+            "--copy-arguments" => config.copy_arguments = true,
+            "--max-call-depth" => match args.next().and_then(|depth| depth.parse().ok()) {
+                Some(depth) => config.max_call_depth = depth,
+                None => {
+                    eprintln!("Expected a number after '--max-call-depth'.");
+                    process::exit(64);
+                }
+            },
+            "--max-steps" => match args.next().and_then(|steps| steps.parse().ok()) {
+                Some(steps) => config.max_steps = Some(steps),
+                None => {
+                    eprintln!("Expected a number after '--max-steps'.");
+                    process::exit(64);
+                }
+            },
+            "--timeout" => match args.next().and_then(|millis| millis.parse().ok()) {
+                Some(millis) => config.timeout = Some(Duration::from_millis(millis)),
+                None => {
+                    eprintln!("Expected a number of milliseconds after '--timeout'.");
+                    process::exit(64);
+                }
+            },
+            _ if positional.is_empty() && arg != "test" && arg != "fmt" => {
+                // Everything after the script path is passed to the script.
+                positional.push(arg);
+                script_args.extend(args.by_ref());
             }
+            _ => positional.push(arg),
         }
-        [_] => {
-            if let Err(err) = rustlox.run_prompt() {
-                eprintln!("An error occurred: {err}");
-                process::exit(1);
+    }
+
+    // Lox calls recurse on the host's stack, so it must fit `max_call_depth`
+    // calls for deep recursion to end in a "Stack overflow." error instead of
+    // crashing.
+    let stack_size = config
+        .max_call_depth
+        .saturating_mul(STACK_PER_CALL)
+        .saturating_add(BASE_STACK_SIZE);
+    let spawned = thread::Builder::new()
+        .stack_size(stack_size)
+        .spawn(move || {
+            let mut rustlox = RustLox::with_config(config);
+            rustlox.set_args(script_args);
+            rustlox.set_color(color);
+            for flag in &flags {
+                rustlox.set_flag(flag);
+            }
+
+            for (level, name) in &lints {
+                let known = match level {
+                    Some(level) => rustlox.set_lint_level(name, *level),
+                    None => rustlox.enable_lint(name),
+                };
+
+                if !known {
+                    eprintln!("Unknown lint '{name}'.");
+                    process::exit(64);
+                }
+            }
+
+            if let Some(path) = &coverage {
+                rustlox.set_coverage_report(path, coverage_format);
+            }
+
+            if show_changes {
+                rustlox.show_changes();
+            }
+
+            if let Some(path) = &session {
+                rustlox.set_session(path);
+            }
+
+            if let Some(path) = events {
+                match File::create(&path) {
+                    Ok(file) => rustlox.set_event_writer(BufWriter::new(file)),
+                    Err(err) => {
+                        eprintln!("Can't create events file '{path}': {err}");
+                        process::exit(74);
+                    }
+                }
+            }
+
+            match positional.as_slice() {
+                [command, file_path] if command == "test" => {
+                    if !doc {
+                        eprintln!(
+                            "Only doc comment tests are supported, use 'rustlox test --doc'."
+                        );
+                        process::exit(64);
+                    }
+
+                    match rustlox.run_doctests(file_path) {
+                        Ok(true) => (),
+                        Ok(false) => process::exit(1),
+                        Err(err) => {
+                            eprintln!("An error occurred: {err}");
+                            process::exit(1);
+                        }
+                    }
+                }
+                [command, file_path] if command == "fmt" => {
+                    if let Err(err) = rustlox.format_file(file_path) {
+                        exit_with(err);
+                    }
+                }
+                [file_path] if ast => {
+                    if let Err(err) = rustlox.print_ast(file_path) {
+                        exit_with(err);
+                    }
+                }
+                [file_path] if dot => {
+                    if let Err(err) = rustlox.print_dot(file_path) {
+                        exit_with(err);
+                    }
+                }
+                [file_path] => {
+                    if let Err(err) = rustlox.run_file(file_path) {
+                        exit_with(err);
+                    }
+                }
+                [] => {
+                    if let Err(err) = rustlox.run_prompt() {
+                        eprintln!("An error occurred: {err}");
+                        process::exit(1);
+                    }
+                }
+                _ => {
+                    eprintln!("You can't pass more than one argument.");
+                    process::exit(64);
+                }
+            }
+        });
+
+    match spawned {
+        // A panic has already been reported by the panic hook.
+        Ok(interpreter) => {
+            if interpreter.join().is_err() {
+                process::exit(101);
             }
         }
-        _ => {
-            eprintln!("You can't pass more than one argument.");
-            process::exit(64);
+        Err(err) => {
+            eprintln!("Can't allocate a stack of {stack_size} bytes: {err}");
+            process::exit(71);
         }
     }
 }
+
+// "error=unused" and the like.
+fn lint_setting(setting: &str) -> Option<(LintLevel, String)> {
+    let (level, name) = setting.split_once('=')?;
+    let level = match level {
+        "error" => LintLevel::Deny,
+        "warn" => LintLevel::Warn,
+        "allow" => LintLevel::Allow,
+        _ => return None,
+    };
+
+    Some((level, String::from(name)))
+}
+
+fn color_choice(name: &str) -> Option<ColorChoice> {
+    match name {
+        "always" => Some(ColorChoice::Always),
+        "never" => Some(ColorChoice::Never),
+        "auto" => Some(ColorChoice::Auto),
+        _ => None,
+    }
+}
+
+// Exits with the status for how a command working on a file failed. Errors
+// in the script itself have already been reported.
+fn exit_with(error: RunError) -> ! {
+    match &error {
+        RunError::Io(err) => eprintln!("An error occurred: {err}"),
+        RunError::Coverage { .. } => eprintln!("{error}"),
+        RunError::Invalid | RunError::Failed => (),
+    }
+
+    process::exit(match error {
+        RunError::Io(_) => 1,
+        RunError::Invalid => 65,
+        RunError::Failed => 70,
+        RunError::Coverage { .. } => 74,
+    })
+}
@@ -0,0 +1,632 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::env;
+use std::fs;
+use std::io;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::ast::{ConversionError, Object};
+use crate::callable::{LoxCallable, LoxNative, NativeImpl};
+use crate::environment::Environment;
+use crate::error::Error;
+use crate::error_code::ErrorCode;
+use crate::interpreter::{Interpreter, InterpreterConfig};
+use crate::json;
+use crate::render::{render, Style};
+use crate::token::Token;
+
+// Natives receive the call's closing paren, used to report their errors at
+// the call site.
+type NativeFn = fn(&mut Interpreter, &Token, &[Object]) -> Result<Object, Error>;
+
+// A native function defined by the program embedding rustlox. It gets the
+// arguments it was called with, and a context to call back into Lox.
+pub type HostNative = Rc<dyn Fn(&mut NativeContext, &[Object]) -> Result<Object, NativeError>>;
+
+/// What a native function defined by the host can do while it runs.
+pub struct NativeContext<'a> {
+    pub(crate) interpreter: &'a mut Interpreter,
+    pub(crate) paren: &'a Token,
+}
+
+impl NativeContext<'_> {
+    /// Calls a Lox function, method or class. Its errors can be passed on
+    /// with `?`.
+    pub fn call(&mut self, callee: &Object, arguments: Vec<Object>) -> Result<Object, NativeError> {
+        self.interpreter
+            .call_value(callee, self.paren, &arguments)
+            .map_err(|error| NativeError(Raised::Lox(error)))
+    }
+}
+
+/// A runtime error raised by a native function defined by the host. Messages
+/// convert into one, as in `Err("Expected a number.".into())`, which is
+/// reported at the call.
+pub struct NativeError(Raised);
+
+enum Raised {
+    Message(String),
+    // From Lox code the native called, already located.
+    Lox(Error),
+}
+
+impl NativeError {
+    pub(crate) fn at(self, paren: &Token) -> Error {
+        match self.0 {
+            Raised::Message(message) => error(paren, &message),
+            Raised::Lox(error) => error,
+        }
+    }
+}
+
+impl From<&str> for NativeError {
+    fn from(message: &str) -> Self {
+        NativeError(Raised::Message(String::from(message)))
+    }
+}
+
+impl From<ConversionError> for NativeError {
+    fn from(error: ConversionError) -> Self {
+        NativeError(Raised::Message(error.to_string()))
+    }
+}
+
+impl From<String> for NativeError {
+    fn from(message: String) -> Self {
+        NativeError(Raised::Message(message))
+    }
+}
+
+pub fn define_natives(globals: &mut Environment, config: &InterpreterConfig) {
+    if config.allow_clock {
+        define(globals, "clock", 0, clock);
+        define(globals, "sleep", 1, sleep);
+    }
+    if config.allow_io {
+        define(globals, "readLine", 0, read_line);
+        define(globals, "readFile", 1, read_file);
+        define(globals, "writeFile", 2, write_file);
+    }
+    if config.allow_env {
+        define(globals, "getenv", 1, getenv);
+    }
+    define(globals, "type", 1, type_of);
+    define(globals, "inspect", 1, inspect);
+    define(globals, "argCount", 0, arg_count);
+    define(globals, "arg", 1, arg);
+    define(globals, "len", 1, len);
+    define(globals, "push", 2, push);
+    define(globals, "pop", 1, pop);
+    define(globals, "insert", 3, insert);
+    define(globals, "removeAt", 2, remove_at);
+    define(globals, "slice", 3, slice);
+    define(globals, "concat", 2, concat);
+    define(globals, "reverse", 1, reverse);
+    define(globals, "map", 2, map);
+    define(globals, "filter", 2, filter);
+    define(globals, "reduce", 3, reduce);
+    define(globals, "sort", 2, sort);
+    define(globals, "write", 1, write);
+    define_variadic(globals, "format", 1, format);
+    define(globals, "normalize", 2, normalize);
+    define(globals, "casefold", 1, casefold);
+    define(globals, "compare", 2, compare);
+    define(globals, "encodeUtf8", 1, encode_utf8);
+    define(globals, "decodeUtf8", 2, decode_utf8);
+    define(globals, "decodeLatin1", 1, decode_latin1);
+    define(globals, "jsonStringify", 1, json_stringify);
+    define(globals, "jsonParse", 1, json_parse);
+}
+
+fn define(globals: &mut Environment, name: &str, arity: usize, call_impl: NativeFn) {
+    define_native(globals, name, arity, false, NativeImpl::Builtin(call_impl));
+}
+
+// Defines a native that takes `arity` or more arguments.
+fn define_variadic(globals: &mut Environment, name: &str, arity: usize, call_impl: NativeFn) {
+    define_native(globals, name, arity, true, NativeImpl::Builtin(call_impl));
+}
+
+pub fn define_native(
+    globals: &mut Environment,
+    name: &str,
+    arity: usize,
+    variadic: bool,
+    call_impl: NativeImpl,
+) {
+    globals.define(
+        name.into(),
+        Object::Callable(LoxCallable::LoxNative {
+            native: Rc::new(LoxNative {
+                name: name.into(),
+                call_impl,
+                arity,
+                variadic,
+            }),
+        }),
+    );
+}
+
+fn error(paren: &Token, message: &str) -> Error {
+    Error::Runtime {
+        token: paren.to_owned(),
+        code: ErrorCode::NativeFailed,
+        message: String::from(message),
+    }
+}
+
+fn list_argument(paren: &Token, value: &Object) -> Result<Rc<RefCell<Vec<Object>>>, Error> {
+    if let Object::List(list) = value {
+        Ok(list.clone())
+    } else {
+        Err(error(paren, "Expected a list."))
+    }
+}
+
+fn string_argument(paren: &Token, value: &Object) -> Result<Rc<str>, Error> {
+    if let Object::String(string) = value {
+        Ok(string.clone())
+    } else {
+        Err(error(paren, "Expected a string."))
+    }
+}
+
+// Bytes are lists of integers between 0 and 255.
+fn bytes_argument(paren: &Token, value: &Object) -> Result<Vec<u8>, Error> {
+    list_argument(paren, value)?
+        .borrow()
+        .iter()
+        .map(|byte| match byte {
+            Object::Number(byte) if byte.fract() == 0.0 && (0.0..=255.0).contains(byte) => {
+                Ok(*byte as u8)
+            }
+            _ => Err(error(paren, "Bytes must be integers between 0 and 255.")),
+        })
+        .collect()
+}
+
+// Indexes may point one past the end when inserting or slicing.
+fn index_argument(paren: &Token, value: &Object, max: usize) -> Result<usize, Error> {
+    match value {
+        Object::Number(index) if index.fract() == 0.0 && *index >= 0.0 => {
+            if *index as usize <= max {
+                Ok(*index as usize)
+            } else {
+                Err(error(paren, "Index out of range."))
+            }
+        }
+        _ => Err(error(paren, "Index must be a non-negative integer.")),
+    }
+}
+
+fn new_list(elements: Vec<Object>) -> Object {
+    Object::List(Rc::new(RefCell::new(elements)))
+}
+
+fn clock(_: &mut Interpreter, _: &Token, _: &[Object]) -> Result<Object, Error> {
+    Ok(Object::Number(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap() // Can safely unwrap here because SystemTime::now() will not be before EPOCH
+            .as_micros() as f64,
+    ))
+}
+
+// How long sleep() waits before checking again whether the script was
+// cancelled or ran out of time.
+const SLEEP_SLICE: Duration = Duration::from_millis(10);
+
+fn sleep(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: &[Object],
+) -> Result<Object, Error> {
+    match &arguments[0] {
+        Object::Number(millis) if *millis >= 0.0 && millis.is_finite() => {
+            let duration = Duration::try_from_secs_f64(millis / 1000.0)
+                .map_err(|_| error(paren, "Sleep duration is too long."))?;
+            let mut remaining = duration;
+            while !remaining.is_zero() {
+                interpreter.checkpoint(paren)?;
+                let slice = remaining.min(SLEEP_SLICE);
+                thread::sleep(slice);
+                remaining -= slice;
+            }
+            Ok(Object::Nil)
+        }
+        _ => Err(error(
+            paren,
+            "Sleep duration must be a non-negative number of milliseconds.",
+        )),
+    }
+}
+
+fn read_line(_: &mut Interpreter, _: &Token, _: &[Object]) -> Result<Object, Error> {
+    let mut line = String::new();
+    match io::stdin().read_line(&mut line) {
+        // Nil on end of input, and also when stdin can't be read.
+        Ok(0) | Err(_) => Ok(Object::Nil),
+        Ok(_) => {
+            let trimmed = line.trim_end_matches(['\n', '\r']).len();
+            line.truncate(trimmed);
+            Ok(Object::String(line.into()))
+        }
+    }
+}
+
+fn read_file(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    if let Object::String(path) = &arguments[0] {
+        fs::read_to_string(path.as_ref())
+            .map(|contents| Object::String(contents.into()))
+            .map_err(|e| error(paren, &format!("Can't read file '{path}': {e}.")))
+    } else {
+        Err(error(paren, "Path must be a string."))
+    }
+}
+
+fn write_file(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    match (&arguments[0], &arguments[1]) {
+        (Object::String(path), Object::String(contents)) => {
+            fs::write(path.as_ref(), contents.as_bytes())
+                .map(|_| Object::Nil)
+                .map_err(|e| error(paren, &format!("Can't write file '{path}': {e}.")))
+        }
+        (Object::String(_), _) => Err(error(paren, "Contents must be a string.")),
+        _ => Err(error(paren, "Path must be a string.")),
+    }
+}
+
+fn type_of(_: &mut Interpreter, _: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    Ok(Object::String(arguments[0].type_name().into()))
+}
+
+fn inspect(_: &mut Interpreter, _: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    Ok(Object::String(render(&arguments[0], Style::Pretty).into()))
+}
+
+fn arg_count(interpreter: &mut Interpreter, _: &Token, _: &[Object]) -> Result<Object, Error> {
+    Ok(Object::Number(interpreter.script_args().len() as f64))
+}
+
+fn arg(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: &[Object],
+) -> Result<Object, Error> {
+    let args = interpreter.script_args();
+    match &arguments[0] {
+        Object::Number(index) if index.fract() == 0.0 && *index >= 0.0 => args
+            .get(*index as usize)
+            .map(|arg| Object::String(arg.as_str().into()))
+            .ok_or_else(|| {
+                error(
+                    paren,
+                    &format!(
+                        "Argument index {index} out of range, there are {} arguments.",
+                        args.len()
+                    ),
+                )
+            }),
+        _ => Err(error(
+            paren,
+            "Argument index must be a non-negative integer.",
+        )),
+    }
+}
+
+fn getenv(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    if let Object::String(name) = &arguments[0] {
+        // Unset variables, and values that aren't valid Unicode, are nil.
+        Ok(env::var(name.as_ref()).map_or(Object::Nil, |value| Object::String(value.into())))
+    } else {
+        Err(error(paren, "Variable name must be a string."))
+    }
+}
+
+fn len(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    match &arguments[0] {
+        Object::List(list) => Ok(Object::Number(list.borrow().len() as f64)),
+        Object::String(string) => Ok(Object::Number(string.chars().count() as f64)),
+        _ => Err(error(paren, "Expected a list or a string.")),
+    }
+}
+
+fn push(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    let list = list_argument(paren, &arguments[0])?;
+    list.borrow_mut().push(arguments[1].clone());
+    Ok(Object::Nil)
+}
+
+fn pop(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    let list = list_argument(paren, &arguments[0])?;
+    let last = list.borrow_mut().pop();
+    last.ok_or_else(|| error(paren, "Can't pop from an empty list."))
+}
+
+fn insert(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    let list = list_argument(paren, &arguments[0])?;
+    let index = index_argument(paren, &arguments[1], list.borrow().len())?;
+    list.borrow_mut().insert(index, arguments[2].clone());
+    Ok(Object::Nil)
+}
+
+fn remove_at(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    let list = list_argument(paren, &arguments[0])?;
+    let len = list.borrow().len();
+    let index = index_argument(paren, &arguments[1], len)?;
+    if index == len {
+        return Err(error(paren, "Index out of range."));
+    }
+
+    let removed = list.borrow_mut().remove(index);
+    Ok(removed)
+}
+
+fn slice(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    let list = list_argument(paren, &arguments[0])?;
+    let list = list.borrow();
+    let start = index_argument(paren, &arguments[1], list.len())?;
+    let end = index_argument(paren, &arguments[2], list.len())?;
+    if start > end {
+        return Err(error(paren, "Slice start can't be after its end."));
+    }
+
+    Ok(new_list(list[start..end].to_vec()))
+}
+
+fn concat(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    let first = list_argument(paren, &arguments[0])?;
+    let second = list_argument(paren, &arguments[1])?;
+    let mut elements = first.borrow().clone();
+    elements.extend(second.borrow().iter().cloned());
+    Ok(new_list(elements))
+}
+
+fn reverse(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    let list = list_argument(paren, &arguments[0])?;
+    let elements = list.borrow().iter().rev().cloned().collect();
+    Ok(new_list(elements))
+}
+
+fn write(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: &[Object],
+) -> Result<Object, Error> {
+    let text = interpreter.stringify(&arguments[0], paren)?;
+    let output = interpreter.output();
+    write!(output, "{text}").ok();
+    // Flush so that partial lines show up before a readLine() prompt.
+    output.flush().ok();
+    Ok(Object::Nil)
+}
+
+// Replaces each `{}` in the format string with the next argument. `{{` and
+// `}}` stand for literal braces.
+fn format(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: &[Object],
+) -> Result<Object, Error> {
+    let format = match &arguments[0] {
+        Object::String(format) => format.clone(),
+        _ => return Err(error(paren, "Format must be a string.")),
+    };
+
+    let mut values = arguments[1..].iter();
+    let mut result = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                result.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                match values.next() {
+                    Some(value) => result.push_str(&interpreter.stringify(value, paren)?),
+                    None => return Err(error(paren, "Not enough arguments for format string.")),
+                }
+            }
+            ('{', _) | ('}', _) => return Err(error(paren, "Unmatched brace in format string.")),
+            _ => result.push(c),
+        }
+    }
+
+    if values.next().is_some() {
+        return Err(error(paren, "Too many arguments for format string."));
+    }
+
+    Ok(Object::String(result.into()))
+}
+
+fn normalize(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    let string = string_argument(paren, &arguments[0])?;
+    let normalized: String = match &arguments[1] {
+        Object::String(form) if &**form == "NFC" => string.nfc().collect(),
+        Object::String(form) if &**form == "NFD" => string.nfd().collect(),
+        Object::String(form) if &**form == "NFKC" => string.nfkc().collect(),
+        Object::String(form) if &**form == "NFKD" => string.nfkd().collect(),
+        _ => {
+            return Err(error(
+                paren,
+                "Form must be \"NFC\", \"NFD\", \"NFKC\" or \"NFKD\".",
+            ))
+        }
+    };
+
+    Ok(Object::String(normalized.into()))
+}
+
+fn casefold(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    let string = string_argument(paren, &arguments[0])?;
+    Ok(Object::String(
+        caseless::default_case_fold_str(&string).into(),
+    ))
+}
+
+// Compares the code points of both strings after normalizing them to NFC, so
+// the result doesn't depend on the locale or on how the text was composed.
+fn compare(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    let lhs = string_argument(paren, &arguments[0])?;
+    let rhs = string_argument(paren, &arguments[1])?;
+    let ordering = match lhs.nfc().cmp(rhs.nfc()) {
+        Ordering::Less => -1.0,
+        Ordering::Equal => 0.0,
+        Ordering::Greater => 1.0,
+    };
+
+    Ok(Object::Number(ordering))
+}
+
+fn encode_utf8(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    let string = string_argument(paren, &arguments[0])?;
+    Ok(new_list(
+        string
+            .bytes()
+            .map(|byte| Object::Number(byte as f64))
+            .collect(),
+    ))
+}
+
+// Invalid sequences are an error in the "strict" mode, and are replaced with
+// U+FFFD in the "replace" mode.
+fn decode_utf8(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    let bytes = bytes_argument(paren, &arguments[0])?;
+    match &arguments[1] {
+        Object::String(mode) if &**mode == "strict" => match String::from_utf8(bytes) {
+            Ok(string) => Ok(Object::String(string.into())),
+            Err(err) => Err(error(
+                paren,
+                &format!("Invalid UTF-8 at byte {}.", err.utf8_error().valid_up_to()),
+            )),
+        },
+        Object::String(mode) if &**mode == "replace" => {
+            Ok(Object::String(String::from_utf8_lossy(&bytes).into()))
+        }
+        _ => Err(error(paren, "Mode must be \"strict\" or \"replace\".")),
+    }
+}
+
+// Every byte is a valid Latin-1 character, so decoding can't fail.
+fn decode_latin1(
+    _: &mut Interpreter,
+    paren: &Token,
+    arguments: &[Object],
+) -> Result<Object, Error> {
+    let bytes = bytes_argument(paren, &arguments[0])?;
+    Ok(Object::String(
+        bytes.into_iter().map(char::from).collect::<String>().into(),
+    ))
+}
+
+// Instances become objects holding their fields, functions and classes become
+// strings, and NaN, the infinities and lists that contain themselves become
+// null.
+fn json_stringify(_: &mut Interpreter, _: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    Ok(Object::String(render(&arguments[0], Style::Json).into()))
+}
+
+// Lox has no maps to hold JSON objects, so parsing one is an error.
+fn json_parse(_: &mut Interpreter, paren: &Token, arguments: &[Object]) -> Result<Object, Error> {
+    let text = string_argument(paren, &arguments[0])?;
+    json::parse(&text).map_err(|message| error(paren, &message))
+}
+
+// The callbacks below may modify the list, so they iterate over a copy of it.
+
+fn map(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: &[Object],
+) -> Result<Object, Error> {
+    let elements = list_argument(paren, &arguments[0])?.borrow().clone();
+    let mut mapped = Vec::with_capacity(elements.len());
+    for element in elements {
+        mapped.push(interpreter.call_value(&arguments[1], paren, &vec![element])?);
+    }
+
+    Ok(new_list(mapped))
+}
+
+fn filter(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: &[Object],
+) -> Result<Object, Error> {
+    let elements = list_argument(paren, &arguments[0])?.borrow().clone();
+    let mut filtered = Vec::new();
+    for element in elements {
+        let keep = interpreter.call_value(&arguments[1], paren, &vec![element.clone()])?;
+        if interpreter.is_condition_true(paren, &keep)? {
+            filtered.push(element);
+        }
+    }
+
+    Ok(new_list(filtered))
+}
+
+fn reduce(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: &[Object],
+) -> Result<Object, Error> {
+    let elements = list_argument(paren, &arguments[0])?.borrow().clone();
+    let mut accumulator = arguments[2].clone();
+    for element in elements {
+        accumulator = interpreter.call_value(&arguments[1], paren, &vec![accumulator, element])?;
+    }
+
+    Ok(accumulator)
+}
+
+// Returns a sorted copy of the list. The comparator returns a negative number
+// when its first argument goes first, a positive one when its second argument
+// does, and zero when their order doesn't matter.
+fn sort(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    arguments: &[Object],
+) -> Result<Object, Error> {
+    let elements = list_argument(paren, &arguments[0])?.borrow().clone();
+    let sorted = merge_sort(interpreter, paren, &arguments[1], elements)?;
+    Ok(new_list(sorted))
+}
+
+// A stable sort that stops at the first failed comparison. The slice sorts
+// can't be used because the comparator may fail, or not be a total order.
+fn merge_sort(
+    interpreter: &mut Interpreter,
+    paren: &Token,
+    comparator: &Object,
+    mut elements: Vec<Object>,
+) -> Result<Vec<Object>, Error> {
+    if elements.len() <= 1 {
+        return Ok(elements);
+    }
+
+    let right = elements.split_off(elements.len() / 2);
+    let left = merge_sort(interpreter, paren, comparator, elements)?;
+    let right = merge_sort(interpreter, paren, comparator, right)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        let order = interpreter.call_value(comparator, paren, &vec![a.clone(), b.clone()])?;
+        match order {
+            Object::Number(order) if order > 0.0 => merged.extend(right.next()),
+            Object::Number(_) => merged.extend(left.next()),
+            _ => return Err(error(paren, "Comparator must return a number.")),
+        }
+    }
+
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
@@ -1,5 +1,8 @@
+use std::mem::take;
+
 use crate::ast::*;
-use crate::error::{error_token, Error};
+use crate::error::{Error, ErrorReporter, SourceReporter};
+use crate::error_code::ErrorCode;
 use crate::token::*;
 
 // Used a macro to implement the "match" method because Rust functions can't be
@@ -17,38 +20,89 @@ macro_rules! match_types {
 
 pub struct Parser<'a> {
     tokens: &'a Vec<Token>,
+    reporter: SourceReporter<'a>,
     current: usize,
+    ast: Ast,
+    // Whether a declaration failed to parse, anywhere in the program.
+    had_error: bool,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(tokens: &'a Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+    // Errors quote `source`, the source the tokens were scanned from.
+    pub fn new(
+        tokens: &'a Vec<Token>,
+        source: &'a str,
+        first_id: u32,
+        reporter: &'a mut dyn ErrorReporter,
+    ) -> Self {
+        Self {
+            tokens,
+            reporter: SourceReporter::new(source, reporter),
+            current: 0,
+            ast: Ast::new(first_id),
+            had_error: false,
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, Error> {
+    pub fn parse(&mut self) -> Result<Ast, Error> {
         let mut statements: Vec<Stmt> = Vec::new();
-        let mut had_error = false;
         while !self.is_at_end() {
-            match self.declaration() {
-                Ok(statement) => statements.push(statement),
-                Err(_) => {
-                    had_error = true;
-                    self.synchronize();
-                }
+            if let Some(statement) = self.recovering_declaration() {
+                statements.push(statement);
             }
         }
 
-        if !had_error {
-            Ok(statements)
+        if !self.had_error {
+            self.ast.statements = statements;
+            Ok(take(&mut self.ast))
         } else {
             Err(Error::Syntax)
         }
     }
 
-    fn expression(&mut self) -> Result<Expr, ()> {
+    // The returned AST has the expression as its only statement, so it can be
+    // resolved like a program.
+    pub fn parse_expression(&mut self) -> Result<(Ast, ExprId), Error> {
+        let line = self.peek().line;
+        let expr = self.expression().map_err(|_| Error::Syntax)?;
+
+        if !self.is_at_end() {
+            self.reporter.error_token(
+                self.peek(),
+                ErrorCode::TrailingTokens,
+                "Expect end of expression.",
+            );
+            return Err(Error::Syntax);
+        }
+
+        self.ast.statements = vec![Stmt::Expression {
+            expression: expr,
+            line,
+        }];
+        Ok((take(&mut self.ast), expr))
+    }
+
+    fn add(&mut self, expr: Expr) -> ExprId {
+        self.ast.add(expr)
+    }
+
+    fn expression(&mut self) -> Result<ExprId, ()> {
         self.assignment()
     }
 
+    // Skips to the next statement after an error, so the errors after it in
+    // the same block are reported too.
+    fn recovering_declaration(&mut self) -> Option<Stmt> {
+        match self.declaration() {
+            Ok(statement) => Some(statement),
+            Err(_) => {
+                self.had_error = true;
+                self.synchronize();
+                None
+            }
+        }
+    }
+
     fn declaration(&mut self) -> Result<Stmt, ()> {
         if match_types!(self, TokenType::Var) {
             self.var_declaration()
@@ -69,6 +123,8 @@ impl<'a> Parser<'a> {
             self.print_statement()
         } else if match_types!(self, TokenType::Return) {
             self.return_statement()
+        } else if match_types!(self, TokenType::Delete) {
+            self.delete_statement()
         } else if match_types!(self, TokenType::For) {
             self.for_statement()
         } else if match_types!(self, TokenType::While) {
@@ -81,6 +137,7 @@ impl<'a> Parser<'a> {
     }
 
     fn if_statement(&mut self) -> Result<Stmt, ()> {
+        let keyword = self.previous().to_owned();
         self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
@@ -93,6 +150,7 @@ impl<'a> Parser<'a> {
         };
 
         Ok(Stmt::If {
+            keyword,
             condition,
             then_branch,
             else_branch,
@@ -100,9 +158,13 @@ impl<'a> Parser<'a> {
     }
 
     fn print_statement(&mut self) -> Result<Stmt, ()> {
-        let value = self.expression()?;
+        let keyword = self.previous().to_owned();
+        let expression = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value")?;
-        Ok(Stmt::Print(value))
+        Ok(Stmt::Print {
+            keyword,
+            expression,
+        })
     }
 
     fn return_statement(&mut self) -> Result<Stmt, ()> {
@@ -119,7 +181,28 @@ impl<'a> Parser<'a> {
         Ok(Stmt::Return { keyword, value })
     }
 
+    fn delete_statement(&mut self) -> Result<Stmt, ()> {
+        let keyword = self.previous().to_owned();
+        let target = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after delete target.")?;
+
+        if let Expr::Get { object, name } = &self.ast[target] {
+            Ok(Stmt::Delete {
+                object: *object,
+                name: name.clone(),
+            })
+        } else {
+            self.reporter.error_token(
+                &keyword,
+                ErrorCode::InvalidDeleteTarget,
+                "Invalid delete target.",
+            );
+            Err(())
+        }
+    }
+
     fn for_statement(&mut self) -> Result<Stmt, ()> {
+        let keyword = self.previous().to_owned();
         self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
 
         let initializer = if match_types!(self, TokenType::Semicolon) {
@@ -133,13 +216,14 @@ impl<'a> Parser<'a> {
         let condition = if !self.check(TokenType::Semicolon) {
             self.expression()?
         } else {
-            Expr::Literal(Object::Boolean(true))
+            self.add(Expr::Literal(Object::Boolean(true)))
         };
 
         self.consume(TokenType::Semicolon, "Expect ';' after loop condition")?;
 
         let increment = if !self.check(TokenType::RightParen) {
-            Some(self.expression()?)
+            let line = self.peek().line;
+            Some((self.expression()?, line))
         } else {
             None
         };
@@ -148,12 +232,13 @@ impl<'a> Parser<'a> {
 
         let mut body = self.statement()?;
 
-        if let Some(inc_expr) = increment {
-            body = Stmt::Block(vec![body, Stmt::Expression(inc_expr)]);
+        if let Some((expression, line)) = increment {
+            body = Stmt::Block(vec![body, Stmt::Expression { expression, line }]);
         }
 
         // Desugaring
         body = Stmt::While {
+            keyword,
             condition,
             body: Box::new(body),
         };
@@ -166,6 +251,7 @@ impl<'a> Parser<'a> {
     }
 
     fn while_statement(&mut self) -> Result<Stmt, ()> {
+        let keyword = self.previous().to_owned();
         self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
@@ -173,6 +259,7 @@ impl<'a> Parser<'a> {
         let body = self.statement()?;
 
         Ok(Stmt::While {
+            keyword,
             condition,
             body: Box::new(body),
         })
@@ -212,8 +299,9 @@ impl<'a> Parser<'a> {
             .to_owned();
 
         let superclass = if match_types!(self, TokenType::Less) {
-            self.consume(TokenType::Identifier, "Expect superclass name")?;
-            Some(Expr::Variable(self.previous().to_owned()))
+            self.consume(TokenType::Identifier, "Expect superclass name.")?;
+            let name = self.previous().to_owned();
+            Some(self.add(Expr::Variable(name)))
         } else {
             None
         };
@@ -221,8 +309,13 @@ impl<'a> Parser<'a> {
         self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
 
         let mut methods = Vec::new();
+        let mut statics = Vec::new();
         while !self.check(TokenType::RightBrace) && !self.is_at_end() {
-            methods.push(self.function("method")?);
+            if match_types!(self, TokenType::Static) {
+                statics.push(self.var_declaration()?);
+            } else {
+                methods.push(self.function("method")?);
+            }
         }
 
         self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
@@ -231,125 +324,153 @@ impl<'a> Parser<'a> {
             name,
             superclass,
             methods,
+            statics,
         })
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, ()> {
-        let value = self.expression()?;
+        let line = self.peek().line;
+        let expression = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after expression")?;
-        Ok(Stmt::Expression(value))
+        Ok(Stmt::Expression { expression, line })
     }
 
     fn block(&mut self) -> Result<Vec<Stmt>, ()> {
         let mut statements = Vec::new();
 
         while !self.check(TokenType::RightBrace) && !self.is_at_end() {
-            statements.push(self.declaration()?)
+            if let Some(statement) = self.recovering_declaration() {
+                statements.push(statement);
+            }
         }
 
         self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
         Ok(statements)
     }
 
-    fn assignment(&mut self) -> Result<Expr, ()> {
+    fn assignment(&mut self) -> Result<ExprId, ()> {
         let expr = self.ternary()?;
 
         if match_types!(self, TokenType::Equal) {
             let equals = self.previous().to_owned();
             let value = self.assignment()?;
 
-            if let Expr::Variable(name) = expr {
-                return Ok(Expr::Assign {
-                    name,
-                    value: Box::new(value),
-                });
-            } else if let Expr::Get { object, name } = expr {
-                return Ok(Expr::Set {
+            let target = match &self.ast[expr] {
+                Expr::Variable(name) => Some(Expr::Assign {
+                    name: name.clone(),
+                    value,
+                }),
+                Expr::Get { object, name } => Some(Expr::Set {
+                    object: *object,
+                    name: name.clone(),
+                    value,
+                }),
+                Expr::Index {
                     object,
-                    name,
-                    value: Box::new(value),
-                });
+                    bracket,
+                    index,
+                } => Some(Expr::IndexSet {
+                    object: *object,
+                    bracket: bracket.clone(),
+                    index: *index,
+                    value,
+                }),
+                _ => None,
+            };
+
+            if let Some(target) = target {
+                return Ok(self.add(target));
             }
 
-            error_token(&equals, "Invalid assignment target.");
+            self.reporter.error_token(
+                &equals,
+                ErrorCode::InvalidAssignmentTarget,
+                "Invalid assignment target.",
+            );
         }
 
         Ok(expr)
     }
 
-    fn ternary(&mut self) -> Result<Expr, ()> {
+    fn ternary(&mut self) -> Result<ExprId, ()> {
         let mut expr = self.or()?;
 
         if match_types!(self, TokenType::Question) {
+            let question = self.previous().to_owned();
             let then_branch = self.ternary()?;
 
             if !self.check(TokenType::Colon) {
-                error_token(self.previous(), "Expect ':' in ternary expression");
+                self.reporter.error_token(
+                    self.previous(),
+                    ErrorCode::MissingColon,
+                    "Expect ':' in ternary expression",
+                );
                 return Err(());
             }
 
             self.advance();
             let else_branch = self.ternary()?;
 
-            expr = Expr::Ternary {
-                condition: Box::new(expr),
-                then_branch: Box::new(then_branch),
-                else_branch: Box::new(else_branch),
-            }
+            expr = self.add(Expr::Ternary {
+                question,
+                condition: expr,
+                then_branch,
+                else_branch,
+            })
         }
 
         Ok(expr)
     }
 
-    fn or(&mut self) -> Result<Expr, ()> {
+    fn or(&mut self) -> Result<ExprId, ()> {
         let mut expr = self.and()?;
 
         while match_types!(self, TokenType::Or) {
             let operator = self.previous().to_owned();
             let right = self.and()?;
-            expr = Expr::Logical {
-                left: Box::new(expr),
+            expr = self.add(Expr::Logical {
+                left: expr,
                 operator,
-                right: Box::new(right),
-            }
+                right,
+            });
         }
 
         Ok(expr)
     }
 
-    fn and(&mut self) -> Result<Expr, ()> {
+    fn and(&mut self) -> Result<ExprId, ()> {
         let mut expr = self.equality()?;
 
         while match_types!(self, TokenType::And) {
             let operator = self.previous().to_owned();
             let right = self.equality()?;
-            expr = Expr::Logical {
-                left: Box::new(expr),
+            expr = self.add(Expr::Logical {
+                left: expr,
                 operator,
-                right: Box::new(right),
-            }
+                right,
+            });
         }
 
         Ok(expr)
     }
 
-    fn equality(&mut self) -> Result<Expr, ()> {
+    fn equality(&mut self) -> Result<ExprId, ()> {
         let mut expr = self.comparison()?;
 
         while match_types!(self, TokenType::BangEqual, TokenType::EqualEqual) {
             let operator = self.previous().to_owned();
             let right = self.comparison()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
+            expr = self.add(Expr::Binary {
+                left: expr,
                 operator,
-                right: Box::new(right),
-            };
+                right,
+            });
         }
 
         Ok(expr)
     }
 
-    fn comparison(&mut self) -> Result<Expr, ()> {
+    fn comparison(&mut self) -> Result<ExprId, ()> {
         let mut expr = self.term()?;
 
         while match_types!(
@@ -357,75 +478,77 @@ impl<'a> Parser<'a> {
             TokenType::Greater,
             TokenType::GreaterEqual,
             TokenType::Less,
-            TokenType::LessEqual
+            TokenType::LessEqual,
+            TokenType::Is
         ) {
             let operator = self.previous().to_owned();
             let right = self.term()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
+            expr = self.add(Expr::Binary {
+                left: expr,
                 operator,
-                right: Box::new(right),
-            }
+                right,
+            });
         }
 
         Ok(expr)
     }
 
-    fn term(&mut self) -> Result<Expr, ()> {
+    fn term(&mut self) -> Result<ExprId, ()> {
         let mut expr = self.factor()?;
 
         while match_types!(self, TokenType::Minus, TokenType::Plus) {
             let operator = self.previous().to_owned();
             let right = self.factor()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
+            expr = self.add(Expr::Binary {
+                left: expr,
                 operator,
-                right: Box::new(right),
-            }
+                right,
+            });
         }
 
         Ok(expr)
     }
 
-    fn factor(&mut self) -> Result<Expr, ()> {
+    fn factor(&mut self) -> Result<ExprId, ()> {
         let mut expr = self.unary()?;
 
         while match_types!(self, TokenType::Slash, TokenType::Star, TokenType::Percent) {
             let operator = self.previous().to_owned();
             let right = self.unary()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
+            expr = self.add(Expr::Binary {
+                left: expr,
                 operator,
-                right: Box::new(right),
-            }
+                right,
+            });
         }
 
         Ok(expr)
     }
 
-    fn unary(&mut self) -> Result<Expr, ()> {
+    fn unary(&mut self) -> Result<ExprId, ()> {
         if match_types!(self, TokenType::Bang, TokenType::Minus) {
             let operator = self.previous().to_owned();
             let right = self.unary()?;
-            Ok(Expr::Unary {
-                operator,
-                right: Box::new(right),
-            })
+            Ok(self.add(Expr::Unary { operator, right }))
         } else {
             self.call()
         }
     }
 
-    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ()> {
+    fn finish_call(&mut self, callee: ExprId) -> Result<ExprId, ()> {
         let mut arguments = Vec::new();
 
         if !self.check(TokenType::RightParen) {
             loop {
                 if arguments.len() >= 255 {
-                    error_token(self.peek(), "Can't have more than 255 arguments.");
+                    self.reporter.error_token(
+                        self.peek(),
+                        ErrorCode::TooManyArguments,
+                        "Can't have more than 255 arguments.",
+                    );
                 }
 
-                arguments.push(self.expression()?);
+                arguments.push(self.element()?);
 
                 if !match_types!(self, TokenType::Comma) {
                     break;
@@ -437,14 +560,25 @@ impl<'a> Parser<'a> {
             .consume(TokenType::RightParen, "Expect ')' after arguments")?
             .to_owned();
 
-        Ok(Expr::Call {
-            callee: Box::new(callee),
+        Ok(self.add(Expr::Call {
+            callee,
             paren,
             arguments,
-        })
+        }))
+    }
+
+    // A call argument or list element, which can spread a list into several.
+    fn element(&mut self) -> Result<ExprId, ()> {
+        if match_types!(self, TokenType::Ellipsis) {
+            let ellipsis = self.previous().to_owned();
+            let list = self.expression()?;
+            Ok(self.add(Expr::Spread { ellipsis, list }))
+        } else {
+            self.expression()
+        }
     }
 
-    fn call(&mut self) -> Result<Expr, ()> {
+    fn call(&mut self) -> Result<ExprId, ()> {
         let mut expr = self.primary()?;
 
         loop {
@@ -454,10 +588,16 @@ impl<'a> Parser<'a> {
                 let name = self
                     .consume(TokenType::Identifier, "Expect property name after '.'.")?
                     .to_owned();
-                expr = Expr::Get {
-                    object: Box::new(expr),
-                    name,
-                }
+                expr = self.add(Expr::Get { object: expr, name })
+            } else if match_types!(self, TokenType::LeftBracket) {
+                let bracket = self.previous().to_owned();
+                let index = self.expression()?;
+                self.consume(TokenType::RightBracket, "Expect ']' after index.")?;
+                expr = self.add(Expr::Index {
+                    object: expr,
+                    bracket,
+                    index,
+                })
             } else {
                 break;
             }
@@ -466,14 +606,21 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn function_expr(&mut self, kind: &str) -> Result<Expr, ()> {
-        self.consume(TokenType::LeftParen, &format!("Expect '('."))?;
+    fn function_expr(&mut self, kind: &str) -> Result<ExprId, ()> {
+        let start = self
+            .consume(TokenType::LeftParen, &format!("Expect '('."))?
+            .span
+            .start;
 
         let mut params = Vec::new();
         if !self.check(TokenType::RightParen) {
             loop {
                 if params.len() >= 255 {
-                    error_token(self.peek(), "Can't have more than 255 parameters.");
+                    self.reporter.error_token(
+                        self.peek(),
+                        ErrorCode::TooManyParameters,
+                        "Can't have more than 255 parameters.",
+                    );
                 }
 
                 params.push(
@@ -493,28 +640,31 @@ impl<'a> Parser<'a> {
             &format!("Expect '{{' before {kind} body."),
         )?;
         let body = self.block()?;
+        let end = self.previous().span.end;
 
-        Ok(Expr::Lambda { params, body })
+        let function = self.add(Expr::Lambda { params, body });
+        self.ast.add_function_span(function, start..end);
+        Ok(function)
     }
 
-    fn primary(&mut self) -> Result<Expr, ()> {
+    fn primary(&mut self) -> Result<ExprId, ()> {
         let expr = match &self.peek().token_type {
             TokenType::False => Expr::Literal(Object::Boolean(false)),
             TokenType::True => Expr::Literal(Object::Boolean(true)),
             TokenType::Nil => Expr::Literal(Object::Nil),
             TokenType::Number(literal) => Expr::Literal(Object::Number(literal.to_owned())),
-            TokenType::String(literal) => Expr::Literal(Object::String(literal.to_owned())),
+            TokenType::String(literal) => Expr::Literal(Object::String(literal.as_str().into())),
             TokenType::Identifier => Expr::Variable(self.peek().to_owned()),
             TokenType::Fun => {
                 self.advance();
-                return Ok(self.function_expr("function")?);
+                return self.function_expr("function");
             }
             TokenType::LeftParen => {
                 // This is needed to consume the LeftParen Token, since we don't use match_types! here
                 self.advance();
                 let expr = self.expression()?;
                 self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
-                return Ok(Expr::Grouping(Box::new(expr)));
+                return Ok(self.add(Expr::Grouping(expr)));
             }
             TokenType::Super => {
                 let keyword = self.peek().to_owned();
@@ -525,28 +675,63 @@ impl<'a> Parser<'a> {
                 let method = self
                     .consume(TokenType::Identifier, "Expect superclass method name.")?
                     .to_owned();
-                return Ok(Expr::Super { keyword, method });
+                return Ok(self.add(Expr::Super { keyword, method }));
             }
             TokenType::This => Expr::This(self.peek().to_owned()),
+            TokenType::LeftBracket => {
+                self.advance();
+                let mut elements = Vec::new();
+                if !self.check(TokenType::RightBracket) {
+                    loop {
+                        elements.push(self.element()?);
+
+                        if !match_types!(self, TokenType::Comma) {
+                            break;
+                        }
+                    }
+                }
+
+                self.consume(TokenType::RightBracket, "Expect ']' after list elements.")?;
+                return Ok(self.add(Expr::List(elements)));
+            }
             _ => {
-                error_token(self.peek(), "Expect expression.");
+                self.reporter.error_token(
+                    self.peek(),
+                    ErrorCode::MissingExpression,
+                    "Expect expression.",
+                );
                 return Err(());
             }
         };
 
         self.advance();
-        Ok(expr)
+        Ok(self.add(expr))
     }
 
     fn consume(&mut self, token_type: TokenType, message: &str) -> Result<&Token, ()> {
+        let code = Parser::missing(&token_type);
         if self.check(token_type) {
             Ok(self.advance())
         } else {
-            error_token(self.peek(), message);
+            self.reporter.error_token(self.peek(), code, message);
             Err(())
         }
     }
 
+    fn missing(token_type: &TokenType) -> ErrorCode {
+        match token_type {
+            TokenType::Semicolon => ErrorCode::MissingSemicolon,
+            TokenType::LeftParen => ErrorCode::MissingLeftParen,
+            TokenType::RightParen => ErrorCode::MissingRightParen,
+            TokenType::LeftBrace => ErrorCode::MissingLeftBrace,
+            TokenType::RightBrace => ErrorCode::MissingRightBrace,
+            TokenType::RightBracket => ErrorCode::MissingRightBracket,
+            TokenType::Identifier => ErrorCode::MissingName,
+            TokenType::Dot => ErrorCode::MissingDot,
+            _ => unreachable!(),
+        }
+    }
+
     fn check(&self, token_type: TokenType) -> bool {
         if self.is_at_end() {
             false
@@ -577,11 +762,11 @@ impl<'a> Parser<'a> {
         self.peek().token_type == TokenType::EOF
     }
 
-    fn peek(&self) -> &Token {
+    fn peek(&self) -> &'a Token {
         &self.tokens[self.current]
     }
 
-    fn previous(&self) -> &Token {
+    fn previous(&self) -> &'a Token {
         &self.tokens[self.current - 1]
     }
 
@@ -601,7 +786,8 @@ impl<'a> Parser<'a> {
                 | TokenType::If
                 | TokenType::While
                 | TokenType::Print
-                | TokenType::Return => return,
+                | TokenType::Return
+                | TokenType::Delete => return,
                 _ => self.advance(),
             };
         }
@@ -0,0 +1,167 @@
+use std::fmt::Write;
+use std::rc::Rc;
+
+use crate::ast::Object;
+
+/// How `render` formats values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// What `print` shows, strings are written without quotes.
+    Plain,
+    /// Like `Plain`, but strings are quoted and escaped.
+    Quoted,
+    /// JSON. Instances are objects holding their fields, `nil` is `null` and
+    /// functions and classes are strings.
+    Json,
+    /// Like `Quoted`, with the elements of lists and the fields of instances
+    /// on their own indented lines.
+    Pretty,
+}
+
+/// Formats a value in the given style. Lists and instances that contain
+/// themselves are shown as `...` where they repeat (`null` in JSON).
+pub fn render(value: &Object, style: Style) -> String {
+    let mut renderer = Renderer {
+        style,
+        output: String::new(),
+        visiting: Vec::new(),
+    };
+    renderer.value(value, 0);
+    renderer.output
+}
+
+struct Renderer {
+    style: Style,
+    output: String,
+    // The lists and instances being rendered, to stop at cycles.
+    visiting: Vec<*const ()>,
+}
+
+impl Renderer {
+    fn value(&mut self, value: &Object, depth: usize) {
+        match value {
+            Object::String(string) if self.style == Style::Plain => self.output.push_str(string),
+            Object::String(string) => self.quoted(string),
+            Object::Number(number) if self.style == Style::Json && !number.is_finite() => {
+                self.output.push_str("null")
+            }
+            Object::Number(number) => write!(self.output, "{number}").unwrap(),
+            Object::Boolean(boolean) => write!(self.output, "{boolean}").unwrap(),
+            Object::Nil if self.style == Style::Json => self.output.push_str("null"),
+            Object::Nil => self.output.push_str("nil"),
+            Object::Callable(callable) if self.style == Style::Json => {
+                self.quoted(&callable.to_string())
+            }
+            Object::Callable(callable) => write!(self.output, "{callable}").unwrap(),
+            Object::List(list) => {
+                let id = Rc::as_ptr(list) as *const ();
+                if self.visiting.contains(&id) {
+                    return self.cycle();
+                }
+
+                self.visiting.push(id);
+                let elements = list.borrow();
+                let entries: Vec<(Option<&str>, &Object)> =
+                    elements.iter().map(|element| (None, element)).collect();
+                self.entries("[", "]", &entries, depth);
+                self.visiting.pop();
+            }
+            Object::Instance(instance) => {
+                let id = Rc::as_ptr(instance) as *const ();
+                if self.visiting.contains(&id) {
+                    return self.cycle();
+                }
+
+                let instance = instance.borrow();
+                let open = match self.style {
+                    Style::Plain | Style::Quoted => {
+                        return write!(self.output, "{instance}").unwrap();
+                    }
+                    Style::Json => String::from("{"),
+                    Style::Pretty => format!("{} {{", instance.class_name()),
+                };
+
+                self.visiting.push(id);
+                let mut entries: Vec<(Option<&str>, &Object)> = instance
+                    .fields()
+                    .map(|(name, value)| (Some(name.as_ref()), value))
+                    .collect();
+                entries.sort_by_key(|(name, _)| *name);
+                self.entries(&open, "}", &entries, depth);
+                self.visiting.pop();
+            }
+        }
+    }
+
+    fn entries(
+        &mut self,
+        open: &str,
+        close: &str,
+        entries: &[(Option<&str>, &Object)],
+        depth: usize,
+    ) {
+        self.output.push_str(open);
+        let pretty = self.style == Style::Pretty && !entries.is_empty();
+        for (index, (name, value)) in entries.iter().enumerate() {
+            if index > 0 {
+                self.output.push(',');
+            }
+
+            if pretty {
+                self.output.push('\n');
+                self.indent(depth + 1);
+            } else if index > 0 {
+                self.output.push(' ');
+            }
+
+            match name {
+                Some(name) if self.style == Style::Json => {
+                    self.quoted(name);
+                    self.output.push_str(": ");
+                }
+                Some(name) => write!(self.output, "{name}: ").unwrap(),
+                None => (),
+            }
+
+            self.value(value, depth + 1);
+        }
+
+        if pretty {
+            self.output.push('\n');
+            self.indent(depth);
+        }
+        self.output.push_str(close);
+    }
+
+    fn cycle(&mut self) {
+        let marker = if self.style == Style::Json {
+            "null"
+        } else {
+            "..."
+        };
+        self.output.push_str(marker);
+    }
+
+    fn indent(&mut self, depth: usize) {
+        for _ in 0..depth {
+            self.output.push_str("  ");
+        }
+    }
+
+    // Quotes a string, escaping it like JSON does.
+    fn quoted(&mut self, string: &str) {
+        self.output.push('"');
+        for c in string.chars() {
+            match c {
+                '"' => self.output.push_str("\\\""),
+                '\\' => self.output.push_str("\\\\"),
+                '\n' => self.output.push_str("\\n"),
+                '\r' => self.output.push_str("\\r"),
+                '\t' => self.output.push_str("\\t"),
+                c if c.is_control() => write!(self.output, "\\u{:04x}", c as u32).unwrap(),
+                c => self.output.push(c),
+            }
+        }
+        self.output.push('"');
+    }
+}
@@ -1,27 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::mem::replace;
+use std::rc::Rc;
 
-use crate::ast::{AstVisitor, Expr, Stmt};
-use crate::error::error_token;
-use crate::interpreter::Interpreter;
+use crate::ast::{Ast, AstVisitor, Expr, ExprId, Stmt};
+use crate::error::{ErrorReporter, SourceReporter};
+use crate::error_code::ErrorCode;
 use crate::token::Token;
 
 enum VarState {
     Declared,
     Defined,
-    Used,
 }
 
 struct Var {
-    name: Option<Token>,
     state: VarState,
+    // Where the variable is stored in its environment.
+    slot: usize,
 }
 
+// What's resolved is recorded in the AST, for the interpreter to read.
 pub struct Resolver<'a> {
-    interpreter: &'a mut Interpreter,
-    scopes: Vec<HashMap<String, Var>>,
+    ast: &'a Ast,
+    scopes: Vec<HashMap<Rc<str>, Var>>,
+    // The AST's ID for each scope in `scopes`.
+    scope_ids: Vec<usize>,
     current_function: FunctionType,
     current_class: ClassType,
+    reporter: SourceReporter<'a>,
     pub had_error: bool,
 }
 
@@ -39,12 +44,16 @@ enum ClassType {
 }
 
 impl<'a> Resolver<'a> {
-    pub fn new(interpreter: &'a mut Interpreter) -> Self {
+    // Errors quote the source kept by the AST.
+    pub fn new(ast: &'a Ast, reporter: &'a mut dyn ErrorReporter) -> Self {
+        let source = ast.source.as_deref().unwrap_or("");
         Self {
-            interpreter,
+            ast,
             scopes: Vec::new(),
+            scope_ids: Vec::new(),
             current_function: FunctionType::None,
             current_class: ClassType::None,
+            reporter: SourceReporter::new(source, reporter),
             had_error: false,
         }
     }
@@ -60,6 +69,17 @@ impl<'a> Resolver<'a> {
 
         self.begin_scope();
 
+        // Methods get 'this' in the first slot of their environment.
+        if let FunctionType::Method | FunctionType::Initializer = self.current_function {
+            self.scopes.last_mut().unwrap().insert(
+                "this".into(),
+                Var {
+                    state: VarState::Defined,
+                    slot: 0,
+                },
+            );
+        }
+
         for param in params {
             self.declare(param);
             self.define(param);
@@ -71,36 +91,39 @@ impl<'a> Resolver<'a> {
     }
 
     fn begin_scope(&mut self) {
+        let enclosing = self.scope_ids.last().copied();
+        self.scope_ids.push(self.ast.add_scope(enclosing));
         self.scopes.push(HashMap::new());
     }
 
     fn end_scope(&mut self) {
-        if let Some(scope) = self.scopes.pop() {
-            for var in scope.values() {
-                if let VarState::Used = var.state {
-                    continue;
-                }
-
-                if let Some(name) = &var.name {
-                    self.error(&name, &format!("Variable '{}' is never used.", name.lexeme));
-                }
-            }
-        }
+        self.scope_ids.pop();
+        self.scopes.pop();
     }
 
     fn declare(&mut self, name: &Token) {
         if let Some(scope) = self.scopes.last_mut() {
             let had_key = scope.contains_key(&name.lexeme);
+            let slot = scope.len();
             scope.insert(
                 name.lexeme.clone(),
                 Var {
-                    name: Some(name.to_owned()),
                     state: VarState::Declared,
+                    slot,
                 },
             );
 
+            if !had_key {
+                let id = *self.scope_ids.last().unwrap();
+                self.ast.add_scope_name(id, name.lexeme.clone());
+            }
+
             if had_key {
-                self.error(&name, "Already a variable with this name in this scope.");
+                self.error(
+                    &name,
+                    ErrorCode::AlreadyDeclared,
+                    "Already a variable with this name in this scope.",
+                );
             }
         }
     }
@@ -113,43 +136,49 @@ impl<'a> Resolver<'a> {
         }
     }
 
-    fn resolve_local(&mut self, name: &Token, is_used: bool) {
-        for (index, scope) in self.scopes.iter_mut().rev().enumerate() {
-            if scope.contains_key(&name.lexeme) {
-                self.interpreter.resolve(name.clone(), index.clone());
-
-                if is_used {
-                    scope.get_mut(&name.lexeme).unwrap().state = VarState::Used;
-                }
-
+    fn resolve_local(&mut self, expr: ExprId, name: &Token) {
+        for (index, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(var) = scope.get(&name.lexeme) {
+                self.ast.resolve(expr, index, var.slot);
                 return;
             }
         }
+
+        // It's a global, so the locals in scope can only help suggest what
+        // was meant if it isn't defined.
+        if let Some(&id) = self.scope_ids.last() {
+            self.ast.resolve_global(expr, id);
+        }
     }
 
-    fn error(&mut self, token: &Token, message: &str) {
-        error_token(token, message);
+    fn error(&mut self, token: &Token, code: ErrorCode, message: &str) {
+        self.reporter.error_token(token, code, message);
         self.had_error = true;
     }
 }
 
 impl<'a> AstVisitor<(), ()> for Resolver<'a> {
-    fn visit_expr(&mut self, expr: &Expr) {
-        match expr {
+    fn visit_expr(&mut self, expr: ExprId) {
+        let ast = self.ast;
+        match &ast[expr] {
             Expr::Variable(name) => {
                 if let Some(scope) = self.scopes.last() {
                     if let Some(var) = scope.get(&name.lexeme) {
                         if let VarState::Declared = var.state {
-                            self.error(&name, "Can't read local variable in its own initializer.");
+                            self.error(
+                                &name,
+                                ErrorCode::ReadInOwnInitializer,
+                                "Can't read local variable in its own initializer.",
+                            );
                         }
                     }
                 }
 
-                self.resolve_local(name, true);
+                self.resolve_local(expr, name);
             }
             Expr::Assign { name, value } => {
-                self.visit_expr(value);
-                self.resolve_local(name, false);
+                self.visit_expr(*value);
+                self.resolve_local(expr, name);
             }
             Expr::Lambda { params, body } => {
                 self.resolve_function(params, body, FunctionType::Function);
@@ -158,55 +187,88 @@ impl<'a> AstVisitor<(), ()> for Resolver<'a> {
                 condition,
                 then_branch,
                 else_branch,
+                ..
             } => {
-                self.visit_expr(&condition);
-                self.visit_expr(&then_branch);
-                self.visit_expr(&else_branch);
+                self.visit_expr(*condition);
+                self.visit_expr(*then_branch);
+                self.visit_expr(*else_branch);
             }
             Expr::Binary { left, right, .. } => {
-                self.visit_expr(&left);
-                self.visit_expr(&right);
+                self.visit_expr(*left);
+                self.visit_expr(*right);
             }
             Expr::Call {
                 callee, arguments, ..
             } => {
-                self.visit_expr(&callee);
+                self.visit_expr(*callee);
                 for argument in arguments {
-                    self.visit_expr(argument);
+                    self.visit_expr(*argument);
                 }
             }
-            Expr::Grouping(expr) => self.visit_expr(expr),
+            Expr::Grouping(expr) | Expr::Spread { list: expr, .. } => self.visit_expr(*expr),
             Expr::Logical { left, right, .. } => {
-                self.visit_expr(&left);
-                self.visit_expr(&right);
+                self.visit_expr(*left);
+                self.visit_expr(*right);
             }
-            Expr::Unary { right, .. } => self.visit_expr(&right),
+            Expr::Unary { right, .. } => self.visit_expr(*right),
             Expr::Get { object, .. } => {
-                self.visit_expr(&object);
+                self.visit_expr(*object);
             }
             Expr::Set { object, value, .. } => {
-                self.visit_expr(&value);
-                self.visit_expr(&object);
+                self.visit_expr(*value);
+                self.visit_expr(*object);
             }
             Expr::Super { keyword, .. } => {
                 match self.current_class {
-                    ClassType::None => self.error(keyword, "Can't use 'super' outside of a class."),
-                    ClassType::Subclass => self.resolve_local(keyword, true),
-                    _ => self.error(keyword, "Can't use 'super' in a class with no superclass."),
+                    ClassType::None => self.error(
+                        keyword,
+                        ErrorCode::SuperOutsideClass,
+                        "Can't use 'super' outside of a class.",
+                    ),
+                    ClassType::Subclass => self.resolve_local(expr, keyword),
+                    _ => self.error(
+                        keyword,
+                        ErrorCode::SuperWithoutSuperclass,
+                        "Can't use 'super' in a class with no superclass.",
+                    ),
                 };
             }
             Expr::This(keyword) => {
                 if let ClassType::None = self.current_class {
-                    self.error(keyword, "Can't use 'this' outside of a class.")
+                    self.error(
+                        keyword,
+                        ErrorCode::ThisOutsideClass,
+                        "Can't use 'this' outside of a class.",
+                    )
                 }
 
-                self.resolve_local(keyword, true);
+                self.resolve_local(expr, keyword);
             }
             Expr::Literal(_) => (),
+            Expr::List(elements) => {
+                for element in elements {
+                    self.visit_expr(*element);
+                }
+            }
+            Expr::Index { object, index, .. } => {
+                self.visit_expr(*object);
+                self.visit_expr(*index);
+            }
+            Expr::IndexSet {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                self.visit_expr(*object);
+                self.visit_expr(*index);
+                self.visit_expr(*value);
+            }
         }
     }
 
     fn visit_stmt(&mut self, stmt: &Stmt) {
+        let ast = self.ast;
         match stmt {
             Stmt::Block(statements) => {
                 self.begin_scope();
@@ -216,86 +278,117 @@ impl<'a> AstVisitor<(), ()> for Resolver<'a> {
             Stmt::Var { name, initializer } => {
                 self.declare(name);
                 if let Some(expr) = initializer {
-                    self.visit_expr(expr);
+                    self.visit_expr(*expr);
                 }
                 self.define(name);
             }
             Stmt::Function { name, definition } => {
                 self.declare(name);
                 self.define(name);
-                self.visit_expr(definition);
+                self.visit_expr(*definition);
             }
-            Stmt::Expression(expr) => self.visit_expr(expr),
+            Stmt::Expression {
+                expression: expr, ..
+            } => self.visit_expr(*expr),
             Stmt::If {
                 condition,
                 then_branch,
                 else_branch,
+                ..
             } => {
-                self.visit_expr(condition);
-                self.visit_stmt(&then_branch);
+                self.visit_expr(*condition);
+                self.visit_stmt(then_branch);
                 if let Some(else_stmt) = else_branch {
                     self.visit_stmt(&else_stmt);
                 }
             }
-            Stmt::Print(expr) => self.visit_expr(expr),
+            Stmt::Print { expression, .. } => self.visit_expr(*expression),
             Stmt::Return { keyword, value } => {
                 if let FunctionType::None = self.current_function {
-                    self.error(&keyword, "Can't return from top-level code.");
+                    self.error(
+                        &keyword,
+                        ErrorCode::ReturnFromTopLevel,
+                        "Can't return from top-level code.",
+                    );
                 }
 
                 if let Some(expression) = value {
                     if let FunctionType::Initializer = self.current_function {
-                        self.error(&keyword, "Can't return a value from an initializer.")
+                        self.error(
+                            &keyword,
+                            ErrorCode::ReturnValueFromInitializer,
+                            "Can't return a value from an initializer.",
+                        )
                     }
-                    self.visit_expr(expression);
+                    self.visit_expr(*expression);
                 }
             }
-            Stmt::While { condition, body } => {
-                self.visit_expr(condition);
-                self.visit_stmt(&body);
+            Stmt::Delete { object, .. } => self.visit_expr(*object),
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.visit_expr(*condition);
+                self.visit_stmt(body);
             }
             Stmt::Class {
                 name,
                 superclass,
                 methods,
+                statics,
             } => {
-                let enclosing_class = replace(&mut self.current_class, ClassType::Class);
-
                 self.declare(name);
                 self.define(name);
 
-                if let Some(Expr::Variable(class_name)) = superclass {
+                // Static initializers run outside the class, where there's no 'this'.
+                for field in statics {
+                    if let Stmt::Var {
+                        initializer: Some(expr),
+                        ..
+                    } = field
+                    {
+                        self.visit_expr(*expr);
+                    }
+                }
+
+                let enclosing_class = replace(&mut self.current_class, ClassType::Class);
+
+                if let Some((id, Expr::Variable(class_name))) = superclass.map(|id| (id, &ast[id]))
+                {
                     if name.lexeme == class_name.lexeme {
-                        self.error(class_name, "A class can't inherit from itself.");
+                        self.error(
+                            class_name,
+                            ErrorCode::InheritFromSelf,
+                            "A class can't inherit from itself.",
+                        );
                     }
 
                     self.current_class = ClassType::Subclass;
 
-                    self.resolve_local(class_name, true);
+                    self.resolve_local(id, class_name);
 
                     self.begin_scope();
                     self.scopes.last_mut().unwrap().insert(
-                        String::from("super"),
+                        "super".into(),
                         Var {
-                            name: None,            // Doesn't have a name Token, as it's not declared
-                            state: VarState::Used, // Assume that 'this' is always used
+                            state: VarState::Defined,
+                            slot: 0,
                         },
                     );
                 }
 
-                self.begin_scope();
-                self.scopes.last_mut().unwrap().insert(
-                    String::from("this"),
-                    Var {
-                        name: None,            // Doesn't have a name Token, as it's not declared
-                        state: VarState::Used, // Assume that 'this' is always used
-                    },
-                );
-
+                let mut method_names = HashSet::new();
                 for method in methods {
                     if let Stmt::Function { definition, name } = method {
-                        if let Expr::Lambda { params, body } = definition {
-                            let func_type = if name.lexeme == "init" {
+                        if !method_names.insert(&name.lexeme) {
+                            self.error(
+                                name,
+                                ErrorCode::DuplicateMethod,
+                                "Already a method with this name in this class.",
+                            );
+                        }
+
+                        if let Expr::Lambda { params, body } = &ast[*definition] {
+                            let func_type = if &*name.lexeme == "init" {
                                 FunctionType::Initializer
                             } else {
                                 FunctionType::Method
@@ -306,8 +399,6 @@ impl<'a> AstVisitor<(), ()> for Resolver<'a> {
                     }
                 }
 
-                self.end_scope();
-
                 if superclass.is_some() {
                     self.end_scope();
                 }
@@ -1,22 +1,27 @@
-use itertools::{Itertools, MultiPeek};
 use phf_macros::phf_map;
-use std::str::Chars;
+use std::collections::HashSet;
+use std::io::{self, BufRead};
+use std::rc::Rc;
 
-use crate::error::error_line;
+use crate::error::{ErrorReporter, SourceReporter};
+use crate::error_code::ErrorCode;
 use crate::token::{Token, TokenType};
 
 static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "and" => TokenType::And,
     "class" => TokenType::Class,
+    "delete" => TokenType::Delete,
     "else" => TokenType::Else,
     "false" => TokenType::False,
     "for" => TokenType::For,
     "fun" => TokenType::Fun,
     "if" => TokenType::If,
+    "is" => TokenType::Is,
     "nil" => TokenType::Nil,
     "or" => TokenType::Or,
     "print" => TokenType::Print,
     "return" => TokenType::Return,
+    "static" => TokenType::Static,
     "super" => TokenType::Super,
     "this" => TokenType::This,
     "true" => TokenType::True,
@@ -24,24 +29,152 @@ static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "while" => TokenType::While
 };
 
-pub struct Scanner<'a> {
-    source: String,
-    source_iter: MultiPeek<Chars<'a>>,
+// State of an open "#if" directive.
+struct Condition {
+    // Where the directive is, to report it if it's never closed.
+    line: u32,
+    column: u32,
+    enclosing_active: bool,
+    value: bool,
+    in_else: bool,
+}
+
+pub struct Scanner<'a, I> {
+    input: I,
+    // Where errors and warnings go. They quote the source read so far.
+    reporter: &'a mut dyn ErrorReporter,
+    // Whether the input failed to be read.
+    unreadable: bool,
     tokens: Vec<Token>,
     start: usize,
     current: usize,
     line: u32,
+    // The column of the next character, and of the start of the lexeme.
+    column: u32,
+    start_column: u32,
+    flags: &'a HashSet<String>,
+    conditions: Vec<Condition>,
+    // Every lexeme seen so far, so tokens with the same text share it.
+    lexemes: HashSet<Rc<str>>,
+    // Whether comments and directives are kept as tokens. Directives aren't
+    // applied then, so the code of every branch is kept too.
+    keep_trivia: bool,
+}
+
+// Where the scanner reads the source from. Lexemes are slices of the text
+// read so far, and positions are byte offsets into it.
+pub trait Input {
+    // Reads until at least `end` bytes have been read, or the source ends.
+    fn fill(&mut self, end: usize) -> io::Result<()>;
+    // The source read so far.
+    fn text(&self) -> &str;
+}
+
+// A source already in memory.
+pub struct StrInput<'a>(&'a str);
+
+impl Input for StrInput<'_> {
+    fn fill(&mut self, _: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn text(&self) -> &str {
+        self.0
+    }
+}
+
+// A source read a line at a time, as it's scanned.
+pub struct ReadInput<R> {
+    reader: R,
+    text: String,
+    // Whether the reader has no more input, or failed.
+    exhausted: bool,
+}
+
+impl<R: BufRead> Input for ReadInput<R> {
+    fn fill(&mut self, end: usize) -> io::Result<()> {
+        while self.text.len() < end && !self.exhausted {
+            match self.reader.read_line(&mut self.text) {
+                Ok(0) => self.exhausted = true,
+                Ok(_) => (),
+                Err(err) => {
+                    self.exhausted = true;
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl<'a> Scanner<'a, StrInput<'a>> {
+    pub fn new(
+        source: &'a str,
+        flags: &'a HashSet<String>,
+        reporter: &'a mut dyn ErrorReporter,
+    ) -> Self {
+        Scanner::with_input(StrInput(source), flags, reporter)
+    }
+
+    pub fn with_trivia(
+        source: &'a str,
+        flags: &'a HashSet<String>,
+        reporter: &'a mut dyn ErrorReporter,
+    ) -> Self {
+        Self {
+            keep_trivia: true,
+            ..Scanner::new(source, flags, reporter)
+        }
+    }
+}
+
+impl<'a, R: BufRead> Scanner<'a, ReadInput<R>> {
+    // Scans input as it's read, without reading all of it first.
+    pub fn from_reader(
+        reader: R,
+        flags: &'a HashSet<String>,
+        reporter: &'a mut dyn ErrorReporter,
+    ) -> Self {
+        let input = ReadInput {
+            reader,
+            text: String::new(),
+            exhausted: false,
+        };
+        Scanner::with_input(input, flags, reporter)
+    }
+
+    // The tokens scanned by `scan_tokens`, and the source they were scanned
+    // from.
+    pub fn into_parts(self) -> (Vec<Token>, String) {
+        (self.tokens, self.input.text)
+    }
 }
 
-impl<'a> Scanner<'a> {
-    pub fn new(source: &'a str) -> Self {
+impl<'a, I: Input> Scanner<'a, I> {
+    fn with_input(
+        input: I,
+        flags: &'a HashSet<String>,
+        reporter: &'a mut dyn ErrorReporter,
+    ) -> Self {
         Self {
-            source: source.to_owned(),
-            source_iter: source.chars().multipeek(),
+            input,
+            reporter,
+            unreadable: false,
             tokens: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
+            column: 1,
+            start_column: 1,
+            flags,
+            conditions: Vec::new(),
+            lexemes: HashSet::new(),
+            keep_trivia: false,
         }
     }
 
@@ -50,23 +183,62 @@ impl<'a> Scanner<'a> {
         while !self.is_at_end() {
             // We are at the beginning of the next lexeme.
             self.start = self.current;
+            self.start_column = self.column;
             if let Err(_) = self.scan_token() {
                 had_error = true;
             }
         }
 
-        self.tokens.push(Token::new(TokenType::EOF, "", self.line, self.current.clone()));
+        if self.unreadable {
+            had_error = true;
+        }
+
+        if let Some(&Condition { line, column, .. }) = self.conditions.last() {
+            self.error(
+                line,
+                column,
+                ErrorCode::UnterminatedIf,
+                "Unterminated '#if' directive.",
+            );
+            had_error = true;
+        }
+
+        let lexeme = Self::intern(&mut self.lexemes, "");
+        let end = self.current;
+        self.tokens.push(Token::new(
+            TokenType::EOF,
+            lexeme,
+            self.line,
+            self.column,
+            end..end,
+        ));
         (&self.tokens, had_error)
     }
 
+    // The tokens scanned by `scan_tokens`.
+    pub fn into_tokens(self) -> Vec<Token> {
+        self.tokens
+    }
+
     fn scan_token(&mut self) -> Result<(), ()> {
         let c = self.advance();
+        if !self.is_active() {
+            return self.skip_inactive(c);
+        }
+
         match c {
             '(' => self.add_token(TokenType::LeftParen),
             ')' => self.add_token(TokenType::RightParen),
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
             ',' => self.add_token(TokenType::Comma),
+            '.' if self.peek() == '.' && self.peek_next() == '.' => {
+                self.advance();
+                self.advance();
+                self.add_token(TokenType::Ellipsis);
+            }
             '.' => self.add_token(TokenType::Dot),
             '-' => self.add_token(TokenType::Minus),
             '+' => self.add_token(TokenType::Plus),
@@ -113,22 +285,33 @@ impl<'a> Scanner<'a> {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    self.add_trivia(TokenType::Comment);
                 } else if self.match_next('*') {
                     self.block_comment()?;
+                    self.add_trivia(TokenType::Comment);
                 } else {
                     self.add_token(TokenType::Slash);
                 }
             }
-            ' ' | '\r' | '\t' => (),
-            '\n' => self.line += 1,
+            ' ' | '\r' | '\t' | '\n' => (),
             '"' => self.string()?,
+            '#' if self.keep_trivia && self.at_line_start() => {
+                self.skip_line();
+                self.add_trivia(TokenType::Directive);
+            }
+            '#' => self.directive()?,
             c => {
                 if c.is_digit(10) {
                     self.number();
-                } else if Scanner::is_alpha(c) {
+                } else if Self::is_alpha(c) {
                     self.identifier();
                 } else {
-                    error_line(&self.line, &format!("Unexpected character: \"{c}\"."));
+                    self.error(
+                        self.line,
+                        self.start_column,
+                        ErrorCode::UnexpectedCharacter,
+                        &format!("Unexpected character: \"{c}\"."),
+                    );
                     return Err(());
                 }
             }
@@ -137,12 +320,14 @@ impl<'a> Scanner<'a> {
     }
 
     fn identifier(&mut self) {
-        while Scanner::is_alphanumeric(self.peek()) {
+        while Self::is_alphanumeric(self.peek()) {
             self.advance();
         }
 
-        let text = &self.source[self.start..self.current];
-        let token_type = KEYWORDS.get(text).cloned().unwrap_or(TokenType::Identifier);
+        let token_type = KEYWORDS
+            .get(self.lexeme())
+            .cloned()
+            .unwrap_or(TokenType::Identifier);
         self.add_token(token_type);
     }
 
@@ -161,12 +346,62 @@ impl<'a> Scanner<'a> {
             }
         }
 
-        let literal = self.source[self.start..self.current]
-            .parse::<f64>()
-            .expect("Unable to parse number.");
+        let lexeme = self.lexeme();
+        let literal = lexeme.parse::<f64>().expect("Unable to parse number.");
+
+        let warning = if literal.is_infinite() {
+            Some(format!(
+                "Number literal '{lexeme}' is too large and becomes {literal}."
+            ))
+        } else if literal == 0.0 && lexeme.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            Some(format!(
+                "Number literal '{lexeme}' is too small and becomes 0."
+            ))
+        } else if Self::full_value(literal) != Self::normalize_number(lexeme) {
+            Some(format!(
+                "Number literal '{lexeme}' can't be represented exactly and becomes {}.",
+                Self::full_value(literal)
+            ))
+        } else {
+            None
+        };
+
+        if let Some(message) = warning {
+            self.warning(self.current - self.start, &message);
+        }
+
         self.add_token(TokenType::Number(literal));
     }
 
+    // The value a literal becomes, with every digit of its integer part. The
+    // shortest form that reads back as the same f64, which `to_string`
+    // prints, rounds lost integer digits back to what was written, as with
+    // 1e23 becoming 99999999999999991611392. Fractions keep that form, since
+    // few of them are exact in binary.
+    fn full_value(literal: f64) -> String {
+        if literal.fract() == 0.0 {
+            format!("{literal:.0}")
+        } else {
+            literal.to_string()
+        }
+    }
+
+    // A number literal written the way f64 values are printed, without
+    // leading zeros in its integer part or trailing zeros in its fraction.
+    // A literal that's exact prints back the same once normalized.
+    fn normalize_number(lexeme: &str) -> String {
+        let (integer, fraction) = lexeme.split_once('.').unwrap_or((lexeme, ""));
+        let integer = match integer.trim_start_matches('0') {
+            "" => "0",
+            integer => integer,
+        };
+
+        match fraction.trim_end_matches('0') {
+            "" => integer.to_owned(),
+            fraction => format!("{integer}.{fraction}"),
+        }
+    }
+
     fn string(&mut self) -> Result<(), ()> {
         while !self.is_at_end() {
             let peek = self.peek();
@@ -174,15 +409,16 @@ impl<'a> Scanner<'a> {
                 break;
             }
 
-            if peek == '\n' {
-                self.line += 1;
-            }
-
             self.advance();
         }
 
         if self.is_at_end() {
-            error_line(&self.line, "Unterminated string.");
+            self.error(
+                self.line,
+                self.column,
+                ErrorCode::UnterminatedString,
+                "Unterminated string.",
+            );
             return Err(());
         }
 
@@ -190,11 +426,168 @@ impl<'a> Scanner<'a> {
         self.advance();
 
         // Trim the surrounding quotes.
-        let literal = self.source[self.start + 1..self.current - 1].to_owned();
+        let lexeme = self.lexeme();
+        let literal = lexeme[1..lexeme.len() - 1].to_owned();
         self.add_token(TokenType::String(literal));
         Ok(())
     }
 
+    // A directive takes a line of its own, though a comment may follow it.
+    fn directive(&mut self) -> Result<(), ()> {
+        if !self.at_line_start() {
+            self.error(
+                self.line,
+                self.start_column,
+                ErrorCode::MisplacedDirective,
+                "Directives must start a line.",
+            );
+            self.skip_line();
+            return Err(());
+        }
+
+        let directive = self.word();
+        match directive.as_str() {
+            "if" => {
+                while self.peek() == ' ' || self.peek() == '\t' {
+                    self.advance();
+                }
+
+                let flag = self.word();
+                if flag.is_empty() {
+                    self.error(
+                        self.line,
+                        self.start_column,
+                        ErrorCode::MissingDirectiveFlag,
+                        "Expect flag name after '#if'.",
+                    );
+                    return Err(());
+                }
+
+                self.conditions.push(Condition {
+                    line: self.line,
+                    column: self.start_column,
+                    enclosing_active: self.is_active(),
+                    value: self.flags.contains(&flag),
+                    in_else: false,
+                });
+            }
+            "else" => match self.conditions.last_mut() {
+                Some(condition) if !condition.in_else => {
+                    condition.value = !condition.value;
+                    condition.in_else = true;
+                }
+                Some(_) => {
+                    self.error(
+                        self.line,
+                        self.start_column,
+                        ErrorCode::DuplicateElse,
+                        "Can't have more than one '#else' per '#if'.",
+                    );
+                    return Err(());
+                }
+                None => {
+                    self.error(
+                        self.line,
+                        self.start_column,
+                        ErrorCode::UnmatchedElse,
+                        "'#else' without matching '#if'.",
+                    );
+                    return Err(());
+                }
+            },
+            "end" => {
+                if self.conditions.pop().is_none() {
+                    self.error(
+                        self.line,
+                        self.start_column,
+                        ErrorCode::UnmatchedEnd,
+                        "'#end' without matching '#if'.",
+                    );
+                    return Err(());
+                }
+            }
+            // Like the rest of the code an "#if" leaves out, lines that
+            // aren't known directives are skipped without errors.
+            _ if !self.is_active() => {
+                self.skip_line();
+                return Ok(());
+            }
+            _ => {
+                self.error(
+                    self.line,
+                    self.start_column,
+                    ErrorCode::UnknownDirective,
+                    &format!("Unknown directive '#{directive}'."),
+                );
+                self.skip_line();
+                return Err(());
+            }
+        }
+
+        while self.peek() == ' ' || self.peek() == '\t' || self.peek() == '\r' {
+            self.advance();
+        }
+
+        let comment = self.peek() == '/' && self.peek_next() == '/';
+        if !(self.peek() == '\n' || self.is_at_end() || comment) {
+            self.error(
+                self.line,
+                self.column,
+                ErrorCode::MisplacedDirective,
+                "Expect end of line after directive.",
+            );
+            self.skip_line();
+            return Err(());
+        }
+
+        Ok(())
+    }
+
+    // Code left out by an "#if" is skipped a line at a time without being
+    // scanned, so errors in it aren't reported. Only directives, which start
+    // their lines, are read.
+    fn skip_inactive(&mut self, c: char) -> Result<(), ()> {
+        match c {
+            ' ' | '\r' | '\t' | '\n' => Ok(()),
+            '#' if self.at_line_start() => self.directive(),
+            _ => {
+                self.skip_line();
+                Ok(())
+            }
+        }
+    }
+
+    // Whether only spaces and tabs come before the lexeme on its line.
+    fn at_line_start(&self) -> bool {
+        let before = &self.input.text()[..self.start];
+        let line = before.rfind('\n').map_or(before, |end| &before[end + 1..]);
+        line.chars().all(|c| c == ' ' || c == '\t')
+    }
+
+    // Advances to the end of the line, leaving the newline.
+    fn skip_line(&mut self) {
+        while self.peek() != '\n' && !self.is_at_end() {
+            self.advance();
+        }
+    }
+
+    // Reads an identifier-like word starting at the current character.
+    fn word(&mut self) -> String {
+        let start = self.current;
+        while Self::is_alphanumeric(self.peek()) {
+            self.advance();
+        }
+
+        self.input.text()[start..self.current].to_owned()
+    }
+
+    // Tokens inside a "#if" whose flag isn't set are dropped.
+    fn is_active(&self) -> bool {
+        self.conditions
+            .last()
+            .is_none_or(|condition| condition.enclosing_active && condition.value)
+    }
+
     fn block_comment(&mut self) -> Result<(), ()> {
         let mut comment_level = 1;
         while !self.is_at_end() {
@@ -215,29 +608,75 @@ impl<'a> Scanner<'a> {
                 break;
             }
 
-            let c = self.advance();
-            if c == '\n' {
-                self.line += 1;
-            }
+            self.advance();
         }
 
         if comment_level != 0 {
-            error_line(&self.line, "Unterminated block comment.");
+            self.error(
+                self.line,
+                self.column,
+                ErrorCode::UnterminatedComment,
+                "Unterminated block comment.",
+            );
             return Err(());
         }
 
         Ok(())
     }
 
+    // The character `offset` characters past the next one, if the source is
+    // that long.
+    fn char_at(&mut self, offset: usize) -> Option<char> {
+        // Input is only read once it's needed, so errors reading it are
+        // reported where it was needed.
+        let mut end = self.current + offset + 1;
+        loop {
+            if let Err(err) = self.input.fill(end) {
+                self.error(
+                    self.line,
+                    self.column,
+                    ErrorCode::UnreadableSource,
+                    &format!("Can't read the script: {err}."),
+                );
+                self.unreadable = true;
+            }
+
+            let text = self.input.text();
+            let c = text[self.current..].chars().nth(offset);
+            // Characters may take more than one byte each.
+            if c.is_some() || text.len() < end {
+                return c;
+            }
+            end = text.len() + 1;
+        }
+    }
+
     fn peek(&mut self) -> char {
-        self.source_iter.reset_peek();
-        *self.source_iter.peek().unwrap_or(&'\0')
+        self.char_at(0).unwrap_or('\0')
     }
 
     fn peek_next(&mut self) -> char {
-        self.source_iter.reset_peek();
-        self.source_iter.peek(); // Advance peeking "cursor"
-        *self.source_iter.peek().unwrap_or(&'\0')
+        self.char_at(1).unwrap_or('\0')
+    }
+
+    // The text of the lexeme being scanned.
+    fn lexeme(&self) -> &str {
+        &self.input.text()[self.start..self.current]
+    }
+
+    fn error(&mut self, line: u32, column: u32, code: ErrorCode, message: &str) {
+        SourceReporter::new(self.input.text(), self.reporter)
+            .error_at(&line, &column, code, message);
+    }
+
+    // Warns about the lexeme being scanned, underlining `width` characters.
+    fn warning(&mut self, width: usize, message: &str) {
+        SourceReporter::new(self.input.text(), self.reporter).warning_at(
+            &self.line,
+            &self.start_column,
+            width,
+            message,
+        );
     }
 
     fn is_alpha(c: char) -> bool {
@@ -249,15 +688,58 @@ impl<'a> Scanner<'a> {
     }
 
     fn add_token(&mut self, token_type: TokenType) {
-        let lexeme = &self.source[self.start..self.current];
-        self.tokens.push(Token::new(token_type, lexeme, self.line, self.current.clone()))
+        if !self.is_active() {
+            return;
+        }
+
+        let lexeme = &self.input.text()[self.start..self.current];
+        let lexeme = Self::intern(&mut self.lexemes, lexeme);
+        self.tokens.push(Token::new(
+            token_type,
+            lexeme,
+            self.line,
+            self.start_column,
+            self.start..self.current,
+        ))
+    }
+
+    fn add_trivia(&mut self, token_type: TokenType) {
+        if self.keep_trivia {
+            let text = self.input.text()[self.start..self.current].trim_end();
+            let lexeme = Self::intern(&mut self.lexemes, text);
+            let span = self.start..self.start + text.len();
+            self.tokens.push(Token::new(
+                token_type,
+                lexeme,
+                self.line,
+                self.start_column,
+                span,
+            ));
+        }
+    }
+
+    fn intern(lexemes: &mut HashSet<Rc<str>>, lexeme: &str) -> Rc<str> {
+        if let Some(interned) = lexemes.get(lexeme) {
+            return interned.clone();
+        }
+
+        let interned: Rc<str> = lexeme.into();
+        lexemes.insert(interned.clone());
+        interned
     }
 
     fn advance(&mut self) -> char {
-        let next_char = self.source_iter.next().expect("Unexpected end.");
+        let next_char = self.char_at(0).expect("Unexpected end.");
         // This is needed because Rust characters can use more than one byte.
         self.current += next_char.len_utf8();
 
+        if next_char == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
         next_char
     }
 
@@ -270,7 +752,78 @@ impl<'a> Scanner<'a> {
         true
     }
 
-    fn is_at_end(&self) -> bool {
-        self.current >= self.source.len()
+    fn is_at_end(&mut self) -> bool {
+        self.char_at(0).is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::io::{self, BufReader, Read};
+
+    use super::Scanner;
+    use crate::error::Diagnostic;
+    use crate::error_code::ErrorCode;
+    use crate::token::TokenType;
+
+    // Fails once the input before it has been read.
+    struct Failing;
+
+    impl Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk on fire"))
+        }
+    }
+
+    #[test]
+    fn scans_from_a_reader() {
+        let flags = HashSet::new();
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        // A tiny buffer, so lexemes span several reads.
+        let reader =
+            BufReader::with_capacity(2, "var greeting = \"héllo\";\nprint greeting;\n".as_bytes());
+        let mut scanner = Scanner::from_reader(reader, &flags, &mut diagnostics);
+        let (tokens, had_error) = scanner.scan_tokens();
+
+        assert!(!had_error);
+        let lexemes: Vec<&str> = tokens.iter().map(|token| &*token.lexeme).collect();
+        assert_eq!(
+            lexemes,
+            [
+                "var",
+                "greeting",
+                "=",
+                "\"héllo\"",
+                ";",
+                "print",
+                "greeting",
+                ";",
+                ""
+            ]
+        );
+        assert_eq!(tokens[5].token_type, TokenType::Print);
+        assert_eq!((tokens[5].line, tokens[5].column), (2, 1));
+        assert_eq!(tokens[3].span, 15..23);
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn reports_unreadable_sources() {
+        let flags = HashSet::new();
+        let mut diagnostics = Vec::new();
+        let reader = BufReader::new("print 1;\n".as_bytes().chain(Failing));
+        let mut scanner = Scanner::from_reader(reader, &flags, &mut diagnostics);
+        let (tokens, had_error) = scanner.scan_tokens();
+
+        assert!(had_error);
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, Some(ErrorCode::UnreadableSource));
+        assert_eq!(
+            diagnostics[0].message,
+            "Error: Can't read the script: disk on fire."
+        );
+        assert_eq!(diagnostics[0].line, 2);
     }
 }
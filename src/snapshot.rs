@@ -0,0 +1,246 @@
+use std::cell::RefCell;
+use std::error;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::ast::Object;
+use crate::callable::{LoxCallable, LoxFunction};
+use crate::class::LoxClass;
+use crate::environment::Environment;
+
+/// A global variable that `RustLox::snapshot` couldn't save.
+#[derive(Debug)]
+pub struct SnapshotError {
+    name: String,
+    reason: &'static str,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Can't save '{}': {}.", self.name, self.reason)
+    }
+}
+
+impl error::Error for SnapshotError {}
+
+// Writes the global variables as a script that defines them again. Functions
+// and classes are declared first, from their source, so variables can refer
+// to them. Natives aren't saved, the interpreter running the script has its
+// own.
+pub fn snapshot(globals: &Rc<RefCell<Environment>>) -> Result<String, SnapshotError> {
+    let mut entries: Vec<(Rc<str>, Object)> = globals
+        .borrow()
+        .entries()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut writer = Writer {
+        globals,
+        entries: &entries,
+        declarations: String::new(),
+        variables: String::new(),
+        statics: String::new(),
+        declared: Vec::new(),
+        visiting: Vec::new(),
+    };
+
+    for (name, value) in &entries {
+        match value {
+            Object::Callable(LoxCallable::LoxNative { .. }) => (),
+            Object::Callable(LoxCallable::LoxFunction { function })
+                if writer.is_declaration(name, function) =>
+            {
+                let source = writer.function_source(name, function, 0)?;
+                writer.declarations += &format!("fun {name}{source}\n");
+            }
+            Object::Callable(LoxCallable::LoxClass { class }) if *class.name == **name => {
+                writer.class(class)?;
+            }
+            _ => {
+                let value = writer.value(name, value)?;
+                writer.variables += &format!("var {name} = {value};\n");
+            }
+        }
+    }
+
+    Ok(writer.declarations + &writer.variables + &writer.statics)
+}
+
+struct Writer<'a> {
+    globals: &'a Rc<RefCell<Environment>>,
+    entries: &'a [(Rc<str>, Object)],
+    declarations: String,
+    variables: String,
+    // Assignments that give classes their static fields back, written last
+    // since the fields can hold any value.
+    statics: String,
+    declared: Vec<*const LoxClass>,
+    // The lists being written, to stop at cycles.
+    visiting: Vec<*const ()>,
+}
+
+impl Writer<'_> {
+    fn error(name: &str, reason: &'static str) -> SnapshotError {
+        SnapshotError {
+            name: name.to_owned(),
+            reason,
+        }
+    }
+
+    // Whether the global variable `name` holds `value`.
+    fn holds(&self, name: &str, value: &Object) -> bool {
+        self.entries
+            .iter()
+            .any(|(global, held)| &**global == name && same(held, value))
+    }
+
+    // Whether the function is declared as the global variable `name`, so it
+    // can be written as a declaration instead of a lambda.
+    fn is_declaration(&self, name: &str, function: &LoxFunction) -> bool {
+        function.this.is_none()
+            && function
+                .name
+                .as_ref()
+                .is_some_and(|token| &*token.lexeme == name)
+    }
+
+    // Methods of subclasses close over the environment holding `super`, so
+    // theirs is a `depth` of 1.
+    fn function_source<'f>(
+        &self,
+        name: &str,
+        function: &'f LoxFunction,
+        depth: usize,
+    ) -> Result<&'f str, SnapshotError> {
+        if function.this.is_some() {
+            return Err(Self::error(name, "it's a method bound to an instance"));
+        }
+
+        let mut closure = function.closure.clone();
+        for _ in 0..depth {
+            let enclosing = closure.borrow().enclosing.clone();
+            match enclosing {
+                Some(enclosing) => closure = enclosing,
+                None => break,
+            }
+        }
+        if !Rc::ptr_eq(&closure, self.globals) {
+            return Err(Self::error(name, "it's a closure over local variables"));
+        }
+
+        function
+            .ast
+            .function_source(function.definition)
+            .ok_or_else(|| Self::error(name, "its source isn't available"))
+    }
+
+    // Declares a class after its superclass.
+    fn class(&mut self, class: &Rc<LoxClass>) -> Result<(), SnapshotError> {
+        if self.declared.contains(&Rc::as_ptr(class)) {
+            return Ok(());
+        }
+
+        let name = &class.name;
+        if !self.holds(name, &class_object(class)) {
+            return Err(Self::error(name, "its class isn't a global variable"));
+        }
+
+        let mut declaration = format!("class {name}");
+        if let Some(superclass) = class.superclass() {
+            self.class(superclass)?;
+            declaration += &format!(" < {}", superclass.name);
+        }
+        declaration += " {\n";
+
+        let mut methods: Vec<_> = class.methods().collect();
+        methods.sort_by_key(|(name, _)| *name);
+        for (method, callable) in methods {
+            if let LoxCallable::LoxFunction { function } = callable {
+                let depth = usize::from(class.superclass().is_some());
+                let source = self.function_source(name, function, depth)?;
+                declaration += &format!("    {method}{source}\n");
+            }
+        }
+        declaration += "}\n";
+
+        let mut statics: Vec<_> = class.statics().into_iter().collect();
+        statics.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (field, value) in statics {
+            let value = self.value(&format!("{name}.{field}"), &value)?;
+            self.statics += &format!("{name}.{field} = {value};\n");
+        }
+
+        self.declarations += &declaration;
+        self.declared.push(Rc::as_ptr(class));
+        Ok(())
+    }
+
+    // A value as an expression. `name` is the variable being saved, for
+    // errors.
+    fn value(&mut self, name: &str, value: &Object) -> Result<String, SnapshotError> {
+        match value {
+            Object::String(string) => Ok(quoted(string)),
+            Object::Number(number) if number.is_finite() => Ok(number.to_string()),
+            Object::Number(_) => Err(Self::error(
+                name,
+                "infinite and NaN numbers can't be written in Lox",
+            )),
+            Object::Boolean(boolean) => Ok(boolean.to_string()),
+            Object::Nil => Ok(String::from("nil")),
+            Object::Instance(_) => Err(Self::error(name, "instances can't be saved")),
+            Object::List(list) => {
+                let id = Rc::as_ptr(list) as *const ();
+                if self.visiting.contains(&id) {
+                    return Err(Self::error(name, "it contains itself"));
+                }
+
+                self.visiting.push(id);
+                let elements = list
+                    .borrow()
+                    .iter()
+                    .map(|element| self.value(name, element))
+                    .collect::<Result<Vec<_>, _>>();
+                self.visiting.pop();
+                Ok(format!("[{}]", elements?.join(", ")))
+            }
+            Object::Callable(LoxCallable::LoxNative { native }) => Ok(native.name.to_string()),
+            Object::Callable(LoxCallable::LoxFunction { function }) => match &function.name {
+                Some(function_name) if self.holds(&function_name.lexeme, value) => {
+                    Ok(function_name.lexeme.to_string())
+                }
+                _ => Ok(format!("fun {}", self.function_source(name, function, 0)?)),
+            },
+            Object::Callable(LoxCallable::LoxClass { class }) => {
+                self.class(class)?;
+                Ok(class.name.clone())
+            }
+        }
+    }
+}
+
+// Functions don't equal themselves in Lox, so they're compared by identity.
+fn same(a: &Object, b: &Object) -> bool {
+    match (a, b) {
+        (
+            Object::Callable(LoxCallable::LoxFunction { function: a }),
+            Object::Callable(LoxCallable::LoxFunction { function: b }),
+        ) => Rc::ptr_eq(a, b),
+        _ => a.equals(b),
+    }
+}
+
+fn class_object(class: &Rc<LoxClass>) -> Object {
+    Object::Callable(LoxCallable::LoxClass {
+        class: class.clone(),
+    })
+}
+
+// Strings can't escape double quotes, so those are decoded from their byte.
+fn quoted(string: &str) -> String {
+    string
+        .split('"')
+        .map(|part| format!("\"{part}\""))
+        .collect::<Vec<_>>()
+        .join(" + decodeUtf8([34], \"strict\") + ")
+}
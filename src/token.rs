@@ -1,20 +1,34 @@
-use std::{fmt, hash::{Hash, Hasher}};
+use std::{fmt, ops::Range, rc::Rc};
 
+/// A lexeme of a script and what kind it is.
 #[derive(Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
-    pub lexeme: String,
+    /// The text of the token. Scanners intern lexemes, so copying a token
+    /// doesn't copy its text.
+    pub lexeme: Rc<str>,
+    /// The line the token ends on, and the column it starts at, counted in
+    /// characters from 1.
     pub line: u32,
-    id: usize
+    pub column: u32,
+    /// Where the token is in the source, in bytes.
+    pub span: Range<usize>,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: &str, line: u32, id: usize) -> Self {
+    pub fn new(
+        token_type: TokenType,
+        lexeme: Rc<str>,
+        line: u32,
+        column: u32,
+        span: Range<usize>,
+    ) -> Self {
         Self {
             token_type,
-            lexeme: lexeme.to_owned(),
+            lexeme,
             line,
-            id
+            column,
+            span,
         }
     }
 }
@@ -37,16 +51,7 @@ impl fmt::Display for Token {
     }
 }
 
-impl Hash for Token {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.id.hash(state);
-        self.lexeme.hash(state);
-        self.line.hash(state);
-    }
-}
-
-impl Eq for Token {}
-
+/// The kinds of tokens. Literals carry their values.
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     // Single-character tokens.
@@ -54,6 +59,8 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
@@ -75,6 +82,9 @@ pub enum TokenType {
     Less,
     LessEqual,
 
+    // Three character tokens.
+    Ellipsis,
+
     // Literals.
     Identifier,
     // String and number literals already have their runtime values in the TokenType
@@ -84,20 +94,28 @@ pub enum TokenType {
     // Keywords.
     And,
     Class,
+    Delete,
     Else,
     False,
     Fun,
     For,
     If,
+    Is,
     Nil,
     Or,
     Print,
     Return,
+    Static,
     Super,
     This,
     True,
     Var,
     While,
 
+    // Only kept by scanners made with `Scanner::with_trivia`, for the
+    // formatter.
+    Comment,
+    Directive,
+
     EOF,
 }
@@ -0,0 +1,72 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::RustLox;
+
+/// What running a script printed, and the errors and warnings about it.
+#[wasm_bindgen]
+pub struct RunResult {
+    output: String,
+    diagnostics: Vec<String>,
+    ok: bool,
+}
+
+#[wasm_bindgen]
+impl RunResult {
+    #[wasm_bindgen(getter)]
+    pub fn output(&self) -> String {
+        self.output.clone()
+    }
+
+    /// Each diagnostic as it would be printed, with the line it's about.
+    #[wasm_bindgen(getter)]
+    pub fn diagnostics(&self) -> Vec<String> {
+        self.diagnostics.clone()
+    }
+
+    /// Whether the script ran without errors.
+    #[wasm_bindgen(getter)]
+    pub fn ok(&self) -> bool {
+        self.ok
+    }
+}
+
+/// Runs a script in a fresh interpreter. Natives that need the file system,
+/// the clock or the environment aren't defined, since browsers don't have
+/// them.
+#[wasm_bindgen]
+pub fn run(source: &str) -> RunResult {
+    let output = Output::default();
+    let mut lox = RustLox::builder().sandboxed().build();
+    lox.set_output(output.clone());
+
+    let (ok, diagnostics) = match lox.run(source) {
+        Ok(diagnostics) => (true, diagnostics),
+        Err(diagnostics) => (false, diagnostics),
+    };
+    let output = String::from_utf8_lossy(&output.0.borrow()).into_owned();
+
+    RunResult {
+        output,
+        diagnostics: diagnostics.iter().map(|d| d.to_string()).collect(),
+        ok,
+    }
+}
+
+// Keeps what the script prints, shared with the interpreter writing it.
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+mod support;
+
+use std::path::Path;
+
+use support::run_script;
+
+#[test]
+fn prints_the_syntax_tree() {
+    let output = run_script(
+        Path::new(env!("CARGO_BIN_EXE_rustlox")),
+        &["--ast"],
+        "var a = 1 + 2 * 3;\nif (a > 6) print a; else print -a;\nfun f(x) { return x; }\n",
+    );
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "(var a (+ 1 (* 2 3)))\n\
+         (if (> a 6) (print a) (print (- a)))\n\
+         (fun f (x) (return x))\n"
+    );
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn does_not_run_the_script() {
+    let output = run_script(
+        Path::new(env!("CARGO_BIN_EXE_rustlox")),
+        &["--ast"],
+        "print undefined;\n",
+    );
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "(print undefined)\n"
+    );
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn reports_syntax_errors() {
+    let output = run_script(
+        Path::new(env!("CARGO_BIN_EXE_rustlox")),
+        &["--ast"],
+        "print;\n",
+    );
+    assert!(output.stdout.is_empty());
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn emits_a_dot_graph() {
+    let output = run_script(
+        Path::new(env!("CARGO_BIN_EXE_rustlox")),
+        &["--emit", "dot"],
+        "print -a + \"b\";\n",
+    );
+    assert_eq!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .collect::<Vec<_>>(),
+        [
+            "digraph ast {",
+            "  ordering=out;",
+            "  node [shape=box];",
+            "  n0 [label=\"program\"];",
+            "  n1 [label=\"print\"];",
+            "  n2 [label=\"+\"];",
+            "  n3 [label=\"-\"];",
+            "  n4 [label=\"a\"];",
+            "  n3 -> n4;",
+            "  n2 -> n3;",
+            "  n5 [label=\"\\\"b\\\"\"];",
+            "  n2 -> n5;",
+            "  n1 -> n2;",
+            "  n0 -> n1;",
+            "}",
+        ]
+    );
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn rejects_unknown_emit_formats() {
+    let output = run_script(
+        Path::new(env!("CARGO_BIN_EXE_rustlox")),
+        &["--emit", "png"],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(64));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Expected 'dot' after '--emit'.\n"
+    );
+}
@@ -0,0 +1,33 @@
+mod support;
+
+use support::run;
+
+const SCRIPT: &str = "print 1 and 2;\nprint nil or \"x\";\nprint nil and 1;\nprint 0 or nil;\n";
+
+#[test]
+fn produces_an_operand_by_default() {
+    let (code, stdout, _) = run(&[], SCRIPT);
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "2\nx\nnil\n0\n");
+}
+
+#[test]
+fn produces_booleans() {
+    let (code, stdout, _) = run(&["--boolean-logic"], SCRIPT);
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "true\ntrue\nfalse\ntrue\n");
+}
+
+#[test]
+fn combines_with_strict_booleans() {
+    let (code, stdout, stderr) = run(
+        &["--boolean-logic", "--strict-booleans"],
+        "print true and false;\nprint 1 and 2;\n",
+    );
+    assert_eq!(code, Some(70));
+    assert_eq!(stdout, "false\n");
+    assert_eq!(
+        stderr,
+        "[line 2:9] Condition must be a boolean; got number. [E4015]\n"
+    );
+}
@@ -0,0 +1,82 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rustlox::{ErrorCode, Object, RustLox};
+
+#[test]
+fn interrupts_infinite_loops_from_another_thread() {
+    let mut lox = RustLox::new();
+    let handle = lox.cancel_handle();
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.cancel();
+    });
+
+    let diagnostics = lox.run("var i = 0;\nwhile (true) i = i + 1;").unwrap_err();
+    canceller.join().unwrap();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Interrupted.");
+    assert_eq!(diagnostics[0].code, Some(ErrorCode::Interrupted));
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 1));
+}
+
+#[test]
+fn interrupts_at_the_next_call() {
+    let mut lox = RustLox::new();
+    lox.cancel_handle().cancel();
+
+    let diagnostics = lox.run("fun f() {}\nprint 1;\nf();").unwrap_err();
+    assert_eq!(diagnostics[0].message, "Interrupted.");
+    assert_eq!(diagnostics[0].line, 3);
+}
+
+#[test]
+fn stops_only_one_script() {
+    let mut lox = RustLox::new();
+    lox.cancel_handle().cancel();
+
+    assert!(lox.run("while (true) {}").is_err());
+    assert_eq!(
+        lox.eval("fun f() { return 1; }\nf();")
+            .ok()
+            .unwrap()
+            .to_string(),
+        "1"
+    );
+}
+
+#[test]
+fn leaves_the_global_scope_after_interrupting_a_function() {
+    let mut lox = RustLox::new();
+    let handle = lox.cancel_handle();
+    lox.define_native("stop", 0, move |_, _| {
+        handle.cancel();
+        Ok(Object::Nil)
+    });
+
+    let diagnostics = lox
+        .run("fun f(n) {\n  var a = n;\n  stop();\n  while (true) {}\n}\nf(1);")
+        .unwrap_err();
+    assert_eq!(diagnostics.last().unwrap().line, 4);
+    assert_eq!(lox.eval("var b = 2;\nb;").ok().unwrap().to_string(), "2");
+    assert_eq!(lox.get_global("b").unwrap().to_string(), "2");
+}
+
+#[test]
+fn interrupts_sleep() {
+    let mut lox = RustLox::new();
+    let handle = lox.cancel_handle();
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.cancel();
+    });
+
+    let start = Instant::now();
+    let diagnostics = lox.run("sleep(3000);\nprint \"done\";").unwrap_err();
+    canceller.join().unwrap();
+
+    assert!(start.elapsed() < Duration::from_millis(2000));
+    assert_eq!(diagnostics[0].message, "Interrupted.");
+    assert_eq!(diagnostics[0].line, 1);
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "capi")]
+
+use rustlox::{lox_eval, lox_free, lox_get_string_result, lox_new, Lox};
+use std::ffi::{CStr, CString};
+
+unsafe fn eval(lox: *mut Lox, source: &str) -> (i32, String) {
+    let source = CString::new(source).unwrap();
+    let status = lox_eval(lox, source.as_ptr());
+    let result = CStr::from_ptr(lox_get_string_result(lox));
+    (status, result.to_str().unwrap().to_owned())
+}
+
+#[test]
+fn evaluates_scripts() {
+    unsafe {
+        let lox = lox_new();
+        assert_eq!(eval(lox, ""), (0, String::from("nil")));
+        assert_eq!(eval(lox, "var a = 20;"), (0, String::from("nil")));
+        assert_eq!(eval(lox, "a + 22;"), (0, String::from("42")));
+        assert_eq!(eval(lox, "\"a\" + \"b\";"), (0, String::from("ab")));
+
+        let (status, errors) = eval(lox, "print x;");
+        assert_eq!(status, 1);
+        assert!(errors.starts_with("[line 1:7] Undefined variable 'x'. [E4003]"));
+        lox_free(lox);
+    }
+}
+
+#[test]
+fn handles_null_pointers() {
+    unsafe {
+        let lox = lox_new();
+        assert_eq!(lox_eval(lox, std::ptr::null()), 1);
+        assert_eq!(
+            CStr::from_ptr(lox_get_string_result(lox)).to_str(),
+            Ok("The source is null.")
+        );
+        lox_free(lox);
+
+        assert_eq!(lox_eval(std::ptr::null_mut(), std::ptr::null()), 1);
+        assert!(lox_get_string_result(std::ptr::null()).is_null());
+        lox_free(std::ptr::null_mut());
+    }
+}
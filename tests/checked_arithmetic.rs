@@ -0,0 +1,38 @@
+mod support;
+
+use support::run;
+
+#[test]
+fn reports_division_by_zero() {
+    let (_, stdout, stderr) = run(&[], "print 1 / 2;\nprint 1 / 0;\n");
+    assert_eq!(stdout, "0.5\n");
+    assert_eq!(stderr, "[line 2:9] Division by zero. [E4023]\n");
+
+    let (_, _, stderr) = run(&[], "print 0 % 0;\n");
+    assert_eq!(stderr, "[line 1:9] Division by zero. [E4023]\n");
+}
+
+#[test]
+fn ieee_division_allows_non_finite_results() {
+    let (_, stdout, _) = run(&["--ieee-division"], "print 1 / 0;\nprint 0 % 0;\n");
+    assert_eq!(stdout, "inf\nNaN\n");
+}
+
+#[test]
+fn reports_non_finite_division() {
+    let (_, stdout, stderr) = run(
+        &["--ieee-division", "--checked-arithmetic"],
+        "print 1 / 2;\nprint 1 / 0;\n",
+    );
+    assert_eq!(stdout, "0.5\n");
+    assert_eq!(stderr, "[line 2:9] 1 / 0 produced inf. [E4016]\n");
+}
+
+#[test]
+fn reports_nan() {
+    let (_, _, stderr) = run(
+        &["--ieee-division", "--checked-arithmetic"],
+        "var zero = 0;\nprint zero % zero;\n",
+    );
+    assert_eq!(stderr, "[line 2:12] 0 % 0 produced NaN. [E4016]\n");
+}
@@ -0,0 +1,54 @@
+mod support;
+
+use std::path::Path;
+
+use support::run_script;
+
+const SOURCE: &str = "print x;\n";
+
+fn stderr(options: &[&str]) -> String {
+    let output = run_script(Path::new(env!("CARGO_BIN_EXE_rustlox")), options, SOURCE);
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn colors_errors_when_asked() {
+    assert_eq!(
+        stderr(&["--color=always"]),
+        "\x1b[1;31m[line 1:7] Undefined variable 'x'. [E4003]\x1b[0m\n\
+         1 | print \x1b[1mx\x1b[0m;\n  \
+         |       \x1b[1;31m^\x1b[0m\n"
+    );
+}
+
+#[test]
+fn colors_warnings_in_yellow() {
+    let output = run_script(
+        Path::new(env!("CARGO_BIN_EXE_rustlox")),
+        &["--color", "always"],
+        "print 123456789012345678901234567890;\n",
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("\x1b[1;33m[line 1:7] Warning: Number literal"));
+}
+
+#[test]
+fn leaves_pipes_uncolored_by_default() {
+    assert_eq!(stderr(&[]), stderr(&["--color", "never"]));
+    assert_eq!(stderr(&["--color", "auto"]), stderr(&["--color", "never"]));
+    assert!(!stderr(&[]).contains('\x1b'));
+}
+
+#[test]
+fn rejects_unknown_choices() {
+    let output = run_script(
+        Path::new(env!("CARGO_BIN_EXE_rustlox")),
+        &["--color=sometimes"],
+        SOURCE,
+    );
+    assert_eq!(output.status.code(), Some(64));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Expected 'always', 'never' or 'auto' after '--color'.\n"
+    );
+}
@@ -0,0 +1,87 @@
+mod support;
+
+use std::path::Path;
+
+use support::run_script;
+
+fn stderr(source: &str) -> Vec<String> {
+    let output = run_script(Path::new(env!("CARGO_BIN_EXE_rustlox")), &[], source);
+    String::from_utf8(output.stderr)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn counts_columns_in_characters() {
+    assert_eq!(
+        stderr("var s = \"é\"; @\n"),
+        [
+            "[line 1:14] Error: Unexpected character: \"@\". [E1001]",
+            "1 | var s = \"é\"; @",
+            "  |              ^",
+        ]
+    );
+}
+
+#[test]
+fn points_at_the_start_of_the_token() {
+    assert_eq!(
+        stderr("print 1;\n  print nil + 1;\n"),
+        [
+            "[line 2:13] Operands must be two numbers or two strings. [E4024]",
+            "2 |   print nil + 1;",
+            "  |             ^",
+        ]
+    );
+}
+
+#[test]
+fn points_past_the_last_token_at_the_end() {
+    assert_eq!(
+        stderr("print 1 +"),
+        [
+            "[line 1:10] Error at end: Expect expression. [E2008]",
+            "1 | print 1 +",
+            "  |          ^",
+        ]
+    );
+}
+
+#[test]
+fn underlines_the_whole_token() {
+    assert_eq!(
+        stderr("print undefined;\n"),
+        [
+            "[line 1:7] Undefined variable 'undefined'. [E4003]",
+            "1 | print undefined;",
+            "  |       ^^^^^^^^^",
+        ]
+    );
+}
+
+#[test]
+fn underlines_the_first_line_of_multiline_tokens() {
+    assert_eq!(
+        stderr("var \"one\ntwo\" = 1;\n"),
+        [
+            "[line 2:5] Error at '\"one",
+            "two\"': Expect variable name. [E2007]",
+            "1 | var \"one",
+            "  |     ^^^^",
+        ]
+    );
+}
+
+#[test]
+fn keeps_tabs_in_the_underline() {
+    assert_eq!(
+        stderr("\tprint x;\n"),
+        [
+            "[line 1:8] Undefined variable 'x'. [E4003]",
+            "1 | \tprint x;",
+            "  | \t      ^",
+        ]
+    );
+}
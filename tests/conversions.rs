@@ -0,0 +1,52 @@
+use rustlox::{NativeContext, NativeError, Object, RustLox};
+
+#[test]
+fn converts_rust_values_into_objects() {
+    assert_eq!(Object::from(1.5).to_string(), "1.5");
+    assert_eq!(Object::from(true).to_string(), "true");
+    assert_eq!(Object::from("text").to_string(), "text");
+    assert_eq!(Object::from(String::from("text")).to_string(), "text");
+    assert_eq!(
+        Object::from(vec![Object::from(1.0), Object::Nil]).to_string(),
+        "[1, nil]"
+    );
+}
+
+#[test]
+fn converts_objects_into_rust_values() {
+    assert_eq!(f64::try_from(Object::from(2.0)).unwrap(), 2.0);
+    assert!(bool::try_from(Object::from(true)).unwrap());
+    assert_eq!(String::try_from(Object::from("text")).unwrap(), "text");
+
+    let elements = Vec::<Object>::try_from(Object::from(vec![Object::from(1.0)])).unwrap();
+    assert_eq!(elements.len(), 1);
+}
+
+#[test]
+fn rejects_objects_of_other_types() {
+    let error = f64::try_from(Object::from("1")).err().unwrap();
+    assert_eq!(error.to_string(), "Expected a number; got string.");
+
+    let error = Vec::<Object>::try_from(Object::Nil).err().unwrap();
+    assert_eq!(error.to_string(), "Expected a list; got nil.");
+}
+
+fn repeat(_: &mut NativeContext, arguments: &[Object]) -> Result<Object, NativeError> {
+    let text = String::try_from(arguments[0].clone())?;
+    let count = f64::try_from(arguments[1].clone())?;
+    Ok(text.repeat(count as usize).into())
+}
+
+#[test]
+fn conversion_errors_are_runtime_errors_in_natives() {
+    let mut lox = RustLox::new();
+    lox.define_native("repeat", 2, repeat);
+
+    assert_eq!(
+        lox.eval("repeat(\"ab\", 2);").ok().unwrap().to_string(),
+        "abab"
+    );
+
+    let diagnostics = lox.eval("repeat(\"ab\", \"2\");").err().unwrap();
+    assert_eq!(diagnostics[0].message, "Expected a number; got string.");
+}
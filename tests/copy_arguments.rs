@@ -0,0 +1,71 @@
+mod support;
+
+use support::run;
+
+const SCRIPT: &str = "\
+fun change(list) {
+  list[0] = 10;
+  push(list[1], 20);
+}
+
+var list = [1, [2]];
+change(list);
+print list;
+";
+
+#[test]
+fn passes_lists_by_reference() {
+    let (code, stdout, _) = run(&[], SCRIPT);
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "[10, [2, 20]]\n");
+}
+
+#[test]
+fn copies_lists_passed_to_functions() {
+    let (code, stdout, _) = run(&["--copy-arguments"], SCRIPT);
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "[1, [2]]\n");
+}
+
+#[test]
+fn copies_lists_passed_to_methods_and_initializers() {
+    let source = "\
+class Box {
+  init(list) { this.list = list; }
+  add(list) { push(list, 4); }
+}
+
+var list = [1, 2];
+var box = Box(list);
+push(box.list, 3);
+box.add(list);
+print list;
+";
+    let (_, stdout, _) = run(&["--copy-arguments"], source);
+    assert_eq!(stdout, "[1, 2]\n");
+}
+
+#[test]
+fn copies_lists_that_contain_themselves() {
+    let source = "\
+fun check(copy, list) {
+  print copy[0] == copy;
+  print copy == list;
+}
+
+var list = [];
+push(list, list);
+check(list, list);
+";
+    let (_, stdout, _) = run(&["--copy-arguments"], source);
+    assert_eq!(stdout, "true\nfalse\n");
+}
+
+#[test]
+fn natives_still_change_their_arguments() {
+    let (_, stdout, _) = run(
+        &["--copy-arguments"],
+        "var list = [];\npush(list, 1);\nprint list;\n",
+    );
+    assert_eq!(stdout, "[1]\n");
+}
@@ -0,0 +1,74 @@
+mod support;
+
+use std::{env, fs, path::Path, process};
+
+use support::run_script;
+
+const SOURCE: &str = "fun f(n) {\n  if (n > 0) {\n    print n;\n  }\n}\nf(0);\nf(1);\n";
+
+// Runs SOURCE with a coverage report, returning the report.
+fn coverage(name: &str, options: &[&str], source: &str) -> (process::Output, String) {
+    let report = env::temp_dir().join(format!("rustlox-coverage-{name}-{}", process::id()));
+    let mut options = options.to_vec();
+    options.extend(["--coverage", report.to_str().unwrap()]);
+
+    let output = run_script(Path::new(env!("CARGO_BIN_EXE_rustlox")), &options, source);
+    let written = fs::read_to_string(&report).unwrap();
+    fs::remove_file(&report).ok();
+    (output, written)
+}
+
+#[test]
+fn annotates_the_source_with_counts() {
+    let (output, report) = coverage("text", &[], SOURCE);
+    assert!(output.status.success());
+    assert_eq!(
+        report.lines().collect::<Vec<_>>(),
+        [
+            "        1:    1:fun f(n) {",
+            "        2:    2:  if (n > 0) {",
+            "        1:    3:    print n;",
+            "        -:    4:  }",
+            "        -:    5:}",
+            "        1:    6:f(0);",
+            "        1:    7:f(1);",
+        ]
+    );
+}
+
+#[test]
+fn marks_lines_that_never_ran() {
+    let (_, report) = coverage("unrun", &[], "if (false) {\n  print 1;\n}\n");
+    assert_eq!(report.lines().nth(1), Some("    #####:    2:  print 1;"));
+}
+
+#[test]
+fn writes_lcov_tracefiles() {
+    let (_, report) = coverage("lcov", &["--coverage-format", "lcov"], SOURCE);
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0], "TN:");
+    assert!(lines[1].starts_with("SF:"));
+    assert_eq!(
+        lines[2..],
+        [
+            "DA:1,1",
+            "DA:2,2",
+            "DA:3,1",
+            "DA:6,1",
+            "DA:7,1",
+            "LF:5",
+            "LH:5",
+            "end_of_record"
+        ]
+    );
+}
+
+#[test]
+fn writes_the_report_when_the_script_fails() {
+    let (output, report) = coverage("error", &[], "print 1;\nprint -nil;\nprint 2;\n");
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(
+        report,
+        "        1:    1:print 1;\n        1:    2:print -nil;\n    #####:    3:print 2;\n"
+    );
+}
@@ -0,0 +1,68 @@
+use std::cell::RefCell;
+
+use rustlox::{ErrorCode, Object, RustLox, Severity};
+
+#[test]
+fn returns_errors_instead_of_printing_them() {
+    let diagnostics = RustLox::new().run("var a = 1;\nprint a +;\n").unwrap_err();
+
+    assert_eq!(diagnostics.len(), 1);
+    let error = &diagnostics[0];
+    assert_eq!(error.severity, Severity::Error);
+    assert_eq!((error.line, error.column), (2, 10));
+    assert_eq!(error.message, "Error at ';': Expect expression.");
+    assert_eq!(error.code, Some(ErrorCode::MissingExpression));
+    assert_eq!(error.snippet.as_ref().unwrap().text, "print a +;");
+    assert_eq!(
+        error.to_string(),
+        "[line 2:10] Error at ';': Expect expression. [E2008]\n2 | print a +;\n  |          ^"
+    );
+}
+
+#[test]
+fn returns_runtime_errors() {
+    let diagnostics = RustLox::new().run("print -\"s\";").unwrap_err();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Operands must be numbers.");
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 7));
+}
+
+#[test]
+fn returns_warnings_of_programs_that_run() {
+    let mut lox = RustLox::new();
+    lox.enable_lint("shadow");
+
+    let diagnostics = lox
+        .run("{\n  var a = 1;\n  { var a = 2; print a; }\n  print a;\n}\n")
+        .unwrap();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (3, 9));
+}
+
+#[test]
+fn returns_nothing_for_clean_programs() {
+    assert!(RustLox::new().run("print 1;").unwrap().is_empty());
+}
+
+#[test]
+fn keeps_the_diagnostics_of_nested_runs_apart() {
+    let inner = RefCell::new(RustLox::new());
+    let mut outer = RustLox::new();
+    outer.define_native("runInner", 0, move |_, _| {
+        let diagnostics = inner.borrow_mut().run("print x;").unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        Ok(Object::Nil)
+    });
+
+    let diagnostics = outer.run("runInner();\nprint -\"s\";").unwrap_err();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Operands must be numbers.");
+    assert_eq!(
+        diagnostics[0].snippet.as_ref().unwrap().text,
+        "print -\"s\";"
+    );
+}
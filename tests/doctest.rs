@@ -0,0 +1,111 @@
+use std::env;
+use std::fs;
+use std::process::{self, Command, Output};
+
+// Runs 'rustlox test --doc' on a script holding `source`.
+fn run_doctests(name: &str, source: &str) -> Output {
+    let script = env::temp_dir().join(format!("rustlox-doctest-{}-{name}.lox", process::id()));
+    fs::write(&script, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlox"))
+        .args(["test", "--doc"])
+        .arg(&script)
+        .output()
+        .unwrap();
+    fs::remove_file(&script).ok();
+    output
+}
+
+#[test]
+fn passes_matching_examples() {
+    let output = run_doctests(
+        "pass",
+        "/// >>> add(1, 2)\n\
+         /// 3\n\
+         fun add(a, b) {\n  return a + b;\n}\n",
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(lines[0].ends_with(":1 ... ok"));
+    assert_eq!(lines[2], "1 passed; 0 failed");
+}
+
+#[test]
+fn reports_unexpected_values() {
+    let output = run_doctests(
+        "fail",
+        "/// >>> 1 + 1\n\
+         /// 3\n",
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(lines[0].ends_with(":1 ... FAILED"));
+    assert_eq!(
+        lines[1..4],
+        ["    >>> 1 + 1", "    expected: 3", "    got: 2"]
+    );
+    assert_eq!(lines[5], "0 passed; 1 failed");
+}
+
+#[test]
+fn keeps_going_after_a_runtime_error() {
+    let output = run_doctests(
+        "runtime-error",
+        "var count = 0;\n\
+         /// >>> count = count + 1\n\
+         /// 1\n\
+         /// >>> count + nil\n\
+         /// 1\n\
+         /// >>> count\n\
+         /// 0\n",
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(lines[0].ends_with(":2 ... ok"));
+    assert!(lines[1].ends_with(":4 ... FAILED"));
+    assert_eq!(lines[2], "    >>> count + nil");
+    assert_eq!(
+        lines[3],
+        "    runtime error: Operands must be two numbers or two strings."
+    );
+    assert!(lines[4].ends_with(":6 ... ok"));
+    assert_eq!(lines[6], "2 passed; 1 failed");
+}
+
+#[test]
+fn starts_each_example_with_fresh_lists() {
+    let output = run_doctests(
+        "lists",
+        "var list = [];\n\
+         var same = list;\n\
+         /// >>> push(list, 1)\n\
+         /// nil\n\
+         /// >>> len(list)\n\
+         /// 0\n\
+         /// >>> push(list, 1) or same\n\
+         /// [1]\n",
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert_eq!(output.status.code(), Some(0), "{stdout}");
+    assert!(stdout.ends_with("3 passed; 0 failed\n"));
+}
+
+#[test]
+fn requires_the_doc_flag() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rustlox"))
+        .args(["test", "script.lox"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(64));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Only doc comment tests are supported, use 'rustlox test --doc'.\n"
+    );
+}
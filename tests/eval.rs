@@ -0,0 +1,45 @@
+use rustlox::{Object, RustLox};
+
+fn eval(source: &str) -> String {
+    match RustLox::new().eval(source) {
+        Ok(value) => value.to_string(),
+        Err(diagnostics) => panic!("{diagnostics:?}"),
+    }
+}
+
+#[test]
+fn returns_the_value_of_the_last_expression() {
+    assert_eq!(eval("var a = 2;\na * 3;"), "6");
+    assert_eq!(eval("\"a\" + \"b\";"), "ab");
+    assert_eq!(eval("[1, nil, true];"), "[1, nil, true]");
+}
+
+#[test]
+fn returns_nil_for_programs_ending_with_other_statements() {
+    assert_eq!(eval("1;\nvar a = 2;"), "nil");
+    assert_eq!(eval(""), "nil");
+}
+
+#[test]
+fn keeps_globals_between_calls() {
+    let mut lox = RustLox::new();
+    lox.eval("fun square(n) { return n * n; }").unwrap();
+
+    match lox.eval("square(4);") {
+        Ok(Object::Number(value)) => assert_eq!(value, 16.0),
+        _ => panic!("Expected a number."),
+    }
+}
+
+#[test]
+fn returns_errors() {
+    let diagnostics = RustLox::new().eval("1 +;").err().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Error at ';': Expect expression.");
+
+    let diagnostics = RustLox::new().eval("nil();").err().unwrap();
+    assert_eq!(
+        diagnostics[0].message,
+        "Can only call functions and classes."
+    );
+}
@@ -0,0 +1,31 @@
+mod support;
+
+use std::{env, fs, path::Path, process};
+
+use support::run_script;
+
+#[test]
+fn writes_events_as_json_lines() {
+    let events = env::temp_dir().join(format!("rustlox-events-{}.jsonl", process::id()));
+    let output = run_script(
+        Path::new(env!("CARGO_BIN_EXE_rustlox")),
+        &["--events", events.to_str().unwrap()],
+        "var a = 1;\nfun add(x) { return x + 1; }\na = add(a);\n",
+    );
+    let written = fs::read_to_string(&events).unwrap();
+    fs::remove_file(&events).ok();
+
+    assert!(output.status.success());
+    assert_eq!(
+        written.lines().collect::<Vec<_>>(),
+        [
+            r#"{"event": "define", "name": "a", "value": 1, "line": 1, "column": 5, "span": [4, 5]}"#,
+            r#"{"event": "define", "name": "add", "value": "<fn add>", "line": 2, "column": 5, "span": [15, 18]}"#,
+            r#"{"event": "call", "callee": "<fn add>", "arguments": [1], "line": 3, "column": 10, "span": [49, 50]}"#,
+            r#"{"event": "enter_scope", "depth": 1}"#,
+            r#"{"event": "exit_scope", "depth": 1}"#,
+            r#"{"event": "return", "value": 2, "line": 3, "column": 10, "span": [49, 50]}"#,
+            r#"{"event": "assign", "name": "a", "value": 2, "line": 3, "column": 1, "span": [40, 41]}"#,
+        ]
+    );
+}
@@ -0,0 +1,60 @@
+use std::process::{Command, Output};
+
+fn explain(code: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rustlox"))
+        .args(["--explain", code])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn explains_error_codes() {
+    let output = explain("E2001");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout.starts_with("Statements end with ';'"));
+    assert!(stdout.contains("    print \"hello\" // Error at end: Expect ';' after value\n"));
+}
+
+#[test]
+fn ignores_the_case_of_codes() {
+    let output = explain("e4003");
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Undefined variable 'count'."));
+}
+
+#[test]
+fn explains_addition_apart_from_comparison() {
+    let output = explain("E4024");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout.starts_with("'+' adds two numbers"));
+    assert!(!stdout.contains("'<'"));
+}
+
+#[test]
+fn rejects_unknown_codes() {
+    let output = explain("E9999");
+    assert_eq!(output.status.code(), Some(64));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Unknown error code 'E9999'.\n"
+    );
+}
+
+#[test]
+fn every_code_is_explained() {
+    for code in (1001..1012)
+        .chain(2001..2016)
+        .chain(3001..3012)
+        .chain(4001..4026)
+        .chain(5001..5002)
+    {
+        let output = explain(&format!("E{code}"));
+        assert!(output.status.success(), "E{code} isn't explained");
+    }
+}
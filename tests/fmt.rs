@@ -0,0 +1,74 @@
+mod support;
+
+use std::{env, fs, path::Path, process::Command};
+
+use support::run_stdin;
+
+fn format(source: &str) -> (String, Option<i32>) {
+    let output = run_stdin(Path::new(env!("CARGO_BIN_EXE_rustlox")), &["fmt"], source);
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        output.status.code(),
+    )
+}
+
+const FORMATTED: &str = "\
+// Doubles every element.
+fun double(list) {
+  return map(list, fun (x) {
+    return x * 2;
+  });
+}
+
+for (var i = 0; i < 3; i = i + 1) {
+  if (!(i == 1)) print -i; // Skips 1.
+  else {
+    print double([i, i + 1])[0];
+  }
+}
+";
+
+#[test]
+fn formats_code() {
+    let source =
+        "// Doubles every element.\nfun double(list){return map(list,fun(x){return x*2;});}\n\n\n\
+                  for(var i=0;i<3;i=i+1){ if(!(i==1))print - i;// Skips 1.\n\
+                  else{print double([i,i+1]) [0];}}";
+    assert_eq!(format(source), (FORMATTED.to_owned(), Some(0)));
+}
+
+#[test]
+fn keeps_formatted_code() {
+    assert_eq!(format(FORMATTED), (FORMATTED.to_owned(), Some(0)));
+}
+
+#[test]
+fn keeps_every_directive_branch() {
+    let source = "#if DEBUG\nprint  \"debug\";\n#else\nprint \"release\" ;\n#end\n";
+    assert_eq!(
+        format(source).0,
+        "#if DEBUG\nprint \"debug\";\n#else\nprint \"release\";\n#end\n"
+    );
+}
+
+#[test]
+fn refuses_invalid_code() {
+    assert_eq!(format("print (1;\n"), (String::new(), Some(65)));
+}
+
+#[test]
+fn rewrites_files_in_place() {
+    let path = env::temp_dir().join(format!("rustlox-fmt-{}.lox", std::process::id()));
+    fs::write(&path, "print   1+2 ;").unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_rustlox"))
+        .arg("fmt")
+        .arg(&path)
+        .status()
+        .unwrap();
+    let formatted = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).ok();
+
+    assert!(status.success());
+    assert_eq!(formatted, "print 1 + 2;\n");
+}
@@ -0,0 +1,32 @@
+use rustlox::{Object, RustLox};
+
+#[test]
+fn passes_globals_into_scripts() {
+    let mut lox = RustLox::new();
+    lox.set_global("name", "world");
+    lox.set_global("count", 2.0);
+
+    let value = lox.eval("var greeting = \"hello \" + name;\ngreeting;");
+    assert_eq!(value.ok().unwrap().to_string(), "hello world");
+    assert_eq!(lox.eval("count * 2;").ok().unwrap().to_string(), "4");
+}
+
+#[test]
+fn reads_globals_left_by_scripts() {
+    let mut lox = RustLox::new();
+    lox.run("var result = [1, 2];\nfun f() {}").unwrap();
+
+    let result = Vec::<Object>::try_from(lox.get_global("result").unwrap()).unwrap();
+    assert_eq!(result.len(), 2);
+    assert_eq!(lox.get_global("f").unwrap().to_string(), "<fn f>");
+    assert!(lox.get_global("missing").is_none());
+}
+
+#[test]
+fn replaces_globals() {
+    let mut lox = RustLox::new();
+    lox.run("var flag = false;").unwrap();
+    lox.set_global("flag", true);
+
+    assert_eq!(lox.eval("flag;").ok().unwrap().to_string(), "true");
+}
@@ -0,0 +1,94 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use rustlox::{NativeContext, NativeError, Object, RustLox};
+
+fn double(_: &mut NativeContext, arguments: &[Object]) -> Result<Object, NativeError> {
+    match arguments[0] {
+        Object::Number(value) => Ok(Object::Number(value * 2.0)),
+        _ => Err("Expected a number.".into()),
+    }
+}
+
+// Calls its second argument with its first, twice.
+fn twice(context: &mut NativeContext, arguments: &[Object]) -> Result<Object, NativeError> {
+    let once = context.call(&arguments[1], vec![arguments[0].clone()])?;
+    context.call(&arguments[1], vec![once])
+}
+
+fn lox() -> RustLox {
+    let mut lox = RustLox::new();
+    lox.define_native("double", 1, double);
+    lox.define_native("twice", 2, twice);
+    lox
+}
+
+#[test]
+fn calls_host_natives() {
+    let value = lox().eval("double(21);").ok().unwrap();
+    assert_eq!(value.to_string(), "42");
+}
+
+#[test]
+fn host_natives_call_back_into_lox() {
+    let value = lox()
+        .eval("fun increment(n) { return n + 1; }\ntwice(1, increment);")
+        .ok()
+        .unwrap();
+    assert_eq!(value.to_string(), "3");
+}
+
+#[test]
+fn reports_host_errors_at_the_call() {
+    let diagnostics = lox().eval("print double(\"a\");").err().unwrap();
+    assert_eq!(diagnostics[0].message, "Expected a number.");
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 17));
+}
+
+#[test]
+fn passes_on_errors_of_the_lox_code_called() {
+    let diagnostics = lox()
+        .eval("fun fail(n) {\n  return n + nil;\n}\ntwice(1, fail);")
+        .err()
+        .unwrap();
+    assert_eq!(
+        diagnostics[0].message,
+        "Operands must be two numbers or two strings."
+    );
+    assert_eq!(diagnostics[0].line, 2);
+}
+
+#[test]
+fn checks_the_arity_of_host_natives() {
+    let diagnostics = lox().eval("double(1, 2);").err().unwrap();
+    assert_eq!(diagnostics[0].message, "Expected 1 arguments but got 2.");
+}
+
+#[test]
+fn natives_keep_state_between_calls() {
+    let mut lox = lox();
+    let count = Cell::new(0.0);
+    lox.define_native("next", 0, move |_, _| {
+        count.set(count.get() + 1.0);
+        Ok(Object::Number(count.get()))
+    });
+
+    let value = lox.eval("next();\nnext();\nnext();").ok().unwrap();
+    assert_eq!(value.to_string(), "3");
+}
+
+#[test]
+fn natives_share_state_with_the_host() {
+    let mut lox = lox();
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let native_log = log.clone();
+    lox.define_native("log", 1, move |_, arguments| {
+        native_log.borrow_mut().push(arguments[0].to_string());
+        Ok(Object::Nil)
+    });
+
+    lox.run("log(\"start\");\nlog(1 + 1);").unwrap();
+    assert_eq!(*log.borrow(), ["start", "2"]);
+}
@@ -0,0 +1,58 @@
+mod support;
+
+use std::time::{Duration, Instant};
+
+use support::run;
+
+const COUNT: &str = "var i = 0;\nwhile (i < 10) i = i + 1;\nprint i;\n";
+
+#[test]
+fn allows_scripts_within_the_step_limit() {
+    let (code, stdout, stderr) = run(&["--max-steps", "100"], COUNT);
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "10\n");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn stops_scripts_past_the_step_limit() {
+    let (code, stdout, stderr) = run(&["--max-steps", "5"], COUNT);
+    assert_eq!(code, Some(70));
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "[line 2:1] Ran more than 5 statements. [E4021]\n");
+}
+
+#[test]
+fn counts_steps_in_calls() {
+    let source = "fun f(n) {\n  if (n > 0) f(n - 1);\n}\nf(100);\n";
+    let (code, _, stderr) = run(&["--max-steps", "50"], source);
+    assert_eq!(code, Some(70));
+    assert_eq!(stderr, "[line 2:21] Ran more than 50 statements. [E4021]\n");
+}
+
+#[test]
+fn stops_scripts_past_the_timeout() {
+    let (code, _, stderr) = run(&["--timeout", "50"], "while (true) {}\n");
+    assert_eq!(code, Some(70));
+    assert_eq!(stderr, "[line 1:1] Ran for more than 50 ms. [E4022]\n");
+}
+
+#[test]
+fn rejects_invalid_limits() {
+    let (code, _, stderr) = run(&["--timeout", "soon"], "");
+    assert_eq!(code, Some(64));
+    assert_eq!(
+        stderr,
+        "Expected a number of milliseconds after '--timeout'.\n"
+    );
+}
+
+#[test]
+fn stops_sleeping_scripts_past_the_timeout() {
+    let start = Instant::now();
+    let (code, stdout, stderr) = run(&["--timeout", "100"], "sleep(3000);\nprint \"done\";\n");
+    assert!(start.elapsed() < Duration::from_millis(2000));
+    assert_eq!(code, Some(70));
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "[line 1:11] Ran for more than 100 ms. [E4022]\n");
+}
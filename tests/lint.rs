@@ -0,0 +1,109 @@
+mod support;
+
+use support::run;
+
+const SHADOWED: &str = "{\n  var a = 1;\n  {\n    var a = 2;\n    print a;\n  }\n}\n";
+
+#[test]
+fn warns_about_unused_locals_by_default() {
+    let (code, stdout, stderr) = run(&[], SHADOWED);
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "2\n");
+    assert_eq!(stderr, "[line 2:7] Warning: Variable 'a' is never used.\n");
+}
+
+#[test]
+fn makes_unused_locals_errors() {
+    let (code, stdout, stderr) = run(&["-W", "error=unused"], SHADOWED);
+    assert_eq!(code, Some(65));
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "[line 2:7] Error at 'a': Variable 'a' is never used. [E3009]\n"
+    );
+}
+
+#[test]
+fn allows_lints_with_w() {
+    let (code, _, stderr) = run(&["-W", "allow=unused"], SHADOWED);
+    assert_eq!(code, Some(0));
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn rejects_unknown_levels() {
+    let (code, _, stderr) = run(&["-W", "fatal=unused"], "");
+    assert_eq!(code, Some(64));
+    assert_eq!(
+        stderr,
+        "Expected 'error=NAME', 'warn=NAME' or 'allow=NAME' after '-W'.\n"
+    );
+}
+
+#[test]
+fn allows_unused_locals() {
+    let (code, stdout, stderr) = run(&["--allow", "unused"], SHADOWED);
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "2\n");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn warns_about_shadowing() {
+    let (code, stdout, stderr) = run(&["--lint", "shadow", "--allow", "unused"], SHADOWED);
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "2\n");
+    assert_eq!(
+        stderr,
+        "[line 4:9] Warning: Variable 'a' shadows a variable of an enclosing scope.\n"
+    );
+}
+
+#[test]
+fn lint_all_keeps_errors_as_errors() {
+    let (code, _, stderr) = run(&["-W", "error=unused", "--lint", "all"], SHADOWED);
+    assert_eq!(code, Some(65));
+    assert_eq!(
+        stderr,
+        "[line 4:9] Warning: Variable 'a' shadows a variable of an enclosing scope.\n\
+         [line 2:7] Error at 'a': Variable 'a' is never used. [E3009]\n"
+    );
+}
+
+#[test]
+fn rejects_unknown_lints() {
+    let (code, _, stderr) = run(&["--lint", "unknown"], "");
+    assert_eq!(code, Some(64));
+    assert_eq!(stderr, "Unknown lint 'unknown'.\n");
+}
+
+#[test]
+fn warns_about_unused_parameters() {
+    let (code, _, stderr) = run(&[], "fun f(a, b) {\n  print b;\n}\n");
+    assert_eq!(code, Some(0));
+    assert_eq!(stderr, "[line 1:7] Warning: Parameter 'a' is never used.\n");
+
+    let (_, _, stderr) = run(&["--allow", "unused"], "fun f(a) {\n  var b;\n}\n");
+    assert_eq!(stderr, "[line 1:7] Warning: Parameter 'a' is never used.\n");
+}
+
+#[test]
+fn allows_unused_parameters() {
+    let (code, _, stderr) = run(
+        &["--allow", "unused-parameter"],
+        "fun f(a) {\n  var b;\n}\n",
+    );
+    assert_eq!(code, Some(0));
+    assert_eq!(stderr, "[line 2:7] Warning: Variable 'b' is never used.\n");
+}
+
+#[test]
+fn ignores_names_starting_with_an_underscore() {
+    let (code, stdout, stderr) = run(
+        &["-W", "error=all"],
+        "fun f(_a) {\n  var _b = 1;\n  return 2;\n}\nprint f(1);\n",
+    );
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "2\n");
+    assert_eq!(stderr, "");
+}
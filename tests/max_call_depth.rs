@@ -0,0 +1,36 @@
+mod support;
+
+use std::path::Path;
+
+use support::{run, run_script};
+
+const COUNT_DOWN: &str = "fun count(n) {\n  if (n > 1) count(n - 1);\n}\n";
+
+#[test]
+fn allows_calls_up_to_the_limit() {
+    let source = format!("{COUNT_DOWN}count(10);\nprint \"done\";\n");
+    let (_, stdout, stderr) = run(&["--max-call-depth", "10"], &source);
+    assert_eq!(stdout, "done\n");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn reports_calls_past_the_limit() {
+    let source = format!("{COUNT_DOWN}count(11);\n");
+    let (_, _, stderr) = run(&["--max-call-depth", "10"], &source);
+    assert_eq!(stderr, "[line 2:25] Stack overflow. [E4009]\n");
+}
+
+#[test]
+fn rejects_invalid_limits() {
+    let output = run_script(
+        Path::new(env!("CARGO_BIN_EXE_rustlox")),
+        &["--max-call-depth", "deep"],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(64));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Expected a number after '--max-call-depth'.\n"
+    );
+}
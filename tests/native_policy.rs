@@ -0,0 +1,71 @@
+mod support;
+
+use std::path::Path;
+
+use rustlox::{NativePolicy, RustLox};
+use support::{run_script, without_snippets};
+
+const SCRIPT: &str = "print len(\"abc\");\nprint type(clock());\n";
+
+fn run(options: &[&str]) -> (String, String) {
+    let output = run_script(Path::new(env!("CARGO_BIN_EXE_rustlox")), options, SCRIPT);
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        without_snippets(output.stderr),
+    )
+}
+
+#[test]
+fn allows_every_native_by_default() {
+    assert_eq!(run(&[]).0, "3\nnumber\n");
+}
+
+#[test]
+fn allows_only_listed_natives() {
+    let (stdout, stderr) = run(&["--allow-natives", "len,type"]);
+    assert_eq!(stdout, "3\n");
+    assert_eq!(
+        stderr,
+        "[line 2:18] Native function 'clock' isn't allowed. [E4018]\n"
+    );
+}
+
+#[test]
+fn denies_listed_natives() {
+    let (stdout, stderr) = run(&["--deny-natives", "len"]);
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "[line 1:16] Native function 'len' isn't allowed. [E4018]\n"
+    );
+}
+
+#[test]
+fn checks_natives_stored_in_other_globals() {
+    let output = run_script(
+        Path::new(env!("CARGO_BIN_EXE_rustlox")),
+        &["--deny-natives", "clock"],
+        "var now = clock;\nprint type(now);\nnow();\n",
+    );
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "function\n");
+    assert_eq!(
+        without_snippets(output.stderr),
+        "[line 3:5] Native function 'clock' isn't allowed. [E4018]\n"
+    );
+}
+
+#[test]
+fn checks_natives_stored_before_the_policy_changed() {
+    let mut lox = RustLox::new();
+    lox.run("var list = [clock];\nclass Box {}\nvar box = Box();\nbox.f = clock;")
+        .unwrap();
+    lox.set_native_policy(NativePolicy::Deny(["clock".to_owned()].into()));
+
+    for source in ["clock();", "list[0]();", "box.f();"] {
+        let diagnostics = lox.eval(source).err().unwrap();
+        assert_eq!(
+            diagnostics[0].message, "Native function 'clock' isn't allowed.",
+            "{source}"
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use rustlox::{RustLox, Severity};
+
+fn warnings(source: &str) -> Vec<String> {
+    let mut lox = RustLox::new();
+    lox.set_output(std::io::sink());
+    lox.run(source)
+        .unwrap()
+        .into_iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Warning)
+        .map(|diagnostic| diagnostic.message)
+        .collect()
+}
+
+#[test]
+fn warns_about_integer_digits_that_are_lost() {
+    assert_eq!(
+        warnings("print 9007199254740993;"),
+        ["Warning: Number literal '9007199254740993' can't be represented exactly and becomes 9007199254740992."]
+    );
+}
+
+#[test]
+fn warns_about_fractional_digits_that_are_lost() {
+    assert_eq!(
+        warnings("print 1.00000000000000000001;"),
+        ["Warning: Number literal '1.00000000000000000001' can't be represented exactly and becomes 1."]
+    );
+}
+
+#[test]
+fn accepts_literals_that_print_back_the_same() {
+    assert!(warnings("print 0.1; print 1.50; print 007; print 10.0; print 123.456;").is_empty());
+}
+
+#[test]
+fn ignores_literals_left_out_by_directives() {
+    assert!(warnings("#if DEBUG\nprint 9007199254740993;\n#end\n").is_empty());
+}
+
+#[test]
+fn warns_about_integers_that_print_back_rounded() {
+    assert_eq!(
+        warnings("print 100000000000000000000000;"),
+        ["Warning: Number literal '100000000000000000000000' can't be represented exactly and becomes 99999999999999991611392."]
+    );
+    assert!(warnings("print 9007199254740992; print 18014398509481984;").is_empty());
+}
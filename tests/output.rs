@@ -0,0 +1,64 @@
+use std::{
+    cell::RefCell,
+    env, fs,
+    io::{self, Write},
+    process,
+    rc::Rc,
+};
+
+use rustlox::RustLox;
+
+// A writer whose contents can still be read once the interpreter owns it.
+#[derive(Clone, Default)]
+struct Captured(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Captured {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+#[test]
+fn captures_print_and_write() {
+    let output = Captured::default();
+    let mut lox = RustLox::new();
+    lox.set_output(output.clone());
+
+    lox.run("print 1 + 2;\nwrite(\"a\");\nwrite(\"b\");\nprint [\"c\"];")
+        .unwrap();
+    assert_eq!(output.text(), "3\nab[c]\n");
+}
+
+#[test]
+fn keeps_output_written_before_an_error() {
+    let output = Captured::default();
+    let mut lox = RustLox::new();
+    lox.set_output(output.clone());
+
+    assert!(lox.run("print \"before\";\nprint nil + 1;").is_err());
+    assert_eq!(output.text(), "before\n");
+}
+
+#[test]
+fn captures_doctest_results() {
+    let script = env::temp_dir().join(format!("rustlox-output-doctest-{}.lox", process::id()));
+    fs::write(&script, "/// >>> 1 + 2\n/// 3\n").unwrap();
+    let output = Captured::default();
+    let mut lox = RustLox::new();
+    lox.set_output(output.clone());
+
+    let passed = lox.run_doctests(script.to_str().unwrap()).unwrap();
+    fs::remove_file(&script).ok();
+    assert!(passed);
+    assert!(output.text().ends_with(":1 ... ok\n\n1 passed; 0 failed\n"));
+}
@@ -0,0 +1,115 @@
+use std::{env, fs, process};
+
+use rustlox::RustLox;
+
+// Scripts report their results through a file, since they print to the test
+// process' stdout.
+#[test]
+fn reruns_cached_and_changed_scripts() {
+    let dir = env::temp_dir();
+    let script = dir.join(format!("rustlox-cache-{}.lox", process::id()));
+    let result = script.with_extension("txt");
+    let script_path = script.to_str().unwrap();
+
+    let source = |value: &str| {
+        format!(
+            "var total = 0;\n\
+             fun add(n) {{ var sum = total + n; return sum; }}\n\
+             total = add({value});\n\
+             writeFile({:?}, format(\"{{}}\", total));\n",
+            result.to_str().unwrap()
+        )
+    };
+
+    let mut rustlox = RustLox::new();
+    fs::write(&script, source("1")).unwrap();
+    rustlox.run_file(script_path).unwrap();
+    assert_eq!(fs::read_to_string(&result).unwrap(), "1");
+
+    // The cached program is resolved against the same interpreter, so its
+    // locals still work.
+    rustlox.run_file(script_path).unwrap();
+    assert_eq!(fs::read_to_string(&result).unwrap(), "1");
+
+    fs::write(&script, source("2")).unwrap();
+    rustlox.run_file(script_path).unwrap();
+    assert_eq!(fs::read_to_string(&result).unwrap(), "2");
+
+    fs::remove_file(&script).ok();
+    fs::remove_file(&result).ok();
+}
+
+// More scripts than the cache holds, so older ones are dropped and parsed
+// again when they run next.
+#[test]
+fn reruns_scripts_dropped_from_the_cache() {
+    let dir = env::temp_dir();
+    let result = dir.join(format!("rustlox-cache-evicted-{}.txt", process::id()));
+    let scripts: Vec<_> = (0..20)
+        .map(|n| {
+            let script = dir.join(format!("rustlox-cache-{}-{n}.lox", process::id()));
+            fs::write(
+                &script,
+                format!(
+                    "class Counter {{ value() {{ return {n}; }} }}\n\
+                     writeFile({:?}, format(\"{{}}\", Counter().value()));\n",
+                    result.to_str().unwrap()
+                ),
+            )
+            .unwrap();
+            script
+        })
+        .collect();
+
+    let mut rustlox = RustLox::new();
+    for _ in 0..2 {
+        for (n, script) in scripts.iter().enumerate() {
+            rustlox.run_file(script.to_str().unwrap()).unwrap();
+            assert_eq!(fs::read_to_string(&result).unwrap(), n.to_string());
+        }
+    }
+
+    for script in &scripts {
+        fs::remove_file(script).ok();
+    }
+    fs::remove_file(&result).ok();
+}
+
+// A function keeps working after the program that declared it is dropped from
+// the cache, since what the resolver found goes away with the program only
+// once nothing uses it.
+#[test]
+fn calls_functions_from_scripts_dropped_from_the_cache() {
+    let dir = env::temp_dir();
+    let result = dir.join(format!("rustlox-cache-function-{}.txt", process::id()));
+    let script = |n: usize, source: &str| {
+        let script = dir.join(format!("rustlox-cache-function-{}-{n}.lox", process::id()));
+        fs::write(&script, source).unwrap();
+        script
+    };
+
+    let declaration = script(0, "fun add(n) { var sum = n + 1; return sum; }\n");
+    let others: Vec<_> = (1..20)
+        .map(|n| script(n, &format!("var x{n} = {n};\n")))
+        .collect();
+    let call = script(
+        20,
+        &format!(
+            "writeFile({:?}, format(\"{{}}\", add(1)));\n",
+            result.to_str().unwrap()
+        ),
+    );
+
+    let mut rustlox = RustLox::new();
+    rustlox.run_file(declaration.to_str().unwrap()).unwrap();
+    for other in &others {
+        rustlox.run_file(other.to_str().unwrap()).unwrap();
+    }
+    rustlox.run_file(call.to_str().unwrap()).unwrap();
+    assert_eq!(fs::read_to_string(&result).unwrap(), "2");
+
+    for script in others.iter().chain([&declaration, &call]) {
+        fs::remove_file(script).ok();
+    }
+    fs::remove_file(&result).ok();
+}
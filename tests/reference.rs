@@ -0,0 +1,75 @@
+mod support;
+
+use std::path::{Path, PathBuf};
+
+use support::{collect_tests, run_test};
+
+// The scanning and expressions tests check the output of earlier chapters of
+// the book, which rustlox doesn't have a mode for.
+const EXCLUDED: &[&str] = &["scanning", "expressions"];
+
+// Tests where rustlox knowingly doesn't match jlox.
+const KNOWN_DIVERGENCES: &[&str] = &[
+    // Invalid assignment targets are reported but don't stop the script.
+    "test/assignment/grouping.lox",
+    "test/assignment/infix_operator.lox",
+    "test/assignment/prefix_operator.lox",
+    "test/assignment/to_this.lox",
+    // Too many arguments or parameters are reported but the script still
+    // runs.
+    "test/function/too_many_arguments.lox",
+    "test/function/too_many_parameters.lox",
+    "test/method/too_many_arguments.lox",
+    "test/method/too_many_parameters.lox",
+    // `fun` starts a lambda expression.
+    "test/for/fun_in_body.lox",
+    "test/if/fun_in_else.lox",
+    "test/if/fun_in_then.lox",
+    "test/while/fun_in_body.lox",
+    // Some parse error messages lack their final period.
+    "test/for/statement_condition.lox",
+    "test/for/statement_initializer.lox",
+    // Every bound method access creates a new, unequal, bound method.
+    "test/operator/equals_method.lox",
+    // Negation shares the arithmetic operators' message.
+    "test/operator/negate_nonnum.lox",
+    // Division by zero is a runtime error.
+    "test/number/nan_equality.lox",
+    // The message ends with an OS-specific description of the failure.
+    "test/native/read_file_missing.lox",
+];
+
+#[test]
+fn matches_reference_results() {
+    let interpreter = Path::new(env!("CARGO_BIN_EXE_rustlox"));
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut tests: Vec<PathBuf> = Vec::new();
+    collect_tests(&root.join("test"), EXCLUDED, &mut tests);
+    tests.sort();
+
+    let mut unexpected = Vec::new();
+    let mut fixed = Vec::new();
+    for path in &tests {
+        let name = path
+            .strip_prefix(root)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+        let known = KNOWN_DIVERGENCES.contains(&name.as_str());
+        match run_test(interpreter, path) {
+            Some(divergence) if !known => unexpected.push(divergence.to_string()),
+            None if known => fixed.push(name),
+            _ => (),
+        }
+    }
+
+    assert!(
+        unexpected.is_empty() && fixed.is_empty(),
+        "{} of {} tests diverge from the reference results:\n\n{}\n\
+         Tests that now match and should be removed from KNOWN_DIVERGENCES: {:?}",
+        unexpected.len(),
+        tests.len(),
+        unexpected.join("\n"),
+        fixed
+    );
+}
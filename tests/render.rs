@@ -0,0 +1,40 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rustlox::{render, Object, Style};
+
+fn list(elements: Vec<Object>) -> Object {
+    Object::List(Rc::new(RefCell::new(elements)))
+}
+
+#[test]
+fn renders_each_style() {
+    let value = list(vec![
+        Object::String("say \"hi\"".into()),
+        Object::Number(1.5),
+        Object::Nil,
+        list(vec![Object::Boolean(true)]),
+    ]);
+
+    assert_eq!(
+        render(&value, Style::Plain),
+        r#"[say "hi", 1.5, nil, [true]]"#
+    );
+    assert_eq!(
+        render(&value, Style::Quoted),
+        r#"["say \"hi\"", 1.5, nil, [true]]"#
+    );
+    assert_eq!(
+        render(&value, Style::Json),
+        r#"["say \"hi\"", 1.5, null, [true]]"#
+    );
+    assert_eq!(
+        render(&value, Style::Pretty),
+        "[\n  \"say \\\"hi\\\"\",\n  1.5,\n  nil,\n  [\n    true\n  ]\n]"
+    );
+}
+
+#[test]
+fn renders_numbers_without_json_equivalent_as_null() {
+    assert_eq!(render(&Object::Number(f64::NAN), Style::Json), "null");
+    assert_eq!(render(&Object::Number(f64::INFINITY), Style::Plain), "inf");
+}
@@ -0,0 +1,95 @@
+use rustlox::{Diagnostic, ErrorCode, ErrorReporter, InterpreterConfig, Object, RustLox, Severity};
+use std::{cell::RefCell, env, fs, process, rc::Rc};
+
+fn script(name: &str, source: &str) -> String {
+    let path = env::temp_dir().join(format!("rustlox-reporter-{}-{name}.lox", process::id()));
+    fs::write(&path, source).unwrap();
+    path.to_str().unwrap().to_owned()
+}
+
+#[test]
+fn sends_diagnostics_to_the_reporter() {
+    let reported = Rc::new(RefCell::new(Vec::<Diagnostic>::new()));
+    let mut lox = RustLox::new();
+    lox.set_reporter(reported.clone());
+
+    let path = script("warning", "{\n    var unused = 1;\n}\n");
+    lox.run_file(&path).unwrap();
+    fs::remove_file(&path).ok();
+
+    let reported = reported.borrow();
+    assert_eq!(reported.len(), 1);
+    assert_eq!(reported[0].severity, Severity::Warning);
+    assert_eq!(reported[0].line, 2);
+    assert_eq!(
+        reported[0].message,
+        "Warning: Variable 'unused' is never used."
+    );
+}
+
+#[test]
+fn run_returns_diagnostics_instead() {
+    let reported = Rc::new(RefCell::new(Vec::<Diagnostic>::new()));
+    let mut lox = RustLox::new();
+    lox.set_reporter(reported.clone());
+
+    let diagnostics = lox.run("print x;").unwrap_err();
+    assert_eq!(diagnostics.len(), 1);
+    assert!(reported.borrow().is_empty());
+}
+
+struct Counter(Rc<RefCell<usize>>);
+
+impl ErrorReporter for Counter {
+    fn report(&mut self, _: Diagnostic) {
+        *self.0.borrow_mut() += 1;
+    }
+}
+
+#[test]
+fn accepts_custom_reporters() {
+    let count = Rc::new(RefCell::new(0));
+    let mut lox = RustLox::new();
+    lox.set_reporter(Counter(count.clone()));
+
+    let path = script("custom", "{ var a; var b; }\n");
+    lox.run_file(&path).unwrap();
+    fs::remove_file(&path).ok();
+
+    assert_eq!(*count.borrow(), 2);
+}
+
+#[test]
+fn reports_crashes_and_recovers() {
+    let reported = Rc::new(RefCell::new(Vec::<Diagnostic>::new()));
+    let mut lox = RustLox::with_config(InterpreterConfig {
+        max_call_depth: 3,
+        ..Default::default()
+    });
+    lox.set_reporter(reported.clone());
+    lox.define_native("crash", 0, |_, _| panic!("Crashed on purpose."));
+
+    // Each crash happens inside calls that never return.
+    let path = script("crash", "fun f() { crash(); }\nf();\n");
+    for _ in 0..3 {
+        assert!(lox.run_file(&path).is_err());
+    }
+    fs::remove_file(&path).ok();
+
+    for diagnostic in reported.borrow().iter() {
+        assert_eq!(diagnostic.code, Some(ErrorCode::InternalError));
+        let report = diagnostic
+            .message
+            .strip_prefix(
+                "Error: Internal error, this is a bug in rustlox. A crash report was written to ",
+            )
+            .and_then(|rest| rest.strip_suffix(", please attach it to your bug report."))
+            .unwrap();
+        fs::remove_file(report).ok();
+    }
+    assert_eq!(reported.borrow().len(), 3);
+
+    // Had the calls been left counted, this would overflow the stack.
+    let value = lox.eval("fun g(n) { if (n > 0) return g(n - 1); return n; }\ng(2);");
+    assert!(matches!(value, Ok(Object::Number(n)) if n == 0.0));
+}
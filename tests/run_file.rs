@@ -0,0 +1,34 @@
+use rustlox::{Diagnostic, RunError, RustLox};
+use std::{env, fs, process};
+
+fn run_file(name: &str, source: &str) -> Result<(), RunError> {
+    let path = env::temp_dir().join(format!("rustlox-run-file-{}-{name}.lox", process::id()));
+    fs::write(&path, source).unwrap();
+
+    let mut lox = RustLox::new();
+    lox.set_reporter(Vec::<Diagnostic>::new());
+    let result = lox.run_file(path.to_str().unwrap());
+    fs::remove_file(&path).ok();
+    result
+}
+
+#[test]
+fn returns_instead_of_exiting() {
+    assert!(run_file("ok", "var a = 1;").is_ok());
+    assert!(matches!(
+        run_file("invalid", "print 1"),
+        Err(RunError::Invalid)
+    ));
+    assert!(matches!(
+        run_file("failed", "print -nil;"),
+        Err(RunError::Failed)
+    ));
+}
+
+#[test]
+fn returns_read_errors() {
+    let error = RustLox::new()
+        .run_file("/nonexistent/script.lox")
+        .unwrap_err();
+    assert!(matches!(error, RunError::Io(_)));
+}
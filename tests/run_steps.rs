@@ -0,0 +1,64 @@
+use rustlox::{InterpreterConfig, RustLox, Step};
+
+fn global(lox: &RustLox, name: &str) -> String {
+    lox.get_global(name).unwrap().to_string()
+}
+
+#[test]
+fn runs_a_few_statements_at_a_time() {
+    let mut lox = RustLox::new();
+    lox.start("var a = 1;\na = a + 1;\na = a * 10;\n").unwrap();
+
+    assert_eq!(lox.run_steps(1).unwrap(), Step::Pending);
+    assert_eq!(global(&lox, "a"), "1");
+    assert_eq!(lox.run_steps(1).unwrap(), Step::Pending);
+    assert_eq!(global(&lox, "a"), "2");
+    assert_eq!(lox.run_steps(5).unwrap(), Step::Done);
+    assert_eq!(global(&lox, "a"), "20");
+
+    // There's nothing left to run.
+    assert_eq!(lox.run_steps(1).unwrap(), Step::Done);
+}
+
+#[test]
+fn runs_nothing_before_a_program_starts() {
+    assert_eq!(RustLox::new().run_steps(1).unwrap(), Step::Done);
+}
+
+#[test]
+fn returns_errors_found_before_running() {
+    let mut lox = RustLox::new();
+    let diagnostics = lox.start("var a = 1;\nprint a +;\n").unwrap_err();
+    assert_eq!(diagnostics[0].message, "Error at ';': Expect expression.");
+
+    assert_eq!(lox.run_steps(1).unwrap(), Step::Done);
+    assert!(lox.get_global("a").is_none());
+}
+
+#[test]
+fn stops_at_a_runtime_error() {
+    let mut lox = RustLox::new();
+    lox.start("var a = 1;\na + nil;\na = 2;\n").unwrap();
+
+    let diagnostics = lox.run_steps(3).unwrap_err();
+    assert_eq!(
+        diagnostics[0].message,
+        "Operands must be two numbers or two strings."
+    );
+    assert_eq!(lox.run_steps(1).unwrap(), Step::Done);
+    assert_eq!(global(&lox, "a"), "1");
+}
+
+#[test]
+fn applies_the_step_limit_to_each_call() {
+    let mut lox = RustLox::with_config(InterpreterConfig {
+        max_steps: Some(100),
+        ..Default::default()
+    });
+    let lap = "for (var i = 0; i < 30; i = i + 1) {}\n";
+    lox.start(&lap.repeat(3)).unwrap();
+
+    assert_eq!(lox.run_steps(1).unwrap(), Step::Pending);
+    assert_eq!(lox.run_steps(1).unwrap(), Step::Pending);
+    assert_eq!(lox.run_steps(1).unwrap(), Step::Done);
+}
@@ -0,0 +1,51 @@
+use rustlox::RustLox;
+
+fn undefined(lox: &mut RustLox, source: &str) -> String {
+    let diagnostics = lox.run(source).unwrap_err();
+    diagnostics[0].message.clone()
+}
+
+#[test]
+fn leaves_out_disallowed_natives() {
+    let mut lox = RustLox::builder()
+        .allow_io(false)
+        .allow_clock(false)
+        .build();
+
+    assert_eq!(
+        undefined(&mut lox, "readFile(\"secret\");"),
+        "Undefined variable 'readFile'."
+    );
+    assert_eq!(
+        undefined(&mut lox, "clock();"),
+        "Undefined variable 'clock'."
+    );
+    assert_eq!(lox.eval("getenv;").ok().unwrap().to_string(), "<native fn>");
+    assert_eq!(lox.eval("len([1, 2]);").ok().unwrap().to_string(), "2");
+}
+
+#[test]
+fn sandboxed_leaves_out_every_outside_native() {
+    let mut lox = RustLox::builder().sandboxed().build();
+
+    for native in [
+        "readLine",
+        "readFile",
+        "writeFile",
+        "clock",
+        "sleep",
+        "getenv",
+    ] {
+        assert_eq!(
+            undefined(&mut lox, &format!("{native};")),
+            format!("Undefined variable '{native}'.")
+        );
+    }
+    assert_eq!(lox.eval("type(1);").ok().unwrap().to_string(), "number");
+}
+
+#[test]
+fn defines_every_native_by_default() {
+    let mut lox = RustLox::builder().build();
+    assert_eq!(lox.eval("clock;").ok().unwrap().to_string(), "<native fn>");
+}
@@ -0,0 +1,33 @@
+mod support;
+
+use std::path::Path;
+
+use support::run_repl;
+
+fn repl(options: &[&str], input: &str) -> String {
+    let output = run_repl(Path::new(env!("CARGO_BIN_EXE_rustlox")), options, input);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn lists_created_and_modified_globals() {
+    let stdout = repl(
+        &["--show-changes"],
+        "var a = 1;\na = 2;\nvar s = \"hi\"; var t = [s];\nprint a;\n",
+    );
+    assert_eq!(
+        stdout,
+        "> + a = 1\n> ~ a = 2\n> + s = \"hi\"\n+ t = [\"hi\"]\n> 2\n> "
+    );
+}
+
+#[test]
+fn shows_the_latest_value_of_a_global() {
+    let stdout = repl(&["--show-changes"], "var a = 1; a = a + 1; a = a * 10;\n");
+    assert_eq!(stdout, "> + a = 20\n> ");
+}
+
+#[test]
+fn lists_nothing_by_default() {
+    assert_eq!(repl(&[], "var a = 1;\na = 2;\n"), "> > > ");
+}
@@ -0,0 +1,108 @@
+use rustlox::RustLox;
+use std::{
+    env, fs,
+    io::Write,
+    process::{self, Command, Stdio},
+};
+
+fn restored(source: &str) -> RustLox {
+    let mut lox = RustLox::new();
+    lox.run(source).unwrap();
+    let snapshot = lox.snapshot().unwrap();
+
+    let mut restored = RustLox::new();
+    restored.restore(&snapshot).unwrap();
+    restored
+}
+
+fn eval(lox: &mut RustLox, source: &str) -> String {
+    lox.eval(source).ok().unwrap().to_string()
+}
+
+#[test]
+fn restores_plain_values() {
+    let mut lox = restored(
+        "var n = -1.5;\nvar s = \"say \" + decodeUtf8([34], \"strict\") + \"hi\";\nvar b = true;\nvar none = nil;\nvar list = [1, [\"a\"], nil];",
+    );
+
+    assert_eq!(eval(&mut lox, "n;"), "-1.5");
+    assert_eq!(eval(&mut lox, "s;"), "say \"hi");
+    assert_eq!(eval(&mut lox, "b;"), "true");
+    assert_eq!(eval(&mut lox, "none;"), "nil");
+    assert_eq!(eval(&mut lox, "list;"), "[1, [a], nil]");
+}
+
+#[test]
+fn restores_functions_and_classes() {
+    let mut lox = restored(
+        "fun square(x) { return x * x; }
+var twice = fun (x) { return x * 2; };
+var alias = square;
+class Animal {
+    init(name) { this.name = name; }
+    speak() { return this.name + \" makes a sound\"; }
+}
+class Dog < Animal {
+    speak() { return super.speak() + \": woof\"; }
+    static count = 0;
+}
+Dog.count = 3;
+var kinds = [Animal, Dog];",
+    );
+
+    assert_eq!(eval(&mut lox, "square(3);"), "9");
+    assert_eq!(eval(&mut lox, "twice(4);"), "8");
+    assert_eq!(eval(&mut lox, "alias(5);"), "25");
+    assert_eq!(
+        eval(&mut lox, "Dog(\"Rex\").speak();"),
+        "Rex makes a sound: woof"
+    );
+    assert_eq!(eval(&mut lox, "Dog.count;"), "3");
+    assert_eq!(eval(&mut lox, "kinds[1] == Dog;"), "true");
+}
+
+#[test]
+fn reports_values_that_cant_be_saved() {
+    let cases = [
+        ("class A {}\nvar a = A();", "Can't save 'a': instances can't be saved."),
+        (
+            "fun counter() { var n = 0; fun next() { n = n + 1; return n; } return next; }\nvar next = counter();",
+            "Can't save 'next': it's a closure over local variables.",
+        ),
+        ("var l = [];\npush(l, l);", "Can't save 'l': it contains itself."),
+    ];
+
+    for (source, message) in cases {
+        let mut lox = RustLox::new();
+        lox.run(source).unwrap();
+        assert_eq!(lox.snapshot().err().unwrap().to_string(), message);
+    }
+}
+
+#[test]
+fn repl_session_keeps_globals() {
+    let session = env::temp_dir().join(format!("rustlox-session-{}.lox", process::id()));
+    let repl = |input: &str| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rustlox"))
+            .arg("--session")
+            .arg(&session)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    };
+
+    repl("var greeting = \"hi\";\nfun shout(s) { return s + \"!\"; }\n");
+    let output = repl("print shout(greeting);\n");
+    fs::remove_file(&session).ok();
+
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "> hi!\n> ");
+}
@@ -0,0 +1,47 @@
+mod support;
+
+use std::path::Path;
+
+use support::{run_stdin, without_snippets};
+
+#[test]
+fn runs_the_program_from_stdin() {
+    let output = run_stdin(
+        Path::new(env!("CARGO_BIN_EXE_rustlox")),
+        &[],
+        "var greeting = \"hello\";\nprint greeting;\n",
+    );
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "hello\n");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn reports_errors_like_a_file() {
+    let output = run_stdin(
+        Path::new(env!("CARGO_BIN_EXE_rustlox")),
+        &[],
+        "print missing;\n",
+    );
+    assert_eq!(
+        without_snippets(output.stderr),
+        "[line 1:7] Undefined variable 'missing'. [E4003]\n"
+    );
+    assert_eq!(output.status.code(), Some(70));
+}
+
+#[test]
+fn quotes_lexical_errors_as_they_are_read() {
+    let output = run_stdin(
+        Path::new(env!("CARGO_BIN_EXE_rustlox")),
+        &[],
+        "print 1;\nvar a = @;\n",
+    );
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "[line 2:9] Error: Unexpected character: \"@\". [E1001]\n\
+         2 | var a = @;\n  |         ^\n\
+         [line 2:10] Error at ';': Expect expression. [E2008]\n\
+         2 | var a = @;\n  |          ^\n"
+    );
+    assert_eq!(output.status.code(), Some(65));
+}
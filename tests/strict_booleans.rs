@@ -0,0 +1,91 @@
+mod support;
+
+use support::run;
+
+fn strict(source: &str) -> String {
+    let (code, stdout, stderr) = run(&["--strict-booleans"], source);
+    assert_eq!(code, Some(70), "{source}");
+    assert_eq!(stdout, "", "{source}");
+    stderr
+}
+
+#[test]
+fn uses_truthiness_by_default() {
+    let (code, stdout, stderr) = run(
+        &[],
+        "if (1) print \"if\";\nwhile (nil) {}\nprint \"a\" ? 1 : 2;\nprint 1 and 2;\nprint nil or \"x\";\n",
+    );
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "if\n1\n2\nx\n");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn accepts_boolean_conditions() {
+    let (code, stdout, stderr) = run(
+        &["--strict-booleans"],
+        "if (true) print \"if\";\nwhile (false) {}\nprint false ? 1 : 2;\nprint true and false;\nprint false or true;\n",
+    );
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "if\n2\nfalse\ntrue\n");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn rejects_non_boolean_if_and_while_conditions() {
+    assert_eq!(
+        strict("if (1) print \"yes\";\n"),
+        "[line 1:1] Condition must be a boolean; got number. [E4015]\n"
+    );
+    assert_eq!(
+        strict("while (nil) {}\n"),
+        "[line 1:1] Condition must be a boolean; got nil. [E4015]\n"
+    );
+}
+
+#[test]
+fn rejects_non_boolean_ternary_conditions() {
+    assert_eq!(
+        strict("print \"a\" ? 1 : 2;\n"),
+        "[line 1:11] Condition must be a boolean; got string. [E4015]\n"
+    );
+}
+
+#[test]
+fn rejects_non_boolean_operands_of_and_and_or() {
+    assert_eq!(
+        strict("print 1 and true;\n"),
+        "[line 1:9] Condition must be a boolean; got number. [E4015]\n"
+    );
+    assert_eq!(
+        strict("print true and 1;\n"),
+        "[line 1:12] Condition must be a boolean; got number. [E4015]\n"
+    );
+    assert_eq!(
+        strict("print false or \"x\";\n"),
+        "[line 1:13] Condition must be a boolean; got string. [E4015]\n"
+    );
+}
+
+#[test]
+fn rejects_non_boolean_filter_results() {
+    assert_eq!(
+        strict("print filter([1, 2], fun (x) { return x; });\n"),
+        "[line 1:43] Condition must be a boolean; got number. [E4015]\n"
+    );
+
+    let (code, stdout, _) = run(
+        &["--strict-booleans"],
+        "print filter([1, 2], fun (x) { return x > 1; });\n",
+    );
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "[2]\n");
+}
+
+#[test]
+fn rejects_non_boolean_operands_of_not() {
+    assert_eq!(
+        strict("print !nil;\n"),
+        "[line 1:7] Condition must be a boolean; got nil. [E4015]\n"
+    );
+}
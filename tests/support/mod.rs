@@ -0,0 +1,279 @@
+// Helpers to run the rustlox binary from tests. `run_test` runs a test from
+// the Lox corpus and compares rustlox's behavior with the reference results
+// recorded in its comments, which come from jlox:
+//
+//     print 1; // expect: 1
+//     -"s"; // expect runtime error: Operand must be a number.
+//     var = 1; // Error at '=': Expect variable name.
+//     // [line 3] Error at end: Expect '}' after block.
+//
+// This follows the conventions of the book's own test runner, minus its
+// handling of clox-only expectations.
+//
+// Each test crate uses only some of these helpers.
+#![allow(dead_code)]
+
+use std::{
+    env, fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{self, Command, Output, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+const EXPECT_OUTPUT: &str = "// expect: ";
+const EXPECT_RUNTIME_ERROR: &str = "// expect runtime error: ";
+
+// Exit codes used by jlox, see sysexits.h.
+const EX_DATAERR: i32 = 65;
+const EX_SOFTWARE: i32 = 70;
+
+pub struct Expectation {
+    output: Vec<String>,
+    compile_errors: Vec<String>,
+    runtime_error: Option<String>,
+}
+
+impl Expectation {
+    pub fn parse(source: &str) -> Expectation {
+        let mut expectation = Expectation {
+            output: Vec::new(),
+            compile_errors: Vec::new(),
+            runtime_error: None,
+        };
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            if let Some(output) = after(line, EXPECT_OUTPUT) {
+                expectation.output.push(output.to_string());
+            } else if let Some(message) = after(line, EXPECT_RUNTIME_ERROR) {
+                expectation.runtime_error = Some(format!("[line {line_number}] {message}"));
+            } else if let Some(error) = after(line, "// [line ") {
+                expectation.compile_errors.push(format!("[line {error}"));
+            } else if let Some(error) = after(line, "// [java line ") {
+                expectation.compile_errors.push(format!("[line {error}"));
+            } else if let Some(error) = after(line, "// Error") {
+                expectation
+                    .compile_errors
+                    .push(format!("[line {line_number}] Error{error}"));
+            }
+        }
+
+        expectation
+    }
+
+    fn exit_code(&self) -> i32 {
+        if !self.compile_errors.is_empty() {
+            EX_DATAERR
+        } else if self.runtime_error.is_some() {
+            EX_SOFTWARE
+        } else {
+            0
+        }
+    }
+}
+
+// The resolver doesn't report errors in source order, so their order is
+// ignored.
+fn same_errors(errors: &[&str], expected: &[String]) -> bool {
+    let mut errors = errors.to_vec();
+    let mut expected: Vec<&str> = expected.iter().map(String::as_str).collect();
+    errors.sort_unstable();
+    expected.sort_unstable();
+    errors == expected
+}
+
+// Snippets quote a source line after its number, or underline it.
+pub fn is_snippet(line: &str) -> bool {
+    line.split_once(" | ")
+        .is_some_and(|(gutter, _)| gutter.trim_start().chars().all(|c| c.is_ascii_digit()))
+}
+
+// The messages in a program's standard error, without their snippets.
+pub fn without_snippets(stderr: Vec<u8>) -> String {
+    String::from_utf8(stderr)
+        .unwrap()
+        .lines()
+        .filter(|line| !is_snippet(line))
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+// The reference results don't have columns or error codes, so
+// "[line 3:7] Error at 'x': Expect ';'. [E2001]" becomes
+// "[line 3] Error at 'x': Expect ';'.".
+fn without_column(line: &str) -> String {
+    let line = match line.rsplit_once(" [E") {
+        Some((message, code)) if code.len() == 5 && code.ends_with(']') => message,
+        _ => line,
+    };
+
+    if let (Some(location), Some(end)) = (line.strip_prefix("[line "), line.find(']')) {
+        if let Some(colon) = location[..end - 6].find(':') {
+            return format!("[line {}{}", &location[..colon], &line[end..]);
+        }
+    }
+
+    line.to_owned()
+}
+
+fn after<'a>(line: &'a str, marker: &str) -> Option<&'a str> {
+    line.find(marker).map(|start| &line[start + marker.len()..])
+}
+
+pub struct Divergence {
+    pub path: PathBuf,
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}:", self.path.display())?;
+        for problem in &self.problems {
+            writeln!(f, "    {problem}")?;
+        }
+
+        Ok(())
+    }
+}
+
+// Runs a single test, returning how it diverged from the reference, if it did.
+pub fn run_test(interpreter: &Path, path: &Path) -> Option<Divergence> {
+    let source = fs::read_to_string(path).unwrap();
+    let expectation = Expectation::parse(&source);
+    let result = Command::new(interpreter).arg(path).output().unwrap();
+    let stdout = String::from_utf8_lossy(&result.stdout);
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    let mut problems = Vec::new();
+
+    let output: Vec<&str> = stdout.lines().collect();
+    if output != expectation.output {
+        problems.push(format!(
+            "Expected output {:?} but got {:?}.",
+            expectation.output, output
+        ));
+    }
+
+    // Warnings and the source snippets under each message aren't part of
+    // the reference results.
+    let errors: Vec<String> = stderr
+        .lines()
+        .filter(|line| !line.contains("] Warning: ") && !is_snippet(line))
+        .map(without_column)
+        .collect();
+    let errors: Vec<&str> = errors.iter().map(String::as_str).collect();
+    if let Some(runtime_error) = &expectation.runtime_error {
+        if errors.first() != Some(&runtime_error.as_str()) {
+            problems.push(format!(
+                "Expected runtime error {runtime_error:?} but got {errors:?}."
+            ));
+        }
+    } else if !same_errors(&errors, &expectation.compile_errors) {
+        problems.push(format!(
+            "Expected errors {:?} but got {:?}.",
+            expectation.compile_errors, errors
+        ));
+    }
+
+    let exit_code = result.status.code().unwrap_or(-1);
+    if exit_code != expectation.exit_code() {
+        problems.push(format!(
+            "Expected exit code {} but got {exit_code}.",
+            expectation.exit_code()
+        ));
+    }
+
+    if problems.is_empty() {
+        None
+    } else {
+        Some(Divergence {
+            path: path.to_owned(),
+            problems,
+        })
+    }
+}
+
+// Runs `source` as a script with the given command line options.
+pub fn run_script(interpreter: &Path, options: &[&str], source: &str) -> Output {
+    static SCRIPTS: AtomicUsize = AtomicUsize::new(0);
+    let script = env::temp_dir().join(format!(
+        "rustlox-test-{}-{}.lox",
+        process::id(),
+        SCRIPTS.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&script, source).unwrap();
+
+    let output = Command::new(interpreter)
+        .args(options)
+        .arg(&script)
+        .output()
+        .unwrap();
+    fs::remove_file(&script).ok();
+    output
+}
+
+// Runs `source` as a script with the given command line options, returning
+// the exit code, the output and the messages in standard error.
+pub fn run(options: &[&str], source: &str) -> (Option<i32>, String, String) {
+    let output = run_script(Path::new(env!("CARGO_BIN_EXE_rustlox")), options, source);
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+        without_snippets(output.stderr),
+    )
+}
+
+// Runs `source` as a script read from standard input, with the given command
+// line options before the "-" path.
+pub fn run_stdin(interpreter: &Path, options: &[&str], source: &str) -> Output {
+    let mut child = Command::new(interpreter)
+        .args(options)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+// Starts the REPL with the given command line options and types `input`
+// into it.
+pub fn run_repl(interpreter: &Path, options: &[&str], input: &str) -> Output {
+    let mut child = Command::new(interpreter)
+        .args(options)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+// Lists the tests under a directory, skipping the ones in `excluded`
+// subdirectories.
+pub fn collect_tests(dir: &Path, excluded: &[&str], tests: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            let name = path.file_name().unwrap().to_string_lossy();
+            if !excluded.contains(&name.as_ref()) {
+                collect_tests(&path, excluded, tests);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "lox") {
+            tests.push(path);
+        }
+    }
+}
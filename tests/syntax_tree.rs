@@ -0,0 +1,94 @@
+use rustlox::{Ast, AstVisitor, ErrorCode, Expr, ExprId, RustLox, Stmt, TokenType};
+
+// Lists the names of the functions called, as a downstream analyzer might.
+struct Calls<'a> {
+    ast: &'a Ast,
+    names: Vec<String>,
+}
+
+impl AstVisitor<(), ()> for Calls<'_> {
+    fn visit_expr(&mut self, expr: ExprId) {
+        match &self.ast[expr] {
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                if let Expr::Variable(name) = &self.ast[*callee] {
+                    self.names.push(name.lexeme.to_string());
+                }
+                for argument in arguments {
+                    self.visit_expr(*argument);
+                }
+            }
+            Expr::Binary { left, right, .. } => {
+                self.visit_expr(*left);
+                self.visit_expr(*right);
+            }
+            _ => (),
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expression { expression, .. } | Stmt::Print { expression, .. } => {
+                self.visit_expr(*expression)
+            }
+            Stmt::Function { definition, .. } => {
+                if let Expr::Lambda { body, .. } = &self.ast[*definition] {
+                    for stmt in body {
+                        self.visit_stmt(stmt);
+                    }
+                }
+            }
+            Stmt::Return {
+                value: Some(value), ..
+            } => self.visit_expr(*value),
+            _ => (),
+        }
+    }
+}
+
+#[test]
+fn visits_a_parsed_program() {
+    let ast = RustLox::new()
+        .parse("fun twice(x) { return double(x) + 0; }\nprint twice(clock());")
+        .ok()
+        .unwrap();
+
+    let mut calls = Calls {
+        ast: &ast,
+        names: Vec::new(),
+    };
+    for stmt in &ast.statements {
+        calls.visit_stmt(stmt);
+    }
+
+    assert_eq!(calls.names, ["double", "twice", "clock"]);
+}
+
+#[test]
+fn exposes_tokens_and_function_source() {
+    let ast = RustLox::new()
+        .parse("fun add(a, b) {\n  return a + b;\n}")
+        .ok()
+        .unwrap();
+
+    let Stmt::Function { name, definition } = &ast.statements[0] else {
+        panic!("Expected a function declaration.");
+    };
+    assert_eq!(name.token_type, TokenType::Identifier);
+    assert_eq!((&*name.lexeme, name.line, name.column), ("add", 1, 5));
+    assert_eq!(
+        ast.function_source(*definition),
+        Some("(a, b) {\n  return a + b;\n}")
+    );
+}
+
+#[test]
+fn parse_returns_syntax_errors() {
+    let diagnostics = RustLox::new().parse("print 1").err().unwrap();
+    assert_eq!(
+        diagnostics[0].message,
+        "Error at end: Expect ';' after value"
+    );
+    assert_eq!(diagnostics[0].code, Some(ErrorCode::MissingSemicolon));
+}
@@ -0,0 +1,23 @@
+#![cfg(feature = "wasm")]
+
+use rustlox::run;
+
+#[test]
+fn returns_output_and_diagnostics() {
+    let result = run("print 1 + 2;\n{ var unused; }");
+    assert!(result.ok());
+    assert_eq!(result.output(), "3\n");
+    assert_eq!(result.diagnostics().len(), 1);
+
+    let result = run("print \"before\";\nprint -nil;");
+    assert!(!result.ok());
+    assert_eq!(result.output(), "before\n");
+    assert!(result.diagnostics()[0].starts_with("[line 2:"));
+    assert!(result.diagnostics()[0].contains("Operands must be numbers."));
+}
+
+#[test]
+fn leaves_out_natives_browsers_lack() {
+    let result = run("clock();");
+    assert!(result.diagnostics()[0].contains("Undefined variable 'clock'."));
+}